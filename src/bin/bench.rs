@@ -0,0 +1,212 @@
+//! mini-redis 吞吐量基准测试工具。
+//!
+//! 打开多个并发连接，每个连接在一个独立的任务中使用异步 `Client` 循环发送 `SET`、`GET` 和 `PUBLISH` 的混合命令，持续指定的时间。结束后报告每秒操作数和延迟百分位数。
+//!
+//! 每个连接一次只有一个请求在进行中，因此吞吐量受连接数和往返延迟的限制。
+
+use mini_redis::{clients::Client, DEFAULT_PORT};
+
+use bytes::Bytes;
+use clap::Parser;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "mini-redis-bench",
+    version,
+    author,
+    about = "测量 mini-redis 服务器的吞吐量和延迟"
+)]
+struct Cli {
+    #[clap(name = "hostname", long, default_value = "127.0.0.1")]
+    host: String,
+
+    #[clap(long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+
+    /// 并发连接数
+    #[clap(long, default_value_t = 50)]
+    connections: usize,
+
+    /// 运行时间（秒）
+    #[clap(long, default_value_t = 10)]
+    duration: u64,
+
+    /// `SET` 命令所占的百分比
+    #[clap(long, default_value_t = 50)]
+    set_percent: u32,
+
+    /// `PUBLISH` 命令所占的百分比，其余为 `GET`
+    #[clap(long, default_value_t = 0)]
+    publish_percent: u32,
+
+    /// 使用的不同键的数量
+    #[clap(long, default_value_t = 10_000)]
+    keyspace: u64,
+
+    /// `SET` 和 `PUBLISH` 发送的值的字节数
+    #[clap(long, default_value_t = 64)]
+    value_size: usize,
+}
+
+/// 基准测试发送的命令。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Set,
+    Get,
+    Publish,
+}
+
+/// 一个连接或所有连接的测量结果。
+#[derive(Debug, Default)]
+struct Report {
+    /// 每个成功命令的延迟（微秒），按命令区分。
+    set: Vec<u64>,
+    get: Vec<u64>,
+    publish: Vec<u64>,
+
+    /// 失败的命令数。
+    errors: u64,
+}
+
+#[tokio::main]
+async fn main() -> mini_redis::Result<()> {
+    let cli = Cli::parse();
+
+    if cli.set_percent + cli.publish_percent > 100 {
+        return Err("--set-percent 和 --publish-percent 之和不能超过 100".into());
+    }
+    if cli.connections == 0 || cli.keyspace == 0 {
+        return Err("--connections 和 --keyspace 必须大于零".into());
+    }
+
+    let addr = format!("{}:{}", cli.host, cli.port);
+    let value = Bytes::from(vec![b'x'; cli.value_size]);
+
+    // 在开始计时之前建立所有连接，连接建立的时间不计入结果。
+    let mut clients = Vec::with_capacity(cli.connections);
+    for _ in 0..cli.connections {
+        clients.push(Client::connect(&addr).await?);
+    }
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(cli.duration);
+
+    let tasks: Vec<_> = clients
+        .into_iter()
+        .map(|client| {
+            let value = value.clone();
+            let (set_percent, publish_percent, keyspace) =
+                (cli.set_percent, cli.publish_percent, cli.keyspace);
+
+            tokio::spawn(async move {
+                let mix = move |roll: u32| {
+                    if roll < set_percent {
+                        Op::Set
+                    } else if roll < set_percent + publish_percent {
+                        Op::Publish
+                    } else {
+                        Op::Get
+                    }
+                };
+
+                run(client, deadline, mix, keyspace, value).await
+            })
+        })
+        .collect();
+
+    let mut report = Report::default();
+    for task in tasks {
+        let partial = task.await?;
+        report.set.extend(partial.set);
+        report.get.extend(partial.get);
+        report.publish.extend(partial.publish);
+        report.errors += partial.errors;
+    }
+
+    let elapsed = started.elapsed().as_secs_f64();
+    let total = report.set.len() + report.get.len() + report.publish.len();
+
+    println!(
+        "{} 个连接，{:.1} 秒，{} 个命令，{} 个错误",
+        cli.connections, elapsed, total, report.errors
+    );
+    println!("吞吐量：{:.0} ops/sec", total as f64 / elapsed);
+    println!();
+    println!(
+        "{:<8} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "command", "count", "p50 (µs)", "p90 (µs)", "p99 (µs)", "max (µs)"
+    );
+
+    for (name, latencies) in [
+        ("SET", &mut report.set),
+        ("GET", &mut report.get),
+        ("PUBLISH", &mut report.publish),
+    ] {
+        if latencies.is_empty() {
+            continue;
+        }
+
+        latencies.sort_unstable();
+        println!(
+            "{:<8} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            name,
+            latencies.len(),
+            percentile(latencies, 50.0),
+            percentile(latencies, 90.0),
+            percentile(latencies, 99.0),
+            latencies[latencies.len() - 1],
+        );
+    }
+
+    Ok(())
+}
+
+/// 在一个连接上发送命令直到 `deadline`，并记录每个命令的延迟。
+///
+/// `mix` 将 `0..100` 中的随机数映射到要发送的命令。
+async fn run(
+    mut client: Client,
+    deadline: Instant,
+    mix: impl Fn(u32) -> Op,
+    keyspace: u64,
+    value: Bytes,
+) -> Report {
+    let mut rng = SmallRng::from_entropy();
+    let mut report = Report::default();
+
+    while Instant::now() < deadline {
+        let op = mix(rng.gen_range(0..100));
+        let key = format!("key:{}", rng.gen_range(0..keyspace));
+
+        let start = Instant::now();
+        let res = match op {
+            Op::Set => client.set(&key, value.clone()).await,
+            Op::Get => client.get(&key).await.map(drop),
+            Op::Publish => client.publish(&key, value.clone()).await.map(drop),
+        };
+        let latency = start.elapsed().as_micros() as u64;
+
+        match (res, op) {
+            (Ok(()), Op::Set) => report.set.push(latency),
+            (Ok(()), Op::Get) => report.get.push(latency),
+            (Ok(()), Op::Publish) => report.publish.push(latency),
+            // 连接出错后无法继续使用，停止此连接。
+            (Err(_), _) => {
+                report.errors += 1;
+                break;
+            }
+        }
+    }
+
+    report
+}
+
+/// 返回已排序的 `sorted` 中的第 `p` 百分位数（最近秩法）。
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.saturating_sub(1).min(sorted.len() - 1)]
+}
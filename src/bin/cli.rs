@@ -1,11 +1,15 @@
-use mini_redis::{clients::Client, DEFAULT_PORT};
+use mini_redis::{
+    clients::{Client, Subscriber},
+    DEFAULT_PORT,
+};
 
 use bytes::Bytes;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::convert::Infallible;
 use std::num::ParseIntError;
 use std::str;
 use std::time::Duration;
+use tokio::io::{self, AsyncBufReadExt, BufReader};
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -23,6 +27,20 @@ struct Cli {
 
     #[clap(long, default_value_t = DEFAULT_PORT)]
     port: u16,
+
+    /// 结果的输出格式
+    #[clap(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+}
+
+/// 结果的输出格式。
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// 便于阅读的文本，例如 `"value"` 或 `(nil)`
+    Text,
+
+    /// 每个结果一行 JSON，便于脚本处理
+    Json,
 }
 
 #[derive(Subcommand, Debug)]
@@ -50,6 +68,15 @@ enum Command {
         #[clap(value_parser = duration_from_ms_str)]
         expires: Option<Duration>,
     },
+    /// 获取键的值；如果键不存在，则先将其设置为默认值。
+    GetOrSet {
+        /// 要获取的键的名称
+        key: String,
+
+        /// 键不存在时存储并返回的值
+        #[clap(value_parser = bytes_from_str)]
+        default: Bytes,
+    },
     /// 发布者将消息发送到特定通道。
     Publish {
         /// 通道的名称
@@ -91,22 +118,11 @@ async fn main() -> mini_redis::Result<()> {
     match cli.command {
         Command::Ping { msg } => {
             let value = client.ping(msg).await?;
-            if let Ok(string) = str::from_utf8(&value) {
-                println!("\"{}\"", string);
-            } else {
-                println!("{:?}", value);
-            }
+            print_value(cli.format, Some(&value));
         }
         Command::Get { key } => {
-            if let Some(value) = client.get(&key).await? {
-                if let Ok(string) = str::from_utf8(&value) {
-                    println!("\"{}\"", string);
-                } else {
-                    println!("{:?}", value);
-                }
-            } else {
-                println!("(nil)");
-            }
+            let value = client.get(&key).await?;
+            print_value(cli.format, value.as_ref());
         }
         Command::Set {
             key,
@@ -114,7 +130,7 @@ async fn main() -> mini_redis::Result<()> {
             expires: None,
         } => {
             client.set(&key, value).await?;
-            println!("OK");
+            print_ok(cli.format);
         }
         Command::Set {
             key,
@@ -122,11 +138,18 @@ async fn main() -> mini_redis::Result<()> {
             expires: Some(expires),
         } => {
             client.set_expires(&key, value, expires).await?;
-            println!("OK");
+            print_ok(cli.format);
+        }
+        Command::GetOrSet { key, default } => {
+            let value = client.get_or_set(&key, default).await?;
+            print_value(cli.format, Some(&value));
         }
         Command::Publish { channel, message } => {
-            client.publish(&channel, message).await?;
-            println!("Publish OK");
+            let receivers = client.publish(&channel, message).await?;
+            match cli.format {
+                Format::Text => println!("Publish OK"),
+                Format::Json => println!("{}", receivers),
+            }
         }
         Command::Subscribe { channels } => {
             if channels.is_empty() {
@@ -134,12 +157,40 @@ async fn main() -> mini_redis::Result<()> {
             }
             let mut subscriber = client.subscribe(channels).await?;
 
-            // 等待通道上的消息
-            while let Some(msg) = subscriber.next_message().await? {
-                println!(
-                    "从通道收到消息：{}；消息 = {:?}",
-                    msg.channel, msg.content
-                );
+            // 提示和订阅状态写入 stderr，以便 stdout 只包含消息。
+            eprintln!("输入 `subscribe <通道>...`、`unsubscribe [通道...]` 或 `quit`");
+
+            // 在等待通道上的消息的同时从 stdin 读取命令。两者都可以安全地在 `select!` 中取消：未完成的帧或行会保留在各自的缓冲区中。
+            let mut lines = BufReader::new(io::stdin()).lines();
+            let mut stdin_open = true;
+
+            loop {
+                tokio::select! {
+                    msg = subscriber.next_message() => match msg? {
+                        Some(msg) => match cli.format {
+                            Format::Text => println!(
+                                "从通道收到消息：{}；消息 = {:?}",
+                                msg.channel, msg.content
+                            ),
+                            Format::Json => println!(
+                                "{{\"channel\":{},\"message\":{}}}",
+                                json_string(&msg.channel),
+                                json_bytes(&msg.content)
+                            ),
+                        },
+                        // 服务器关闭了连接
+                        None => break,
+                    },
+                    line = lines.next_line(), if stdin_open => match line? {
+                        Some(line) => {
+                            if !subscribe_prompt(&mut subscriber, &line).await {
+                                break;
+                            }
+                        }
+                        // stdin 已关闭，例如输入来自 `/dev/null`。继续接收消息。
+                        None => stdin_open = false,
+                    },
+                }
             }
         }
     }
@@ -147,6 +198,118 @@ async fn main() -> mini_redis::Result<()> {
     Ok(())
 }
 
+/// 执行在订阅模式中输入的一行命令。
+///
+/// 如果应该退出，则返回 `false`。命令的错误会被打印出来，订阅继续进行。
+async fn subscribe_prompt(subscriber: &mut Subscriber, line: &str) -> bool {
+    let mut words = line.split_whitespace();
+
+    let command = match words.next() {
+        Some(command) => command.to_lowercase(),
+        // 忽略空行
+        None => return true,
+    };
+    let channels: Vec<String> = words.map(str::to_string).collect();
+
+    let res = match &command[..] {
+        "quit" | "exit" => return false,
+        "subscribe" if !channels.is_empty() => subscriber.subscribe(&channels).await,
+        "unsubscribe" => subscriber.unsubscribe(&channels).await,
+        _ => {
+            eprintln!("未知命令：{}", line.trim());
+            return true;
+        }
+    };
+
+    match res {
+        Ok(()) => eprintln!("已订阅：{:?}", subscriber.get_subscribed()),
+        Err(err) => eprintln!("错误：{}", err),
+    }
+
+    true
+}
+
+/// 以 `format` 格式打印一个值。`None` 表示值不存在。
+fn print_value(format: Format, value: Option<&Bytes>) {
+    match (format, value) {
+        (Format::Text, Some(value)) => {
+            if let Ok(string) = str::from_utf8(value) {
+                println!("\"{}\"", string);
+            } else {
+                println!("{:?}", value);
+            }
+        }
+        (Format::Text, None) => println!("(nil)"),
+        (Format::Json, Some(value)) => println!("{}", json_bytes(value)),
+        (Format::Json, None) => println!("null"),
+    }
+}
+
+/// 以 `format` 格式打印成功的确认。
+fn print_ok(format: Format) {
+    match format {
+        Format::Text => println!("OK"),
+        Format::Json => println!("\"OK\""),
+    }
+}
+
+/// 将值编码为 JSON。
+///
+/// 有效的 UTF-8 编码为 JSON 字符串。否则编码为 `{"type":"base64","data":"..."}`，以便输出始终是有效的 JSON。
+fn json_bytes(value: &[u8]) -> String {
+    match str::from_utf8(value) {
+        Ok(string) => json_string(string),
+        Err(_) => format!("{{\"type\":\"base64\",\"data\":\"{}\"}}", base64(value)),
+    }
+}
+
+/// 将字符串编码为带引号的 JSON 字符串，并转义引号、反斜杠和控制字符。
+fn json_string(string: &str) -> String {
+    let mut out = String::with_capacity(string.len() + 2);
+    out.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+/// 使用标准字母表和填充对 `data` 进行 base64 编码。
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
 fn duration_from_ms_str(src: &str) -> Result<Duration, ParseIntError> {
     let ms = src.parse::<u64>()?;
     Ok(Duration::from_millis(ms))
@@ -155,3 +318,45 @@ fn duration_from_ms_str(src: &str) -> Result<Duration, ParseIntError> {
 fn bytes_from_str(src: &str) -> Result<Bytes, Infallible> {
     Ok(Bytes::from(src.to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test vectors from RFC 4648, covering every amount of padding.
+    #[test]
+    fn base64_rfc4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+
+        for (data, encoded) in vectors {
+            assert_eq!(base64(data.as_bytes()), encoded, "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn json_bytes_non_utf8() {
+        assert_eq!(
+            json_bytes(&[0xff, 0xfe, 0x00]),
+            r#"{"type":"base64","data":"//4A"}"#
+        );
+        assert_eq!(json_bytes(b"hello"), r#""hello""#);
+    }
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(json_string(r"a\b"), r#""a\\b""#);
+        assert_eq!(json_string("a\nb\rc\td"), r#""a\nb\rc\td""#);
+        assert_eq!(json_string("\u{0}\u{1f}"), r#""\u0000\u001f""#);
+        // Characters from 0x20 up, including non-ASCII, are kept as they are.
+        assert_eq!(json_string(" ~\u{7f}é"), "\" ~\u{7f}é\"");
+    }
+}
@@ -4,9 +4,12 @@
 //!
 //! 使用 `clap` crate 进行参数解析。
 
-use mini_redis::{server, DEFAULT_PORT};
+use mini_redis::server::{self, EvictionPolicy, ShutdownReason};
+use mini_redis::DEFAULT_PORT;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use std::convert::TryFrom;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::signal;
 
@@ -37,11 +40,82 @@ pub async fn main() -> mini_redis::Result<()> {
     // ?是一个用于传播错误的快捷方式，如果发生错误，将立即返回Err。
     let listener = TcpListener::bind(&format!("127.0.0.1:{}", port)).await?;
 
-    server::run(listener, signal::ctrl_c()).await;
+    let mut config = server::Config::default();
+    if let Some(capacity) = cli.pubsub_capacity {
+        if capacity == 0 || capacity > server::MAX_PUBSUB_CAPACITY {
+            return Err(format!(
+                "--pubsub-capacity must be between 1 and {}",
+                server::MAX_PUBSUB_CAPACITY
+            )
+            .into());
+        }
+        config.pubsub_capacity = capacity;
+    }
+    config.read_only = cli.read_only;
+    config.maxmemory = cli.maxmemory;
+    config.tcp_nodelay = !cli.no_tcp_nodelay;
+    config.tcp_keepalive = match cli.tcp_keepalive {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    };
+    // 与 Redis 的 `slowlog-log-slower-than` 一样，负数禁用慢日志。
+    config.slowlog_log_slower_than = u64::try_from(cli.slowlog_log_slower_than)
+        .ok()
+        .map(Duration::from_micros);
+    config.slowlog_max_len = cli.slowlog_max_len;
+    config.output_buffer_limit = cli.client_output_buffer_limit;
+    config.maxmemory_policy = match cli.maxmemory_policy {
+        Policy::Noeviction => EvictionPolicy::NoEviction,
+        Policy::AllkeysLru => EvictionPolicy::AllKeysLru,
+        Policy::AllkeysLfu => EvictionPolicy::AllKeysLfu,
+    };
+
+    // 如果指定了指标端口，则同时在该端口上以 Prometheus 文本格式提供统计信息。
+    #[cfg(feature = "metrics")]
+    if let Some(metrics_port) = cli.metrics_port {
+        let metrics = TcpListener::bind(&format!("127.0.0.1:{}", metrics_port)).await?;
+        server::run_with_metrics(listener, metrics, config, shutdown_signal()).await;
+        return Ok(());
+    }
+
+    server::run_with_config(listener, config, shutdown_signal()).await;
 
     Ok(())
 }
 
+/// 等待触发优雅关闭的信号并返回是哪一个。
+///
+/// 在 Unix 上同时监听 Ctrl-C 和 SIGTERM，在其他平台上只监听 Ctrl-C。如果无法安装信号处理程序，则永远不会因该信号而关闭，但会记录错误。
+async fn shutdown_signal() -> ShutdownReason {
+    let ctrl_c = async {
+        if let Err(err) = signal::ctrl_c().await {
+            tracing::error!(cause = %err, "failed to listen for ctrl-c");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(err) => {
+                tracing::error!(cause = %err, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => ShutdownReason::CtrlC,
+        _ = terminate => ShutdownReason::Terminate,
+    }
+}
+
 /// 这里使用了derive宏，它会自动为结构体实现一些 trait，其中包括Parser和Debug。Parser trait 是由 clap 提供的，用于生成命令行解析器。
 #[derive(Parser, Debug)]
 /// 这是clap宏的配置部分，用于配置命令行解析器的元数据。具体来说，设置了应用程序的名称（name）、版本号（version）、作者（author）和简介（about）。
@@ -51,6 +125,60 @@ struct Cli {
     // 这里使用了#[clap(long)]属性，它告诉clap库在解析命令行参数时要考虑port字段，并且使用--port这样的长格式命令行参数。
     #[clap(long)]
     port: Option<u16>,
+
+    // 每个发布/订阅通道为慢速订阅者保留的消息数。默认为 1024。
+    #[clap(long)]
+    pubsub_capacity: Option<usize>,
+
+    // 拒绝所有写命令，只允许读命令和发布/订阅。
+    #[clap(long)]
+    read_only: bool,
+
+    // 估算的内存使用量的上限（字节），0 表示没有限制。
+    #[clap(long, default_value_t = 0)]
+    maxmemory: usize,
+
+    // 超过 `--maxmemory` 时选择要删除的键的策略。
+    #[clap(long, value_enum, default_value_t = Policy::Noeviction)]
+    maxmemory_policy: Policy,
+
+    // 连接空闲多少秒后开始发送 TCP keepalive 探测，0 表示不启用。
+    #[clap(long, default_value_t = 300)]
+    tcp_keepalive: u64,
+
+    // 不在接受的套接字上设置 `TCP_NODELAY`。
+    #[clap(long)]
+    no_tcp_nodelay: bool,
+
+    // 执行时间达到多少微秒的命令被记录到慢日志中，负数表示禁用慢日志。
+    #[clap(long, default_value_t = 10_000, allow_hyphen_values = true)]
+    slowlog_log_slower_than: i64,
+
+    // 慢日志保留的最大记录数。
+    #[clap(long, default_value_t = 128)]
+    slowlog_max_len: usize,
+
+    // 每个连接待发送的回复的上限（字节），超过时关闭连接，0 表示没有限制。
+    #[clap(long, default_value_t = 0)]
+    client_output_buffer_limit: usize,
+
+    // 提供 `GET /metrics` 的端口。未指定时不提供指标。
+    #[cfg(feature = "metrics")]
+    #[clap(long)]
+    metrics_port: Option<u16>,
+}
+
+/// `--maxmemory-policy` 的取值，名称与 Redis 的 `maxmemory-policy` 配置相同。
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Policy {
+    /// 不删除键，超过限制时拒绝写命令
+    Noeviction,
+
+    /// 删除最久未被访问的键
+    AllkeysLru,
+
+    /// 删除访问频率最低的键
+    AllkeysLfu,
 }
 
 #[cfg(not(feature = "otel"))]
@@ -1,6 +1,18 @@
 //! Minimal blocking Redis client implementation
 //!
 //! Provides a blocking connect and methods for issuing the supported commands.
+//!
+//! `BlockingClient` mirrors the asynchronous [`Client`](crate::clients::Client)
+//! method for method: each method runs the corresponding `Client` future to
+//! completion on a private `current_thread` runtime. Every method that talks to
+//! the server therefore blocks the current thread until the response is
+//! received. When a command is added to `Client`, the matching method should
+//! be added here as well.
+//!
+//! These types must not be used from within an asynchronous context, as
+//! blocking inside a Tokio runtime panics.
+
+use crate::Frame;
 
 use bytes::Bytes;
 use std::time::Duration;
@@ -80,10 +92,39 @@ impl BlockingClient {
         Ok(BlockingClient { inner, rt })
     }
 
+    /// Ping to the server.
+    ///
+    /// Returns PONG if no argument is provided, otherwise
+    /// return a copy of the argument as a bulk.
+    ///
+    /// This command is often used to test if a connection
+    /// is still alive, or to measure latency.
+    ///
+    /// Blocks the current thread until the response is received.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    /// ```no_run
+    /// use mini_redis::clients::BlockingClient;
+    ///
+    /// fn main() {
+    ///     let mut client = BlockingClient::connect("localhost:6379").unwrap();
+    ///
+    ///     let pong = client.ping(None).unwrap();
+    ///     assert_eq!(b"PONG", &pong[..]);
+    /// }
+    /// ```
+    pub fn ping(&mut self, msg: Option<Bytes>) -> crate::Result<Bytes> {
+        self.rt.block_on(self.inner.ping(msg))
+    }
+
     /// Get the value of key.
     ///
     /// If the key does not exist the special value `None` is returned.
     ///
+    /// Blocks the current thread until the response is received.
+    ///
     /// # Examples
     ///
     /// Demonstrates basic usage.
@@ -110,6 +151,8 @@ impl BlockingClient {
     /// If key already holds a value, it is overwritten. Any previous time to
     /// live associated with the key is discarded on successful SET operation.
     ///
+    /// Blocks the current thread until the response is received.
+    ///
     /// # Examples
     ///
     /// Demonstrates basic usage.
@@ -141,6 +184,8 @@ impl BlockingClient {
     /// If key already holds a value, it is overwritten. Any previous time to
     /// live associated with the key is discarded on a successful SET operation.
     ///
+    /// Blocks the current thread until the response is received.
+    ///
     /// # Examples
     ///
     /// Demonstrates basic usage. This example is not **guaranteed** to always
@@ -180,12 +225,39 @@ impl BlockingClient {
             .block_on(self.inner.set_expires(key, value, expiration))
     }
 
+    /// Get the value of key, setting it to `default` if it does not exist.
+    ///
+    /// Returns the existing value, or `default` if it has just been stored.
+    /// The server performs the lookup and the insert atomically.
+    ///
+    /// Blocks the current thread until the response is received.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::BlockingClient;
+    ///
+    /// fn main() {
+    ///     let mut client = BlockingClient::connect("localhost:6379").unwrap();
+    ///
+    ///     let val = client.get_or_set("foo", "bar".into()).unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    pub fn get_or_set(&mut self, key: &str, default: Bytes) -> crate::Result<Bytes> {
+        self.rt.block_on(self.inner.get_or_set(key, default))
+    }
+
     /// Posts `message` to the given `channel`.
     ///
     /// Returns the number of subscribers currently listening on the channel.
     /// There is no guarantee that these subscribers receive the message as they
     /// may disconnect at any time.
     ///
+    /// Blocks the current thread until the response is received.
+    ///
     /// # Examples
     ///
     /// Demonstrates basic usage.
@@ -204,6 +276,60 @@ impl BlockingClient {
         self.rt.block_on(self.inner.publish(channel, message))
     }
 
+    /// Sends an arbitrary command and returns the raw response frame.
+    ///
+    /// `args` holds the command name followed by its arguments, each sent as
+    /// a bulk string. An error response from the server is returned as `Err`.
+    ///
+    /// Blocks the current thread until the response is received.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::BlockingClient;
+    ///
+    /// fn main() {
+    ///     let mut client = BlockingClient::connect("localhost:6379").unwrap();
+    ///
+    ///     let args = vec!["INCR".into(), "counter".into()];
+    ///     let val = client.execute(args).unwrap();
+    ///     println!("Got = {}", val);
+    /// }
+    /// ```
+    pub fn execute(&mut self, args: Vec<Bytes>) -> crate::Result<Frame> {
+        self.rt.block_on(self.inner.execute(args))
+    }
+
+    /// Returns which of `args` are keys when sent as arguments of the command
+    /// `name`.
+    ///
+    /// The returned positions are indices into `args`. The server's `COMMAND`
+    /// table is fetched on the first call and cached by the client.
+    ///
+    /// Blocks the current thread until the response is received, if the
+    /// table has not been fetched yet.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::BlockingClient;
+    ///
+    /// fn main() {
+    ///     let mut client = BlockingClient::connect("localhost:6379").unwrap();
+    ///
+    ///     let args = vec!["foo".into(), "bar".into()];
+    ///     let keys = client.key_positions("set", &args).unwrap();
+    ///     assert_eq!(keys, vec![0]);
+    /// }
+    /// ```
+    pub fn key_positions(&mut self, name: &str, args: &[Bytes]) -> crate::Result<Vec<usize>> {
+        self.rt.block_on(self.inner.key_positions(name, args))
+    }
+
     /// Subscribes the client to the specified channels.
     ///
     /// Once a client issues a subscribe command, it may no longer issue any
@@ -212,6 +338,8 @@ impl BlockingClient {
     ///
     /// The `BlockingSubscriber` value is used to receive messages as well as
     /// manage the list of channels the client is subscribed to.
+    ///
+    /// Blocks the current thread until the server confirms every subscription.
     pub fn subscribe(self, channels: Vec<String>) -> crate::Result<BlockingSubscriber> {
         let subscriber = self.rt.block_on(self.inner.subscribe(channels))?;
         Ok(BlockingSubscriber {
@@ -223,6 +351,8 @@ impl BlockingClient {
 
 impl BlockingSubscriber {
     /// Returns the set of channels currently subscribed to.
+    ///
+    /// This does not block.
     pub fn get_subscribed(&self) -> &[String] {
         self.inner.get_subscribed()
     }
@@ -231,12 +361,17 @@ impl BlockingSubscriber {
     /// necessary.
     ///
     /// `None` indicates the subscription has been terminated.
+    ///
+    /// Blocks the current thread until a message is received.
     pub fn next_message(&mut self) -> crate::Result<Option<Message>> {
         self.rt.block_on(self.inner.next_message())
     }
 
     /// Convert the subscriber into an `Iterator` yielding new messages published
     /// on subscribed channels.
+    ///
+    /// Each call to `next` on the iterator blocks the current thread until a
+    /// message is received.
     pub fn into_iter(self) -> impl Iterator<Item = crate::Result<Message>> {
         SubscriberIterator {
             inner: self.inner,
@@ -245,11 +380,16 @@ impl BlockingSubscriber {
     }
 
     /// Subscribe to a list of new channels
+    ///
+    /// Blocks the current thread until the server confirms every subscription.
     pub fn subscribe(&mut self, channels: &[String]) -> crate::Result<()> {
         self.rt.block_on(self.inner.subscribe(channels))
     }
 
     /// Unsubscribe to a list of new channels
+    ///
+    /// Blocks the current thread until the server confirms every
+    /// unsubscription.
     pub fn unsubscribe(&mut self, channels: &[String]) -> crate::Result<()> {
         self.rt.block_on(self.inner.unsubscribe(channels))
     }
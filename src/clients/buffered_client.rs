@@ -2,8 +2,35 @@ use crate::clients::Client;
 use crate::Result;
 
 use bytes::Bytes;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio::time::{self, Duration};
+use tracing::debug;
+
+/// Maximum number of in-flight commands used by `BufferedClient::buffer`.
+const DEFAULT_MAX_IN_FLIGHT: usize = 32;
+
+/// Options for [`BufferedClient::with_config`].
+///
+/// Use `BufferConfig::default()` to get the behavior of
+/// [`BufferedClient::buffer`] and override the fields to change.
+#[derive(Debug, Clone)]
+pub struct BufferConfig {
+    /// Maximum number of commands in flight at once, see
+    /// [`BufferedClient::with_max_in_flight`]. Defaults to 32.
+    pub max_in_flight: usize,
+
+    /// Send a `PING` after the connection has been idle for this long.
+    ///
+    /// Any command resets the idle timer. If the `PING` fails or gets no
+    /// response within the same duration, the connection is considered dead:
+    /// [`BufferedClient::is_alive`] returns `false` and further commands fail.
+    /// Defaults to `None`, which never sends a `PING`.
+    pub keep_alive: Option<Duration>,
+}
 
 // Enum used to message pass the requested command from the `BufferedClient` handle
 #[derive(Debug)]
@@ -13,38 +40,131 @@ enum Command {
 }
 
 // Message type sent over the channel to the connection task.
-//
-// `Command` is the command to forward to the connection.
-//
-// `oneshot::Sender` is a channel type that sends a **single** value. It is used
-// here to send the response received from the connection back to the original
-// requester.
-type Message = (Command, oneshot::Sender<Result<Option<Bytes>>>);
+struct Message {
+    // The command to forward to the connection.
+    command: Command,
+
+    // `oneshot::Sender` is a channel type that sends a **single** value. It is
+    // used here to send the response received from the connection back to the
+    // original requester.
+    //
+    // `None` for commands sent with `queue_set`, which do not wait for their
+    // response. Their errors are reported by the next `flush` instead.
+    response: Option<oneshot::Sender<Result<Option<Bytes>>>>,
+
+    // Held until the response has been received, which bounds the number of
+    // in-flight commands.
+    permit: OwnedSemaphorePermit,
+}
+
+// State shared by every `BufferedClient` handle and the connection task.
+struct Shared {
+    // One permit per command that may be in flight at once.
+    in_flight: Arc<Semaphore>,
+
+    // Total number of permits of `in_flight`. Acquiring all of them waits for
+    // every in-flight command to complete.
+    max_in_flight: u32,
+
+    // The first error returned for a command queued with `queue_set` since the
+    // last `flush`.
+    error: Mutex<Option<crate::Error>>,
+
+    // How long the connection may stay idle before a `PING` is sent.
+    keep_alive: Option<Duration>,
+
+    // Cleared by the connection task when a keep-alive `PING` fails.
+    alive: AtomicBool,
+}
+
+impl Default for BufferConfig {
+    fn default() -> BufferConfig {
+        BufferConfig {
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            keep_alive: None,
+        }
+    }
+}
 
 /// Receive commands sent through the channel and forward them to client. The
 /// response is returned back to the caller via a `oneshot`.
-async fn run(mut client: Client, mut rx: Receiver<Message>) {
+async fn run(mut client: Client, mut rx: Receiver<Message>, shared: Arc<Shared>) {
     // Repeatedly pop messages from the channel. A return value of `None`
     // indicates that all `BufferedClient` handles have dropped and there will never be
     // another message sent on the channel.
-    while let Some((cmd, tx)) = rx.recv().await {
+    while let Some(message) = next_message(&mut client, &mut rx, &shared).await {
         // The command is forwarded to the connection
-        let response = match cmd {
+        let response = match message.command {
             Command::Get(key) => client.get(&key).await,
             Command::Set(key, value) => client.set(&key, value).await.map(|_| None),
         };
 
-        // Send the response back to the caller.
-        //
-        // Failing to send the message indicates the `rx` half dropped
-        // before receiving the message. This is a normal runtime event.
-        let _ = tx.send(response);
+        match message.response {
+            // Send the response back to the caller.
+            //
+            // Failing to send the message indicates the `rx` half dropped
+            // before receiving the message. This is a normal runtime event.
+            Some(tx) => {
+                let _ = tx.send(response);
+            }
+            // Nobody is waiting for this response, keep the error for `flush`.
+            // Only the first error is kept.
+            None => {
+                if let Err(err) = response {
+                    shared.error.lock().unwrap().get_or_insert(err);
+                }
+            }
+        }
+
+        // The command is complete, allow another one to be sent. The error, if
+        // any, has been recorded first so a concurrent `flush` sees it.
+        drop(message.permit);
+    }
+}
+
+/// Wait for the next message, sending keep-alive `PING`s while the connection
+/// is idle if enabled.
+///
+/// Returns `None` once all handles have dropped, or when a `PING` fails. The
+/// connection is then marked dead and the task stops, which fails any pending
+/// and future commands.
+async fn next_message(
+    client: &mut Client,
+    rx: &mut Receiver<Message>,
+    shared: &Shared,
+) -> Option<Message> {
+    let interval = match shared.keep_alive {
+        Some(interval) => interval,
+        None => return rx.recv().await,
+    };
+
+    loop {
+        // Receiving a message restarts the timeout, so only an idle
+        // connection is pinged.
+        if let Ok(message) = time::timeout(interval, rx.recv()).await {
+            return message;
+        }
+
+        match time::timeout(interval, client.ping(None)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => {
+                debug!(cause = ?err, "keep-alive ping failed");
+                shared.alive.store(false, Ordering::Relaxed);
+                return None;
+            }
+            Err(_) => {
+                debug!("keep-alive ping timed out");
+                shared.alive.store(false, Ordering::Relaxed);
+                return None;
+            }
+        }
     }
 }
 
 #[derive(Clone)]
 pub struct BufferedClient {
     tx: Sender<Message>,
+    shared: Arc<Shared>,
 }
 
 impl BufferedClient {
@@ -64,16 +184,79 @@ impl BufferedClient {
     ///
     /// The returned `BufferedClient` handle may be cloned before passing the new handle to
     /// separate tasks.
+    ///
+    /// At most 32 commands may be in flight at once, see
+    /// [`with_max_in_flight`](BufferedClient::with_max_in_flight).
     pub fn buffer(client: Client) -> BufferedClient {
-        // Setting the message limit to a hard coded value of 32. in a real-app, the
-        // buffer size should be configurable, but we don't need to do that here.
-        let (tx, rx) = channel(32);
+        BufferedClient::with_max_in_flight(client, DEFAULT_MAX_IN_FLIGHT)
+    }
+
+    /// Create a new client request buffer allowing at most `max_in_flight`
+    /// commands to be in flight at once.
+    ///
+    /// A command is in flight from the moment it is accepted by the buffer
+    /// until its response has been received. Once the limit is reached,
+    /// sending another command waits for an earlier one to complete. This
+    /// bounds the memory used by the buffer, however fast commands are sent.
+    /// The limit is shared by every clone of the returned handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_in_flight` is zero or larger than `u32::MAX`.
+    pub fn with_max_in_flight(client: Client, max_in_flight: usize) -> BufferedClient {
+        BufferedClient::with_config(
+            client,
+            BufferConfig {
+                max_in_flight,
+                ..BufferConfig::default()
+            },
+        )
+    }
+
+    /// Create a new client request buffer configured by `config`.
+    ///
+    /// Keep-alive `PING`s are only sent if `config.keep_alive` is set, by the
+    /// task that already manages the connection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config.max_in_flight` is zero or larger than `u32::MAX`.
+    pub fn with_config(client: Client, config: BufferConfig) -> BufferedClient {
+        let BufferConfig {
+            max_in_flight,
+            keep_alive,
+        } = config;
+
+        assert!(max_in_flight > 0, "max_in_flight must be greater than zero");
+        let max_in_flight_permits =
+            u32::try_from(max_in_flight).expect("max_in_flight must fit in a u32");
+
+        let shared = Arc::new(Shared {
+            in_flight: Arc::new(Semaphore::new(max_in_flight)),
+            max_in_flight: max_in_flight_permits,
+            error: Mutex::new(None),
+            keep_alive,
+            alive: AtomicBool::new(true),
+        });
+
+        // The semaphore is what limits the in-flight commands, so the channel
+        // never holds more than `max_in_flight` messages.
+        let (tx, rx) = channel(max_in_flight);
 
         // Spawn a task to process requests for the connection.
-        tokio::spawn(async move { run(client, rx).await });
+        let task_shared = shared.clone();
+        tokio::spawn(async move { run(client, rx, task_shared).await });
 
         // Return the `BufferedClient` handle.
-        BufferedClient { tx }
+        BufferedClient { tx, shared }
+    }
+
+    /// Returns `false` once a keep-alive `PING` has failed.
+    ///
+    /// A dead client fails every command and should be discarded. Without
+    /// keep-alive, this always returns `true`.
+    pub fn is_alive(&self) -> bool {
+        self.shared.alive.load(Ordering::Relaxed)
     }
 
     /// Get the value of a key.
@@ -88,7 +271,7 @@ impl BufferedClient {
         let (tx, rx) = oneshot::channel();
 
         // Send the request
-        self.tx.send((get, tx)).await?;
+        self.send(get, Some(tx)).await?;
 
         // Await the response
         match rx.await {
@@ -109,7 +292,7 @@ impl BufferedClient {
         let (tx, rx) = oneshot::channel();
 
         // Send the request
-        self.tx.send((set, tx)).await?;
+        self.send(set, Some(tx)).await?;
 
         // Await the response
         match rx.await {
@@ -117,4 +300,60 @@ impl BufferedClient {
             Err(err) => Err(err.into()),
         }
     }
+
+    /// Set `key` to hold the given `value` without waiting for the response.
+    ///
+    /// This returns as soon as the command is accepted by the buffer, which
+    /// waits only if the maximum number of commands are already in flight.
+    /// This makes it suitable for bulk inserts. Call [`flush`] to wait for
+    /// the queued commands to complete and learn whether any of them failed.
+    ///
+    /// [`flush`]: BufferedClient::flush
+    pub async fn queue_set(&mut self, key: &str, value: Bytes) -> Result<()> {
+        self.send(Command::Set(key.into(), value), None).await
+    }
+
+    /// Wait until every command accepted by the buffer so far has been written
+    /// to the connection and its response received.
+    ///
+    /// This covers commands sent through every clone of this handle. Returns
+    /// the first error of a command sent with [`queue_set`] since the previous
+    /// call to `flush`, if any.
+    ///
+    /// [`queue_set`]: BufferedClient::queue_set
+    pub async fn flush(&mut self) -> Result<()> {
+        // Commands hold a permit until their response is received, so holding
+        // every permit means nothing is in flight.
+        let _all = self
+            .shared
+            .in_flight
+            .acquire_many(self.shared.max_in_flight)
+            .await?;
+
+        match self.shared.error.lock().unwrap().take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Send `command` to the connection task once a command may be in flight.
+    async fn send(
+        &self,
+        command: Command,
+        response: Option<oneshot::Sender<Result<Option<Bytes>>>>,
+    ) -> Result<()> {
+        let permit = self.shared.in_flight.clone().acquire_owned().await?;
+
+        let message = Message {
+            command,
+            response,
+            permit,
+        };
+
+        if self.tx.send(message).await.is_err() {
+            return Err("connection task has stopped".into());
+        }
+
+        Ok(())
+    }
 }
@@ -2,14 +2,20 @@
 //!
 //! Provides an async connect and methods for issuing the supported commands.
 
-use crate::cmd::{Get, Ping, Publish, Set, Subscribe, Unsubscribe};
+use crate::cmd::{Get, GetOrSet, Ping, Publish, Set, Subscribe, Unsubscribe};
+use crate::connection::DEFAULT_WRITE_BUFFER_CAPACITY;
 use crate::{Connection, Frame};
 
 use async_stream::try_stream;
 use bytes::Bytes;
+use std::collections::HashMap;
+use std::future::Future;
 use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use std::time::Duration;
 use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::time;
 use tokio_stream::Stream;
 use tracing::{debug, instrument};
 
@@ -17,7 +23,8 @@ use tracing::{debug, instrument};
 ///
 /// Backed by a single `TcpStream`, `Client` provides basic network client
 /// functionality (no pooling, retrying, ...). Connections are established using
-/// the [`connect`](fn@connect) function.
+/// the [`connect`](fn@connect) function, or a [`ClientBuilder`] to set options
+/// such as timeouts.
 ///
 /// Requests are issued using the various methods of `Client`.
 pub struct Client {
@@ -29,6 +36,74 @@ pub struct Client {
     /// `Connection` allows the handler to operate at the "frame" level and keep
     /// the byte level protocol parsing details encapsulated in `Connection`.
     connection: Connection,
+
+    /// Key positions of every command supported by the server, keyed by lower
+    /// case command name. Fetched with `COMMAND` on the first call to
+    /// [`key_positions`](Client::key_positions) and kept for the lifetime of
+    /// the client.
+    key_specs: Option<HashMap<String, KeySpec>>,
+
+    /// How long to wait for a response before failing, see
+    /// [`ClientBuilder::read_timeout`].
+    read_timeout: Option<Duration>,
+
+    /// How long to wait for a request to be written before failing, see
+    /// [`ClientBuilder::write_timeout`].
+    write_timeout: Option<Duration>,
+}
+
+/// Options for establishing a [`Client`] connection.
+///
+/// [`Client::connect`] is a shortcut for `ClientBuilder::new(addr).build()`
+/// with every option left to its default.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mini_redis::clients::ClientBuilder;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let client = ClientBuilder::new("localhost:6379")
+///         .read_timeout(Duration::from_secs(1))
+///         .name("worker-1")
+///         .build()
+///         .await
+///         .unwrap();
+/// # drop(client);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    /// Address of the server.
+    addr: String,
+
+    /// See [`ClientBuilder::read_timeout`].
+    read_timeout: Option<Duration>,
+
+    /// See [`ClientBuilder::write_timeout`].
+    write_timeout: Option<Duration>,
+
+    /// See [`ClientBuilder::write_capacity`].
+    write_capacity: usize,
+
+    /// See [`ClientBuilder::name`].
+    name: Option<String>,
+}
+
+/// Positions of the key arguments of a command, as reported by `COMMAND`.
+#[derive(Debug, Clone, Copy)]
+struct KeySpec {
+    /// Position of the first key, or `0` if the command takes no keys.
+    first_key: i64,
+
+    /// Position of the last key. Negative values count back from the end of
+    /// the command.
+    last_key: i64,
+
+    /// Step between key positions.
+    step: i64,
 }
 
 /// A client that has entered pub/sub mode.
@@ -84,7 +159,23 @@ impl Client {
         // perform redis protocol frame parsing.
         let connection = Connection::new(socket);
 
-        Ok(Client { connection })
+        Ok(Client::from_connection(connection))
+    }
+
+    /// Returns a [`ClientBuilder`] to configure a connection to the server
+    /// located at `addr`.
+    pub fn builder(addr: impl ToString) -> ClientBuilder {
+        ClientBuilder::new(addr)
+    }
+
+    /// Wraps an established `connection`, with no timeouts.
+    fn from_connection(connection: Connection) -> Client {
+        Client {
+            connection,
+            key_specs: None,
+            read_timeout: None,
+            write_timeout: None,
+        }
     }
 
     /// Ping to the server.
@@ -113,7 +204,7 @@ impl Client {
     pub async fn ping(&mut self, msg: Option<Bytes>) -> crate::Result<Bytes> {
         let frame = Ping::new(msg).into_frame();
         debug!(request = ?frame);
-        self.connection.write_frame(&frame).await?;
+        self.write_frame(&frame).await?;
 
         match self.read_response().await? {
             Frame::Simple(value) => Ok(value.into()),
@@ -150,7 +241,7 @@ impl Client {
 
         // Write the frame to the socket. This writes the full frame to the
         // socket, waiting if necessary.
-        self.connection.write_frame(&frame).await?;
+        self.write_frame(&frame).await?;
 
         // Wait for the response from the server
         //
@@ -260,7 +351,7 @@ impl Client {
 
         // Write the frame to the socket. This writes the full frame to the
         // socket, waiting if necessary.
-        self.connection.write_frame(&frame).await?;
+        self.write_frame(&frame).await?;
 
         // Wait for the response from the server. On success, the server
         // responds simply with `OK`. Any other response indicates an error.
@@ -270,6 +361,45 @@ impl Client {
         }
     }
 
+    /// Get the value of key, setting it to `default` if it does not exist.
+    ///
+    /// Returns the existing value, or `default` if it has just been stored.
+    /// The server performs the lookup and the insert atomically, so clients
+    /// racing on the same key all get the same value. This is not a Redis
+    /// command, it is specific to `mini-redis`.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let val = client.get_or_set("foo", "bar".into()).await.unwrap();
+    ///     println!("Got = {:?}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn get_or_set(&mut self, key: &str, default: Bytes) -> crate::Result<Bytes> {
+        // Create a `GetOrSet` command for the `key` and convert it to a frame.
+        let frame = GetOrSet::new(key, default).into_frame();
+
+        debug!(request = ?frame);
+
+        // Write the frame to the socket
+        self.write_frame(&frame).await?;
+
+        // The server always responds with the value
+        match self.read_response().await? {
+            Frame::Bulk(value) => Ok(value),
+            frame => Err(frame.to_error()),
+        }
+    }
+
     /// Posts `message` to the given `channel`.
     ///
     /// Returns the number of subscribers currently listening on the channel.
@@ -299,15 +429,164 @@ impl Client {
         debug!(request = ?frame);
 
         // Write the frame to the socket
-        self.connection.write_frame(&frame).await?;
+        self.write_frame(&frame).await?;
 
         // Read the response
         match self.read_response().await? {
-            Frame::Integer(response) => Ok(response),
+            Frame::Integer(response) if response >= 0 => Ok(response as u64),
             frame => Err(frame.to_error()),
         }
     }
 
+    /// Sends an arbitrary command and returns the raw response frame.
+    ///
+    /// `args` holds the command name followed by its arguments, each sent as
+    /// a bulk string. This gives access to commands that have no dedicated
+    /// method. As with the other methods, an error response from the server
+    /// is returned as `Err`.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let args = vec!["INCR".into(), "counter".into()];
+    ///     let val = client.execute(args).await.unwrap();
+    ///     println!("Got = {}", val);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn execute(&mut self, args: Vec<Bytes>) -> crate::Result<Frame> {
+        let mut frame = Frame::array();
+        for arg in args {
+            frame.push_bulk(arg);
+        }
+
+        debug!(request = ?frame);
+
+        // Write the frame to the socket
+        self.write_frame(&frame).await?;
+
+        // Any successful response is returned as is
+        self.read_response().await
+    }
+
+    /// Returns which of `args` are keys when sent as arguments of the command
+    /// `name`.
+    ///
+    /// The returned positions are indices into `args`, which must not include
+    /// the command name. This is useful to route a command to the shard
+    /// owning its keys.
+    ///
+    /// The key positions are looked up in the server's `COMMAND` table. The
+    /// table is fetched on the first call and cached by the client, later
+    /// calls do not contact the server. Returns `Err` if the server does not
+    /// know the command.
+    ///
+    /// # Examples
+    ///
+    /// Demonstrates basic usage.
+    ///
+    /// ```no_run
+    /// use mini_redis::clients::Client;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut client = Client::connect("localhost:6379").await.unwrap();
+    ///
+    ///     let args = vec!["foo".into(), "bar".into()];
+    ///     let keys = client.key_positions("set", &args).await.unwrap();
+    ///     assert_eq!(keys, vec![0]);
+    /// }
+    /// ```
+    #[instrument(skip(self))]
+    pub async fn key_positions(&mut self, name: &str, args: &[Bytes]) -> crate::Result<Vec<usize>> {
+        if self.key_specs.is_none() {
+            self.key_specs = Some(self.fetch_key_specs().await?);
+        }
+
+        let spec = match self
+            .key_specs
+            .as_ref()
+            .and_then(|specs| specs.get(&name.to_lowercase()))
+        {
+            Some(spec) => *spec,
+            None => return Err(format!("unknown command '{}'", name).into()),
+        };
+
+        if spec.first_key <= 0 || spec.step <= 0 {
+            return Ok(vec![]);
+        }
+
+        // Positions count the command name as `0`, so `args[i]` is at position
+        // `i + 1`.
+        let argc = args.len() as i64 + 1;
+        let last_key = if spec.last_key < 0 {
+            argc + spec.last_key
+        } else {
+            spec.last_key.min(argc - 1)
+        };
+
+        Ok((spec.first_key..=last_key)
+            .step_by(spec.step as usize)
+            .map(|position| position as usize - 1)
+            .collect())
+    }
+
+    /// Fetches the key positions of every command supported by the server.
+    async fn fetch_key_specs(&mut self) -> crate::Result<HashMap<String, KeySpec>> {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("command"));
+
+        debug!(request = ?frame);
+
+        self.write_frame(&frame).await?;
+
+        let entries = match self.read_response().await? {
+            Frame::Array(entries) => entries,
+            frame => return Err(frame.to_error()),
+        };
+
+        let mut specs = HashMap::with_capacity(entries.len());
+
+        // Each entry is `[name, arity, flags, first key, last key, step, ...]`.
+        for entry in entries {
+            match &entry {
+                Frame::Array(fields) if fields.len() >= 6 => {
+                    let name = match &fields[0] {
+                        Frame::Bulk(name) => String::from_utf8_lossy(name).to_lowercase(),
+                        Frame::Simple(name) => name.to_lowercase(),
+                        _ => return Err(entry.to_error()),
+                    };
+
+                    let spec = match (&fields[3], &fields[4], &fields[5]) {
+                        (
+                            Frame::Integer(first_key),
+                            Frame::Integer(last_key),
+                            Frame::Integer(step),
+                        ) => KeySpec {
+                            first_key: *first_key,
+                            last_key: *last_key,
+                            step: *step,
+                        },
+                        _ => return Err(entry.to_error()),
+                    };
+
+                    specs.insert(name, spec);
+                }
+                _ => return Err(entry.to_error()),
+            }
+        }
+
+        Ok(specs)
+    }
+
     /// Subscribes the client to the specified channels.
     ///
     /// Once a client issues a subscribe command, it may no longer issue any
@@ -337,7 +616,7 @@ impl Client {
         debug!(request = ?frame);
 
         // Write the frame to the socket
-        self.connection.write_frame(&frame).await?;
+        self.write_frame(&frame).await?;
 
         // For each channel being subscribed to, the server responds with a
         // message confirming subscription to that channel.
@@ -358,7 +637,7 @@ impl Client {
                     // num-subscribed is the number of channels that the client
                     // is currently subscribed to.
                     [subscribe, schannel, ..]
-                        if *subscribe == "subscribe" && *schannel == channel => {}
+                        if *subscribe == "subscribe" && *schannel == channel.as_str() => {}
                     _ => return Err(response.to_error()),
                 },
                 frame => return Err(frame.to_error()),
@@ -368,11 +647,22 @@ impl Client {
         Ok(())
     }
 
+    /// Writes a request frame to the socket.
+    ///
+    /// Fails with `TimedOut` if the frame cannot be written within the write
+    /// timeout.
+    async fn write_frame(&mut self, frame: &Frame) -> crate::Result<()> {
+        with_timeout(self.write_timeout, self.connection.write_frame(frame)).await?;
+        Ok(())
+    }
+
     /// Reads a response frame from the socket.
     ///
     /// If an `Error` frame is received, it is converted to `Err`.
+    ///
+    /// Fails with `TimedOut` if no response arrives within the read timeout.
     async fn read_response(&mut self) -> crate::Result<Frame> {
-        let response = self.connection.read_frame().await?;
+        let response = with_timeout(self.read_timeout, self.connection.read_frame()).await?;
 
         debug!(?response);
 
@@ -392,6 +682,143 @@ impl Client {
     }
 }
 
+/// Yields messages published on the subscribed channels, like
+/// `Subscriber::next_message`.
+///
+/// The stream ends when the server closes the connection. Errors are yielded
+/// as items without ending the stream, so callers typically stop polling after
+/// the first one.
+///
+/// Unlike a `next_message` future, polling the stream holds no borrow across
+/// calls, so a `Subscriber` can be polled from `tokio::select!` or stream
+/// combinators and still be used to `subscribe` or `unsubscribe` in between.
+impl ClientBuilder {
+    /// Create a builder for a connection to the server located at `addr`,
+    /// with every option set to its default.
+    ///
+    /// `addr` may be anything that formats as a socket address or a host
+    /// name and port, such as `"localhost:6379"`.
+    pub fn new(addr: impl ToString) -> ClientBuilder {
+        ClientBuilder {
+            addr: addr.to_string(),
+            read_timeout: None,
+            write_timeout: None,
+            write_capacity: DEFAULT_WRITE_BUFFER_CAPACITY,
+            name: None,
+        }
+    }
+
+    /// Fail requests which get no response within `timeout` with a
+    /// `TimedOut` error.
+    ///
+    /// Waiting for messages on a [`Subscriber`] is not subject to the
+    /// timeout, as channels may stay quiet for any length of time. Defaults
+    /// to no timeout.
+    pub fn read_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail requests which cannot be written to the socket within `timeout`
+    /// with a `TimedOut` error. Defaults to no timeout.
+    pub fn write_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the initial capacity of the buffer requests are written to, see
+    /// [`Connection::with_capacity`]. Defaults to 8 KiB.
+    pub fn write_capacity(mut self, capacity: usize) -> ClientBuilder {
+        self.write_capacity = capacity;
+        self
+    }
+
+    /// Name the connection with `CLIENT SETNAME` once it is established.
+    pub fn name(mut self, name: impl ToString) -> ClientBuilder {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Establish the connection and send the configured setup commands.
+    ///
+    /// Returns `Err` if the connection cannot be established or the server
+    /// rejects a setup command, e.g. because the name is invalid.
+    pub async fn build(self) -> crate::Result<Client> {
+        let socket = TcpStream::connect(&self.addr[..]).await?;
+        let connection = Connection::with_capacity(socket, self.write_capacity);
+
+        let mut client = Client::from_connection(connection);
+        client.read_timeout = self.read_timeout;
+        client.write_timeout = self.write_timeout;
+
+        if let Some(name) = self.name {
+            let args = vec!["CLIENT".into(), "SETNAME".into(), name.into()];
+            client.execute(args).await?;
+        }
+
+        Ok(client)
+    }
+}
+
+impl Stream for Subscriber {
+    type Item = crate::Result<Message>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<crate::Result<Message>>> {
+        // `Subscriber` has no self-referential fields, so it is `Unpin`.
+        let this = self.get_mut();
+
+        this.client
+            .connection
+            .poll_read_frame(cx)
+            .map(|res| match res {
+                Ok(Some(mframe)) => Some(to_message(mframe)),
+                Ok(None) => None,
+                Err(err) => Some(Err(err)),
+            })
+    }
+}
+
+/// Awaits `future`, failing with a `TimedOut` error if it does not complete
+/// within `timeout`. `None` waits indefinitely.
+async fn with_timeout<T, E>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = Result<T, E>>,
+) -> crate::Result<T>
+where
+    E: Into<crate::Error>,
+{
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return future.await.map_err(Into::into),
+    };
+
+    match time::timeout(timeout, future).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(_) => Err(Error::new(ErrorKind::TimedOut, "timed out waiting for the server").into()),
+    }
+}
+
+/// Converts a frame received in pub/sub mode into a `Message`.
+///
+/// Anything other than a `message` frame is converted to `Err`.
+fn to_message(mframe: Frame) -> crate::Result<Message> {
+    debug!(?mframe);
+
+    match mframe {
+        Frame::Array(ref frame) => match frame.as_slice() {
+            [message, channel, content] if *message == "message" => Ok(Message {
+                channel: channel.to_string(),
+                content: Bytes::from(content.to_string()),
+            }),
+            _ => Err(mframe.to_error()),
+        },
+        frame => Err(frame.to_error()),
+    }
+}
+
 impl Subscriber {
     /// Returns the set of channels currently subscribed to.
     pub fn get_subscribed(&self) -> &[String] {
@@ -404,20 +831,7 @@ impl Subscriber {
     /// `None` indicates the subscription has been terminated.
     pub async fn next_message(&mut self) -> crate::Result<Option<Message>> {
         match self.client.connection.read_frame().await? {
-            Some(mframe) => {
-                debug!(?mframe);
-
-                match mframe {
-                    Frame::Array(ref frame) => match frame.as_slice() {
-                        [message, channel, content] if *message == "message" => Ok(Some(Message {
-                            channel: channel.to_string(),
-                            content: Bytes::from(content.to_string()),
-                        })),
-                        _ => Err(mframe.to_error()),
-                    },
-                    frame => Err(frame.to_error()),
-                }
-            }
+            Some(mframe) => to_message(mframe).map(Some),
             None => Ok(None),
         }
     }
@@ -425,11 +839,10 @@ impl Subscriber {
     /// Convert the subscriber into a `Stream` yielding new messages published
     /// on subscribed channels.
     ///
-    /// `Subscriber` does not implement stream itself as doing so with safe code
-    /// is non trivial. The usage of async/await would require a manual Stream
-    /// implementation to use `unsafe` code. Instead, a conversion function is
-    /// provided and the returned stream is implemented with the help of the
-    /// `async-stream` crate.
+    /// `Subscriber` also implements `Stream` itself, which lets it be polled
+    /// without giving it up. The returned stream differs in that it ends after
+    /// the first error. It is implemented with the help of the `async-stream`
+    /// crate.
     pub fn into_stream(mut self) -> impl Stream<Item = crate::Result<Message>> {
         // Uses the `try_stream` macro from the `async-stream` crate. Generators
         // are not stable in Rust. The crate uses a macro to simulate generators
@@ -463,7 +876,7 @@ impl Subscriber {
         debug!(request = ?frame);
 
         // Write the frame to the socket
-        self.client.connection.write_frame(&frame).await?;
+        self.client.write_frame(&frame).await?;
 
         // if the input channel list is empty, server acknowledges as unsubscribing
         // from all subscribed channels, so we assert that the unsubscribe list received
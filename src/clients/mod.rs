@@ -1,8 +1,8 @@
 mod client;
-pub use client::{Client, Message, Subscriber};
+pub use client::{Client, ClientBuilder, Message, Subscriber};
 
 mod blocking_client;
 pub use blocking_client::BlockingClient;
 
 mod buffered_client;
-pub use buffered_client::BufferedClient;
+pub use buffered_client::{BufferConfig, BufferedClient};
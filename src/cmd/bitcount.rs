@@ -0,0 +1,137 @@
+use crate::cmd::{CommandError, Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use tracing::{debug, instrument};
+
+/// Counts the set bits in the string value stored at key.
+///
+/// By default every byte of the string is inspected. The optional `start` and
+/// `end` arguments restrict counting to an inclusive range of bytes. Negative
+/// indices count from the end of the string, `-1` being the last byte. A
+/// missing key counts as an empty string.
+#[derive(Debug)]
+pub struct BitCount {
+    /// the lookup key
+    key: String,
+
+    /// inclusive byte range to count, if any
+    range: Option<(i64, i64)>,
+}
+
+impl BitCount {
+    /// Create a new `BitCount` command which counts the set bits of the value
+    /// stored at `key`, optionally within the inclusive byte `range`.
+    pub fn new(key: impl ToString, range: Option<(i64, i64)>) -> BitCount {
+        BitCount {
+            key: key.to_string(),
+            range,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the byte range
+    pub fn range(&self) -> Option<(i64, i64)> {
+        self.range
+    }
+
+    /// Parse a `BitCount` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BITCOUNT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BitCount` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing either two or four entries.
+    ///
+    /// ```text
+    /// BITCOUNT key [start end]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitCount> {
+        let key = parse.next_string()?;
+
+        // The range is optional, but if `start` is given `end` must be too.
+        let range = match parse.next_i64() {
+            Ok(start) => Some((start, parse.next_i64()?)),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(BitCount { key, range })
+    }
+
+    /// Apply the `BitCount` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.get(&self.key) {
+            Ok(value) => {
+                let value = value.unwrap_or_default();
+
+                let bytes = match self.range {
+                    Some((start, end)) => match byte_range(start, end, value.len()) {
+                        Some((start, end)) => &value[start..=end],
+                        None => &[],
+                    },
+                    None => &value[..],
+                };
+
+                let count: u32 = bytes.iter().map(|b| b.count_ones()).sum();
+                Frame::Integer(count as i64)
+            }
+            Err(err) => CommandError::from(err).into(),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Resolve the inclusive range `start..=end` against a string of `len` bytes.
+///
+/// Negative indices count from the end of the string. The range is clamped to
+/// the string, and `None` is returned if it is empty. The steps match the ones
+/// Redis takes, including its edge cases.
+///
+/// This is shared with `GETRANGE`, which resolves its range the same way.
+pub(crate) fn byte_range(start: i64, end: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+
+    // A range running backwards from the end is always empty, even though
+    // clamping both ends to `0` below would select the first byte.
+    if start < 0 && end < 0 && start > end {
+        return None;
+    }
+
+    let start = if start < 0 {
+        (start + len).max(0)
+    } else {
+        start
+    };
+    let end = if end < 0 {
+        (end + len).max(0)
+    } else {
+        end.min(len - 1)
+    };
+
+    if start > end || len == 0 {
+        return None;
+    }
+
+    Some((start as usize, end as usize))
+}
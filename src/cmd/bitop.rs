@@ -0,0 +1,167 @@
+use crate::cmd::{CommandError, Parse};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Performs a bitwise operation between the string values stored at the
+/// source keys, and stores the result at the destination key.
+///
+/// Sources of different lengths are padded with zero bytes to the length of
+/// the longest one, and missing keys count as empty strings. The result
+/// replaces the destination key regardless of its type, and clears its
+/// expiration. If the result is empty, the destination key is deleted.
+///
+/// The reply is the length of the result in bytes.
+#[derive(Debug)]
+pub struct BitOp {
+    /// the operation to perform
+    operation: BitOperation,
+
+    /// key the result is stored at
+    destination: String,
+
+    /// keys of the operands, in order
+    sources: Vec<String>,
+}
+
+/// The bitwise operations supported by `BITOP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    /// `AND` -- bits set in every source.
+    And,
+
+    /// `OR` -- bits set in any source.
+    Or,
+
+    /// `XOR` -- bits set in an odd number of sources.
+    Xor,
+
+    /// `NOT` -- the inverse of the single source.
+    Not,
+}
+
+impl BitOp {
+    /// Create a new `BitOp` command which stores the result of `operation`
+    /// between the values of `sources` at `destination`.
+    pub fn new(operation: BitOperation, destination: impl ToString, sources: Vec<String>) -> BitOp {
+        BitOp {
+            operation,
+            destination: destination.to_string(),
+            sources,
+        }
+    }
+
+    /// Get the destination key
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    /// Parse a `BitOp` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BITOP` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BitOp` value on success. If the frame is malformed, the
+    /// operation is not supported or `NOT` is given more than one source,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// BITOP AND | OR | XOR | NOT destkey srckey [srckey ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitOp> {
+        let operation = match &parse.next_string()?.to_uppercase()[..] {
+            "AND" => BitOperation::And,
+            "OR" => BitOperation::Or,
+            "XOR" => BitOperation::Xor,
+            "NOT" => BitOperation::Not,
+            s => {
+                return Err(format!("protocol error; unsupported `BITOP` operation `{}`", s).into())
+            }
+        };
+
+        let destination = parse.next_string()?;
+
+        // The command table guarantees at least one source.
+        let mut sources = vec![parse.next_string()?];
+        while parse.remaining() > 0 {
+            sources.push(parse.next_string()?);
+        }
+
+        if operation == BitOperation::Not && sources.len() > 1 {
+            return Err("protocol error; `BITOP NOT` takes a single source key".into());
+        }
+
+        Ok(BitOp {
+            operation,
+            destination,
+            sources,
+        })
+    }
+
+    /// Apply the `BitOp` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let operation = self.operation;
+
+        let response = match db.bitop(&self.destination, &self.sources, |values| {
+            operation.apply(values)
+        }) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => CommandError::from(err).into(),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl BitOperation {
+    /// Computes the result of the operation between `values`, padding the
+    /// shorter ones with zero bytes.
+    fn apply(self, values: &[Bytes]) -> Vec<u8> {
+        let len = values.iter().map(Bytes::len).max().unwrap_or(0);
+
+        let (first, rest) = match values.split_first() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+
+        let mut result = first.to_vec();
+        result.resize(len, 0);
+
+        if self == BitOperation::Not {
+            result.iter_mut().for_each(|byte| *byte = !*byte);
+            return result;
+        }
+
+        for value in rest {
+            for (i, byte) in result.iter_mut().enumerate() {
+                let other = value.get(i).copied().unwrap_or(0);
+
+                *byte = match self {
+                    BitOperation::And => *byte & other,
+                    BitOperation::Or => *byte | other,
+                    BitOperation::Xor => *byte ^ other,
+                    BitOperation::Not => unreachable!(),
+                };
+            }
+        }
+
+        result
+    }
+}
@@ -0,0 +1,203 @@
+use crate::cmd::{CommandError, Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use tracing::{debug, instrument};
+
+/// Returns the position of the first bit set to `1` or `0` in the string value
+/// stored at key.
+///
+/// Positions are counted from the most significant bit of the first byte, as
+/// in `GETBIT` and `SETBIT`. By default the whole string is searched. The
+/// optional `start` and `end` arguments restrict the search to an inclusive
+/// range, of bytes by default or of bits with `BIT`. Negative indices count
+/// from the end of the string. The returned position is always absolute.
+///
+/// A missing key is treated as an endless run of zero bits. When looking for a
+/// `0` bit without an `end`, a string made only of set bits is considered to
+/// be padded with zero bits, so the position right after its last bit is
+/// returned. In every other case where no matching bit is found, the reply is
+/// `-1`.
+#[derive(Debug)]
+pub struct BitPos {
+    /// the lookup key
+    key: String,
+
+    /// the bit value to look for
+    bit: bool,
+
+    /// first position of the range to search, if any
+    start: Option<i64>,
+
+    /// last position of the range to search, if any. Only given with `start`.
+    end: Option<i64>,
+
+    /// whether `start` and `end` are bit rather than byte positions
+    bit_range: bool,
+}
+
+impl BitPos {
+    /// Create a new `BitPos` command which looks for the first bit equal to
+    /// `bit` in the value stored at `key`, searching the whole string.
+    pub fn new(key: impl ToString, bit: bool) -> BitPos {
+        BitPos {
+            key: key.to_string(),
+            bit,
+            start: None,
+            end: None,
+            bit_range: false,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the bit value to look for
+    pub fn bit(&self) -> bool {
+        self.bit
+    }
+
+    /// Parse a `BitPos` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `BITPOS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `BitPos` value on success. If the frame is malformed, the
+    /// bit is neither `0` nor `1` or the range unit is not supported, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three to six entries.
+    ///
+    /// ```text
+    /// BITPOS key bit [start [end [BYTE | BIT]]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<BitPos> {
+        let key = parse.next_string()?;
+
+        let bit = match parse.next_int()? {
+            0 => false,
+            1 => true,
+            _ => return Err("protocol error; bit must be `0` or `1`".into()),
+        };
+
+        let mut bitpos = BitPos::new(key, bit);
+
+        // Each optional argument requires the previous one.
+        bitpos.start = match parse.next_i64() {
+            Ok(start) => Some(start),
+            Err(ParseError::EndOfStream) => return Ok(bitpos),
+            Err(err) => return Err(err.into()),
+        };
+
+        bitpos.end = match parse.next_i64() {
+            Ok(end) => Some(end),
+            Err(ParseError::EndOfStream) => return Ok(bitpos),
+            Err(err) => return Err(err.into()),
+        };
+
+        bitpos.bit_range = match parse.next_string() {
+            Ok(s) if s.eq_ignore_ascii_case("byte") => false,
+            Ok(s) if s.eq_ignore_ascii_case("bit") => true,
+            Ok(s) => {
+                return Err(format!("protocol error; unsupported `BITPOS` option `{}`", s).into())
+            }
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(bitpos)
+    }
+
+    /// Apply the `BitPos` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.get(&self.key) {
+            Ok(Some(value)) => Frame::Integer(self.search(&value)),
+            // A missing key holds no set bit, and its first clear bit is the
+            // very first one.
+            Ok(None) => Frame::Integer(if self.bit { -1 } else { 0 }),
+            Err(err) => CommandError::from(err).into(),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Search `value` for the first matching bit within the requested range,
+    /// returning its position or `-1`.
+    ///
+    /// The range is resolved with the same steps as Redis, including its edge
+    /// cases.
+    fn search(&self, value: &[u8]) -> i64 {
+        let len = if self.bit_range {
+            value.len() as i64 * 8
+        } else {
+            value.len() as i64
+        };
+
+        let clamp = |index: i64| {
+            let index = if index < 0 { index + len } else { index };
+            index.max(0)
+        };
+
+        let start = self.start.map_or(0, clamp);
+        let end = self.end.map_or(len - 1, clamp).min(len - 1);
+
+        if start > end {
+            return -1;
+        }
+
+        // Turn the range into inclusive bit positions.
+        let (first, last) = if self.bit_range {
+            (start as usize, end as usize)
+        } else {
+            (start as usize * 8, end as usize * 8 + 7)
+        };
+
+        match find_bit(value, self.bit, first, last) {
+            Some(pos) => pos as i64,
+            // Without an explicit end, the string counts as padded with zero
+            // bits on the right.
+            None if !self.bit && self.end.is_none() => (last + 1) as i64,
+            None => -1,
+        }
+    }
+}
+
+/// Returns the position of the first bit equal to `bit` between the bit
+/// positions `first` and `last`, inclusive, which must be within `value`.
+fn find_bit(value: &[u8], bit: bool, first: usize, last: usize) -> Option<usize> {
+    // A byte made only of the other bit value can be skipped at once.
+    let skip = if bit { 0x00 } else { 0xff };
+
+    let mut pos = first;
+    while pos <= last {
+        let byte = value[pos / 8];
+
+        if pos.is_multiple_of(8) && pos + 7 <= last && byte == skip {
+            pos += 8;
+            continue;
+        }
+
+        if (byte >> (7 - pos % 8)) & 1 == bit as u8 {
+            return Some(pos);
+        }
+
+        pos += 1;
+    }
+
+    None
+}
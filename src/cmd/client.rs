@@ -0,0 +1,233 @@
+use crate::cmd::CommandError;
+use crate::session::ClientInfo;
+use crate::{Connection, Frame, Parse, ParseError, Session};
+
+use bytes::Bytes;
+use std::fmt::Write;
+use tracing::{debug, instrument};
+
+/// Inspect and modify the state of the current connection.
+///
+/// # Subcommands
+///
+/// Currently, the following subcommands are supported:
+///
+/// * ID -- the unique id assigned to the connection when it was accepted.
+/// * SETNAME `name` -- assign a name to the connection. An empty name clears
+///   the current name.
+/// * GETNAME -- the name assigned to the connection, or nil.
+/// * LIST -- one line per active connection describing its id, address,
+///   name, age in seconds and the last command it ran.
+/// * KILL `filter value` [`filter value` ...] -- terminate every connection
+///   matching all of the given filters and reply with the number of
+///   connections killed. The supported filters are `ID id`, `ADDR ip:port`
+///   and `SKIPME yes|no`. As in Redis, the calling connection is skipped
+///   unless `SKIPME no` is given.
+#[derive(Debug)]
+pub enum ClientCommand {
+    /// `CLIENT ID`
+    Id,
+
+    /// `CLIENT SETNAME name`
+    SetName(String),
+
+    /// `CLIENT GETNAME`
+    GetName,
+
+    /// `CLIENT LIST`
+    List,
+
+    /// `CLIENT KILL filter value [filter value ...]`
+    Kill(KillFilter),
+}
+
+/// Selects the connections terminated by `CLIENT KILL`.
+///
+/// A connection is killed only if it matches every filter that was given.
+#[derive(Debug)]
+pub struct KillFilter {
+    /// Only kill the connection with this id.
+    id: Option<u64>,
+
+    /// Only kill the connection with this peer address.
+    addr: Option<String>,
+
+    /// Never kill the connection issuing the command.
+    skip_me: bool,
+}
+
+impl ClientCommand {
+    /// Parse a `ClientCommand` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `CLIENT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ClientCommand` value on success. If the frame is malformed
+    /// or the subcommand is not supported, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing the subcommand and its arguments.
+    ///
+    /// ```text
+    /// CLIENT ID
+    /// CLIENT SETNAME name
+    /// CLIENT GETNAME
+    /// CLIENT LIST
+    /// CLIENT KILL [ID id] [ADDR ip:port] [SKIPME yes|no]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ClientCommand> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "id" => Ok(ClientCommand::Id),
+            "setname" => Ok(ClientCommand::SetName(parse.next_string()?)),
+            "getname" => Ok(ClientCommand::GetName),
+            "list" => Ok(ClientCommand::List),
+            "kill" => Ok(ClientCommand::Kill(KillFilter::parse_frames(parse)?)),
+            _ => Err(format!("unknown subcommand '{}' for `CLIENT`", subcommand).into()),
+        }
+    }
+
+    /// Apply the `ClientCommand` to the current connection's `Session`.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst, session))]
+    pub(crate) async fn apply(
+        self,
+        dst: &mut Connection,
+        session: &mut Session,
+    ) -> crate::Result<()> {
+        let response = match self {
+            ClientCommand::Id => Frame::Integer(session.id() as i64),
+            ClientCommand::SetName(name) => {
+                // Names show up in space separated listings, so they may only
+                // contain printable characters other than space. This matches
+                // the validation performed by Redis.
+                if name.chars().any(|c| !c.is_ascii_graphic()) {
+                    CommandError::InvalidClientName.into()
+                } else {
+                    // Setting an empty name removes the current name.
+                    session.set_name(Some(name).filter(|name| !name.is_empty()));
+                    Frame::Simple("OK".to_string())
+                }
+            }
+            ClientCommand::GetName => match session.name() {
+                Some(name) => Frame::Bulk(Bytes::from(name.into_bytes())),
+                None => Frame::Null,
+            },
+            ClientCommand::List => {
+                let mut list = String::new();
+
+                for info in session.clients().list() {
+                    write_client_info(&mut list, &info);
+                }
+
+                Frame::Bulk(Bytes::from(list.into_bytes()))
+            }
+            ClientCommand::Kill(filter) => {
+                let me = session.id();
+                let killed = session.clients().kill(|info| filter.matches(info, me));
+
+                Frame::Integer(killed as i64)
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl KillFilter {
+    /// Parse the `filter value` pairs following `CLIENT KILL`.
+    ///
+    /// At least one filter must be given.
+    fn parse_frames(parse: &mut Parse) -> crate::Result<KillFilter> {
+        use ParseError::EndOfStream;
+
+        let mut filter = KillFilter {
+            id: None,
+            addr: None,
+            skip_me: true,
+        };
+
+        // Read the first filter name. Without any filter the command would
+        // kill every connection, which is never what the client meant.
+        let mut name = parse.next_string()?;
+
+        loop {
+            match &name.to_lowercase()[..] {
+                "id" => filter.id = Some(parse.next_int()?),
+                "addr" => filter.addr = Some(parse.next_string()?),
+                "skipme" => {
+                    filter.skip_me = match &parse.next_string()?.to_lowercase()[..] {
+                        "yes" => true,
+                        "no" => false,
+                        _ => return Err("protocol error; SKIPME must be `yes` or `no`".into()),
+                    }
+                }
+                _ => return Err(format!("unsupported `CLIENT KILL` filter '{}'", name).into()),
+            }
+
+            // Continue with the next filter until the frame is exhausted.
+            name = match parse.next_string() {
+                Ok(name) => name,
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            };
+        }
+
+        Ok(filter)
+    }
+
+    /// Returns `true` if the connection described by `info` should be killed.
+    ///
+    /// `me` is the id of the connection issuing the command.
+    fn matches(&self, info: &ClientInfo, me: u64) -> bool {
+        if self.skip_me && info.id == me {
+            return false;
+        }
+
+        if let Some(id) = self.id {
+            if info.id != id {
+                return false;
+            }
+        }
+
+        if let Some(addr) = &self.addr {
+            if info.addr.to_string() != *addr {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Append the `CLIENT LIST` line describing `info` to `dst`.
+///
+/// The line is a space separated list of `field=value` pairs, in the same
+/// format used by Redis.
+fn write_client_info(dst: &mut String, info: &ClientInfo) {
+    // Writing to a `String` cannot fail.
+    let _ = writeln!(
+        dst,
+        "id={} addr={} name={} age={} idle={} cmd={} tot-cmds={}",
+        info.id,
+        info.addr,
+        info.name.as_deref().unwrap_or(""),
+        info.created_at.elapsed().as_secs(),
+        info.last_interaction.elapsed().as_secs(),
+        info.last_command.as_deref().unwrap_or("NULL"),
+        info.commands,
+    );
+}
@@ -0,0 +1,646 @@
+use crate::cmd::*;
+use crate::{Connection, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Introspect the server's command table.
+///
+/// # Subcommands
+///
+/// * (none) -- one entry per supported command, describing its name, arity,
+///   flags and the positions of its key arguments.
+/// * COUNT -- the number of supported commands.
+/// * DOCS [`command` ...] -- documentation for the given commands, or for
+///   every command if none are given.
+#[derive(Debug)]
+pub enum CommandCommand {
+    /// `COMMAND`
+    List,
+
+    /// `COMMAND COUNT`
+    Count,
+
+    /// `COMMAND DOCS [command ...]`
+    Docs(Vec<String>),
+}
+
+/// Describes a single supported command.
+///
+/// The fields mirror the entries Redis reports in its `COMMAND` reply.
+#[derive(Debug)]
+pub(crate) struct CommandSpec {
+    /// Lower case command name.
+    pub(crate) name: &'static str,
+
+    /// Number of arguments, including the command name. A negative value `-N`
+    /// means the command takes at least `N` arguments.
+    pub(crate) arity: i64,
+
+    /// Command flags, e.g. `readonly` or `write`.
+    pub(crate) flags: &'static [&'static str],
+
+    /// Position of the first key argument, or `0` if the command takes no
+    /// keys.
+    pub(crate) first_key: i64,
+
+    /// Position of the last key argument. `-1` means the keys extend to the
+    /// last argument.
+    pub(crate) last_key: i64,
+
+    /// Step between key arguments.
+    pub(crate) step: i64,
+
+    /// One line description of the command.
+    pub(crate) summary: &'static str,
+
+    /// Parses the arguments of the command, which follow the command name.
+    pub(crate) parse: fn(&mut Parse) -> crate::Result<Command>,
+}
+
+/// Every command supported by `mini-redis`.
+///
+/// This is the single source of truth for the supported commands:
+/// `Command::from_frame` dispatches through it, and `COMMAND` and `INFO`
+/// report from it. A command that is not listed here is unknown to the server.
+pub(crate) static COMMAND_TABLE: &[CommandSpec] = &[
+    CommandSpec {
+        name: "get",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns the string value of a key.",
+        parse: |parse| Ok(Command::Get(Get::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "set",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Sets the string value of a key, optionally with an expiration.",
+        parse: |parse| Ok(Command::Set(Set::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "publish",
+        arity: 3,
+        flags: &["pubsub", "loading", "stale", "fast"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Posts a message to a channel.",
+        parse: |parse| Ok(Command::Publish(Publish::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "subscribe",
+        arity: -2,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Listens for messages published to channels.",
+        parse: |parse| Ok(Command::Subscribe(Subscribe::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "unsubscribe",
+        arity: -1,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Stops listening to messages posted to channels.",
+        parse: |parse| Ok(Command::Unsubscribe(Unsubscribe::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "ping",
+        arity: -1,
+        flags: &["fast", "stale"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Returns the server's liveliness response.",
+        parse: |parse| Ok(Command::Ping(Ping::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "randomkey",
+        arity: 1,
+        flags: &["readonly", "random"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Returns a random key name from the database.",
+        parse: |parse| Ok(Command::RandomKey(RandomKey::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "touch",
+        arity: -2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        summary: "Returns the number of existing keys out of those specified.",
+        parse: |parse| Ok(Command::Touch(Touch::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "object",
+        arity: -2,
+        flags: &["readonly", "random"],
+        first_key: 2,
+        last_key: 2,
+        step: 1,
+        summary: "Inspects the internals of a value.",
+        parse: |parse| Ok(Command::Object(Object::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "wait",
+        arity: 3,
+        flags: &["noscript"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Blocks until writes are acknowledged by replicas.",
+        parse: |parse| Ok(Command::Wait(Wait::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "debug",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Debugging and testing helpers.",
+        parse: |parse| Ok(Command::Debug(DebugCommand::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "client",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Inspects and modifies client connections.",
+        parse: |parse| Ok(Command::Client(ClientCommand::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "command",
+        arity: -1,
+        flags: &["random", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Returns detailed information about all commands.",
+        parse: |parse| Ok(Command::Command(CommandCommand::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "info",
+        arity: -1,
+        flags: &["random", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Returns information and statistics about the server.",
+        parse: |parse| Ok(Command::Info(Info::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "reset",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Resets the connection.",
+        parse: |parse| Ok(Command::Reset(Reset::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "setbit",
+        arity: 4,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Sets or clears the bit at offset of the string value.",
+        parse: |parse| Ok(Command::SetBit(SetBit::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "getbit",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns a bit value by offset.",
+        parse: |parse| Ok(Command::GetBit(GetBit::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "bitcount",
+        arity: -2,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Counts the number of set bits in a string.",
+        parse: |parse| Ok(Command::BitCount(BitCount::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "getrange",
+        arity: 4,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns a substring of the string stored at a key.",
+        parse: |parse| Ok(Command::GetRange(GetRange::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "setrange",
+        arity: 4,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Overwrites a part of a string value with another by an offset.",
+        parse: |parse| Ok(Command::SetRange(SetRange::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "zadd",
+        arity: -4,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Adds one or more members to a sorted set.",
+        parse: |parse| Ok(Command::ZAdd(ZAdd::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "zscore",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns the score of a member in a sorted set.",
+        parse: |parse| Ok(Command::ZScore(ZScore::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "zrank",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns the index of a member in a sorted set ordered by ascending scores.",
+        parse: |parse| Ok(Command::ZRank(ZRank::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "zrange",
+        arity: -4,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns members in a sorted set within a range of indexes.",
+        parse: |parse| Ok(Command::ZRange(ZRange::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "zrangebyscore",
+        arity: -4,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns members in a sorted set within a range of scores.",
+        parse: |parse| Ok(Command::ZRangeByScore(ZRangeByScore::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "zrem",
+        arity: -3,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Removes one or more members from a sorted set.",
+        parse: |parse| Ok(Command::ZRem(ZRem::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "incrbyfloat",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Increments the floating point value of a key by a number.",
+        parse: |parse| Ok(Command::IncrByFloat(IncrByFloat::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "getex",
+        arity: -2,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns the string value of a key after setting its expiration time.",
+        parse: |parse| Ok(Command::GetEx(GetEx::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "shutdown",
+        arity: -1,
+        flags: &["admin", "noscript"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Shuts down the server.",
+        parse: |parse| Ok(Command::Shutdown(ShutdownCommand::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "getorset",
+        arity: 3,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns the string value of a key, setting it to a default value if the key doesn't exist.",
+        parse: |parse| Ok(Command::GetOrSet(GetOrSet::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "zscan",
+        arity: -3,
+        flags: &["readonly", "random"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Iterates over members and scores of a sorted set.",
+        parse: |parse| Ok(Command::ZScan(ZScan::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "dump",
+        arity: 2,
+        flags: &["readonly", "random"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns a serialized representation of the value stored at a key.",
+        parse: |parse| Ok(Command::Dump(Dump::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "restore",
+        arity: -4,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Creates a key from the serialized representation of a value.",
+        parse: |parse| Ok(Command::Restore(Restore::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "lolwut",
+        arity: -1,
+        flags: &["readonly", "fast"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Displays computer art and the server version.",
+        parse: |parse| Ok(Command::Lolwut(Lolwut::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "slowlog",
+        arity: -2,
+        flags: &["admin", "random", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Reads and clears the log of slow commands.",
+        parse: |parse| Ok(Command::SlowLog(SlowLogCommand::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "config",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Reads and modifies the server configuration.",
+        parse: |parse| Ok(Command::Config(ConfigCommand::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "zrandmember",
+        arity: -2,
+        flags: &["readonly", "random"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Returns random members of a sorted set.",
+        parse: |parse| Ok(Command::ZRandMember(ZRandMember::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "expire",
+        arity: -3,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Sets a key's time to live in seconds.",
+        parse: |parse| Ok(Command::Expire(Expire::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "role",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Returns the replication role.",
+        parse: |parse| Ok(Command::Role(Role::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "bitpos",
+        arity: -3,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        summary: "Finds the first set or clear bit in a string.",
+        parse: |parse| Ok(Command::BitPos(BitPos::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "bitop",
+        arity: -4,
+        flags: &["write", "denyoom"],
+        first_key: 2,
+        last_key: -1,
+        step: 1,
+        summary: "Performs bitwise operations on multiple strings, and stores the result.",
+        parse: |parse| Ok(Command::BitOp(BitOp::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "memory",
+        arity: -2,
+        flags: &["readonly"],
+        first_key: 2,
+        last_key: 2,
+        step: 1,
+        summary: "Estimates the memory usage of a key.",
+        parse: |parse| Ok(Command::Memory(Memory::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "lcs",
+        arity: -3,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 2,
+        step: 1,
+        summary: "Finds the longest common substring.",
+        parse: |parse| Ok(Command::Lcs(Lcs::parse_frames(parse)?)),
+    },
+    CommandSpec {
+        name: "time",
+        arity: 1,
+        flags: &["random", "loading", "stale", "fast"],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        summary: "Returns the server time.",
+        parse: |parse| Ok(Command::Time(Time::parse_frames(parse)?)),
+    },
+];
+
+impl CommandCommand {
+    /// Parse a `CommandCommand` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `COMMAND` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `CommandCommand` value on success. If the frame is malformed
+    /// or the subcommand is not supported, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing an optional subcommand and its
+    /// arguments.
+    ///
+    /// ```text
+    /// COMMAND
+    /// COMMAND COUNT
+    /// COMMAND DOCS [command ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<CommandCommand> {
+        use ParseError::EndOfStream;
+
+        let subcommand = match parse.next_string() {
+            Ok(subcommand) => subcommand.to_lowercase(),
+            Err(EndOfStream) => return Ok(CommandCommand::List),
+            Err(err) => return Err(err.into()),
+        };
+
+        match &subcommand[..] {
+            "count" => Ok(CommandCommand::Count),
+            "docs" => {
+                let mut names = vec![];
+
+                loop {
+                    match parse.next_string() {
+                        Ok(name) => names.push(name.to_lowercase()),
+                        Err(EndOfStream) => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+
+                Ok(CommandCommand::Docs(names))
+            }
+            _ => Err(format!("unknown subcommand '{}' for `COMMAND`", subcommand).into()),
+        }
+    }
+
+    /// Apply the `CommandCommand`.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self {
+            CommandCommand::List => {
+                Frame::Array(COMMAND_TABLE.iter().map(CommandSpec::to_frame).collect())
+            }
+            CommandCommand::Count => Frame::Integer(COMMAND_TABLE.len() as i64),
+            CommandCommand::Docs(names) => {
+                // RESP2 has no map type, so the map is flattened into an array
+                // of alternating command names and documentation. Unknown
+                // command names are skipped.
+                let mut entries = vec![];
+
+                for spec in COMMAND_TABLE {
+                    if names.is_empty() || names.iter().any(|name| name == spec.name) {
+                        entries.push(Frame::Bulk(Bytes::from(spec.name)));
+                        entries.push(spec.docs_frame());
+                    }
+                }
+
+                Frame::Array(entries)
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl CommandSpec {
+    /// Returns the spec of the command named `name`, which must be lower case.
+    pub(crate) fn lookup(name: &str) -> Option<&'static CommandSpec> {
+        COMMAND_TABLE.iter().find(|spec| spec.name == name)
+    }
+
+    /// Returns `true` if the command may be called with `count` arguments,
+    /// including the command name.
+    pub(crate) fn accepts_arg_count(&self, count: usize) -> bool {
+        let count = count as i64;
+
+        if self.arity < 0 {
+            count >= -self.arity
+        } else {
+            count == self.arity
+        }
+    }
+
+    /// Returns `true` if the command has the flag `flag`, e.g. `"write"`.
+    pub(crate) fn has_flag(&self, flag: &str) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Converts the spec into the entry reported by `COMMAND`.
+    fn to_frame(&self) -> Frame {
+        let flags = self
+            .flags
+            .iter()
+            .map(|flag| Frame::Simple(flag.to_string()))
+            .collect();
+
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from(self.name)),
+            Frame::Integer(self.arity),
+            Frame::Array(flags),
+            Frame::Integer(self.first_key),
+            Frame::Integer(self.last_key),
+            Frame::Integer(self.step),
+        ])
+    }
+
+    /// Converts the spec into the flattened documentation map reported by
+    /// `COMMAND DOCS`.
+    fn docs_frame(&self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("summary"));
+        frame.push_bulk(Bytes::from(self.summary));
+        frame
+    }
+}
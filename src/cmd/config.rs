@@ -0,0 +1,215 @@
+use crate::cmd::CommandError;
+use crate::db::EvictionPolicy;
+use crate::server::{Config, MAX_PUBSUB_CAPACITY};
+use crate::{glob, Connection, Db, Frame, Parse, Session};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Read and modify the server configuration at runtime.
+///
+/// The configuration is shared by every connection. Only the parameters in
+/// `PARAMS` are exposed:
+///
+/// * maxmemory -- the memory limit in bytes, `0` for no limit. Takes effect
+///   immediately, evicting keys if the new limit is already exceeded.
+/// * maxmemory-policy -- `noeviction`, `allkeys-lru` or `allkeys-lfu`. Takes
+///   effect immediately.
+/// * proto-max-bulk-len -- the largest bulk string a client may send. Applies
+///   to connections accepted afterwards.
+/// * pubsub-capacity -- the number of messages kept for slow subscribers, at
+///   most `MAX_PUBSUB_CAPACITY`. Applies to channels created afterwards.
+///
+/// Memory values accept the same unit suffixes as Redis, e.g. `100mb` or
+/// `1gb`.
+///
+/// # Subcommands
+///
+/// * GET `pattern` -- a flat array of parameter names and values, for every
+///   parameter matching the glob-style `pattern`.
+/// * SET `parameter value` -- validate and apply the new value.
+#[derive(Debug)]
+pub enum ConfigCommand {
+    /// `CONFIG GET pattern`
+    Get(String),
+
+    /// `CONFIG SET parameter value`
+    Set(String, String),
+}
+
+/// A configuration parameter exposed by `CONFIG`.
+struct Param {
+    /// Name of the parameter, as used by Redis where it has an equivalent.
+    name: &'static str,
+
+    /// Renders the current value.
+    get: fn(&Config) -> String,
+
+    /// Validates `value` and stores it in the configuration. Changes which
+    /// take effect immediately are also applied to the database. On failure,
+    /// returns a description of the expected value.
+    set: fn(&mut Config, &Db, &str) -> Result<(), &'static str>,
+}
+
+/// Every parameter exposed by `CONFIG`, in the order `CONFIG GET` reports
+/// them.
+const PARAMS: &[Param] = &[
+    Param {
+        name: "maxmemory",
+        get: |config| config.maxmemory.to_string(),
+        set: |config, db, value| {
+            let maxmemory = parse_memory(value).ok_or("argument must be a memory value")?;
+
+            config.maxmemory = maxmemory;
+            db.set_maxmemory(maxmemory);
+
+            // As in Redis, lowering the limit evicts keys right away rather
+            // than on the next write.
+            db.free_memory();
+            Ok(())
+        },
+    },
+    Param {
+        name: "maxmemory-policy",
+        get: |config| config.maxmemory_policy.name().to_string(),
+        set: |config, db, value| {
+            let policy = EvictionPolicy::from_name(value).ok_or(
+                "argument(s) must be one of the following: noeviction, allkeys-lru, allkeys-lfu",
+            )?;
+
+            config.maxmemory_policy = policy;
+            db.set_maxmemory_policy(policy);
+            Ok(())
+        },
+    },
+    Param {
+        name: "proto-max-bulk-len",
+        get: |config| config.max_bulk_len.to_string(),
+        set: |config, _, value| {
+            config.max_bulk_len = parse_memory(value)
+                .filter(|&len| len > 0)
+                .ok_or("argument must be a memory value greater than zero")?;
+            Ok(())
+        },
+    },
+    Param {
+        name: "pubsub-capacity",
+        get: |config| config.pubsub_capacity.to_string(),
+        set: |config, db, value| {
+            let capacity = value
+                .parse()
+                .ok()
+                .filter(|&capacity| capacity > 0 && capacity <= MAX_PUBSUB_CAPACITY)
+                .ok_or("argument must be an integer between 1 and 1048576")?;
+
+            config.pubsub_capacity = capacity;
+            db.set_pubsub_capacity(capacity);
+            Ok(())
+        },
+    },
+];
+
+impl ConfigCommand {
+    /// Parse a `ConfigCommand` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `CONFIG` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ConfigCommand` value on success. If the frame is malformed
+    /// or the subcommand is not supported, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// CONFIG GET pattern
+    /// CONFIG SET parameter value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ConfigCommand> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "get" => Ok(ConfigCommand::Get(parse.next_string()?.to_lowercase())),
+            "set" => {
+                let name = parse.next_string()?.to_lowercase();
+                let value = parse.next_string()?;
+
+                Ok(ConfigCommand::Set(name, value))
+            }
+            _ => Err(format!("unknown subcommand '{}' for `CONFIG`", subcommand).into()),
+        }
+    }
+
+    /// Apply the `ConfigCommand` to the shared server configuration.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, session))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        session: &mut Session,
+    ) -> crate::Result<()> {
+        let response = {
+            let mut config = session.config().lock().unwrap();
+
+            match self {
+                ConfigCommand::Get(pattern) => {
+                    let mut response = Frame::array();
+
+                    for param in PARAMS {
+                        if glob::matches(pattern.as_bytes(), param.name.as_bytes()) {
+                            response.push_bulk(Bytes::from(param.name));
+                            response.push_bulk(Bytes::from((param.get)(&config)));
+                        }
+                    }
+
+                    response
+                }
+                ConfigCommand::Set(name, value) => {
+                    match PARAMS.iter().find(|param| param.name == name) {
+                        Some(param) => match (param.set)(&mut config, db, &value) {
+                            Ok(()) => Frame::Simple("OK".to_string()),
+                            Err(reason) => CommandError::InvalidConfig(param.name, reason).into(),
+                        },
+                        None => CommandError::UnknownConfig(name).into(),
+                    }
+                }
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Parses a memory value such as `1048576`, `100mb` or `1gb`.
+///
+/// As in Redis, `k`, `m` and `g` are powers of 1000 and `kb`, `mb` and `gb`
+/// powers of 1024. Units are case insensitive. Returns `None` if the value is
+/// malformed or does not fit in a `usize`.
+fn parse_memory(value: &str) -> Option<usize> {
+    let value = value.to_lowercase();
+    let digits = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+
+    let unit: usize = match &value[digits.len()..] {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    digits.parse::<usize>().ok()?.checked_mul(unit)
+}
@@ -0,0 +1,210 @@
+use crate::cmd::CommandError;
+use crate::db::ObjectInfo;
+use crate::{Connection, Db, Frame, Parse, ParseError, Shutdown};
+
+use bytes::Bytes;
+use tokio::task;
+use tokio::time::{self, Duration};
+use tracing::{debug, instrument};
+
+/// Largest number of keys a single `DEBUG POPULATE` may create.
+const MAX_POPULATE_COUNT: u64 = 100_000_000;
+
+/// Number of keys `DEBUG POPULATE` inserts while holding the database lock.
+const POPULATE_BATCH: u64 = 1024;
+
+/// Debugging and testing helpers.
+///
+/// These are not meant for production use. They exist to make it easy to
+/// exercise server behavior, such as slow commands, from tests.
+///
+/// # Subcommands
+///
+/// Currently, the following subcommands are supported:
+///
+/// * SLEEP `seconds` -- block the connection for the given, possibly
+///   fractional, number of seconds and then reply `OK`.
+/// * POPULATE `count` [`prefix`] [`size`] -- create the string keys
+///   `prefix:0` to `prefix:<count - 1>`, `prefix` defaulting to `key`, and
+///   reply `OK`. Each value is `value:<n>`, padded with zero bytes or
+///   truncated to `size` bytes if given. Existing keys are left untouched.
+///   At most 100 million keys may be requested at once. With `maxmemory`
+///   set, keys are only created while memory can be kept within the limit;
+///   past it, the reply is an `OOM` error.
+/// * OBJECT `key` -- a single line of `field:value` pairs describing how the
+///   value at `key` is stored: its encoding, the length of its `DUMP`
+///   payload, its idle time and, for sorted sets, the number of members.
+///   Fields which only make sense in Redis, such as the address of the
+///   value, are reported with placeholder values. Replies with an error if
+///   the key does not exist.
+#[derive(Debug)]
+pub enum DebugCommand {
+    /// `DEBUG SLEEP seconds`
+    Sleep(Duration),
+
+    /// `DEBUG POPULATE count [prefix] [size]`
+    Populate {
+        count: u64,
+        prefix: String,
+        size: Option<usize>,
+    },
+
+    /// `DEBUG OBJECT key`
+    Object(String),
+}
+
+impl DebugCommand {
+    /// Parse a `DebugCommand` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `DEBUG` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `DebugCommand` value on success. If the frame is malformed
+    /// or the subcommand is not supported, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing the subcommand and its arguments.
+    ///
+    /// ```text
+    /// DEBUG SLEEP seconds
+    /// DEBUG POPULATE count [prefix] [size]
+    /// DEBUG OBJECT key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<DebugCommand> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "sleep" => {
+                // The duration may be fractional, e.g. `0.5`. Negative, NaN,
+                // infinite and too large values are rejected.
+                let secs = parse.next_f64()?;
+                let duration = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| "protocol error; invalid sleep duration")?;
+
+                Ok(DebugCommand::Sleep(duration))
+            }
+            "populate" => {
+                let count = parse.next_int()?;
+                if count > MAX_POPULATE_COUNT {
+                    return Err("protocol error; invalid key count".into());
+                }
+
+                let prefix = match parse.next_string() {
+                    Ok(prefix) => prefix,
+                    Err(ParseError::EndOfStream) => "key".to_string(),
+                    Err(err) => return Err(err.into()),
+                };
+
+                // Values are limited to 512MB, as in Redis.
+                let size = match parse.next_int() {
+                    Ok(size) if size <= 512 * 1024 * 1024 => Some(size as usize),
+                    Ok(_) => return Err("protocol error; invalid value size".into()),
+                    Err(ParseError::EndOfStream) => None,
+                    Err(err) => return Err(err.into()),
+                };
+
+                Ok(DebugCommand::Populate {
+                    count,
+                    prefix,
+                    size,
+                })
+            }
+            "object" => Ok(DebugCommand::Object(parse.next_string()?)),
+            _ => Err(format!("unknown subcommand '{}' for `DEBUG`", subcommand).into()),
+        }
+    }
+
+    /// Apply the `DebugCommand`.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        let response = match self {
+            DebugCommand::Sleep(duration) => {
+                // Sleep while still listening for the shutdown signal. A
+                // sleeping connection must not hold up graceful shutdown, so
+                // when the signal is received the sleep is abandoned and no
+                // response is sent. The handler then observes the shutdown and
+                // terminates the connection.
+                tokio::select! {
+                    _ = time::sleep(duration) => {}
+                    _ = shutdown.recv() => return Ok(()),
+                }
+
+                Frame::Simple("OK".to_string())
+            }
+            DebugCommand::Populate {
+                count,
+                prefix,
+                size,
+            } => populate(db, &prefix, count, size).await,
+            DebugCommand::Object(key) => match db.object_info(&key) {
+                Some(info) => Frame::Bulk(Bytes::from(describe(&info))),
+                None => CommandError::NoSuchKey.into(),
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Creates the keys requested by `DEBUG POPULATE` and returns the reply.
+///
+/// Keys are inserted in batches. The lock is released and the task yields to
+/// the runtime between batches, so other connections keep being served. When
+/// a memory limit is set, it is checked before each batch, as it is for
+/// commands flagged `denyoom`. Once no key can be evicted to stay within it,
+/// the remaining keys are not created and an error is returned. The keys
+/// already created are kept.
+async fn populate(db: &Db, prefix: &str, count: u64, size: Option<usize>) -> Frame {
+    let mut start = 0;
+
+    while start < count {
+        if !db.free_memory() {
+            return CommandError::OutOfMemory.into();
+        }
+
+        let end = count.min(start + POPULATE_BATCH);
+        db.populate(prefix, start..end, size);
+        start = end;
+
+        task::yield_now().await;
+    }
+
+    Frame::Simple("OK".to_string())
+}
+
+/// Formats the reply to `DEBUG OBJECT`, following the layout used by Redis.
+///
+/// There is no shared object pool nor a stable value address, so `at` and
+/// `refcount` are fixed, as in `OBJECT REFCOUNT`.
+fn describe(info: &ObjectInfo) -> String {
+    let mut line = format!(
+        "Value at:0x0 refcount:1 encoding:{} serializedlength:{} lru_seconds_idle:{}",
+        info.encoding,
+        info.serialized_length,
+        info.idle.as_secs()
+    );
+
+    if let Some(len) = info.len {
+        line.push_str(&format!(" length:{}", len));
+    }
+
+    line
+}
@@ -0,0 +1,200 @@
+use crate::db::{IncrError, LfuNotSelected, RestoreError, WrongType};
+use crate::Frame;
+
+use std::fmt;
+
+/// An error reply sent by a command.
+///
+/// Each variant renders to the message used by Redis, which starts with an
+/// error code such as `ERR` or `WRONGTYPE`. Clients can match on the code
+/// rather than on the wording of the message.
+///
+/// Commands return a `CommandError` and convert it into a `Frame::Error`
+/// just before writing the response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    /// The key holds a value of a type the command does not operate on.
+    WrongType,
+
+    /// The command name is not recognized. The second field holds the
+    /// leading arguments, which are echoed back to help identify the request.
+    UnknownCommand(String, Vec<String>),
+
+    /// The named command was called with the wrong number of arguments.
+    WrongArity(String),
+
+    /// The expire time given to the named command is out of range.
+    InvalidExpireTime(&'static str),
+
+    /// The value stored at the key is not a valid float.
+    NotAFloat,
+
+    /// The increment would produce NaN or infinity.
+    NanOrInfinity,
+
+    /// The client name contains spaces, newlines or special characters.
+    InvalidClientName,
+
+    /// The command asked to save the dataset, but `mini-redis` has no
+    /// persistence.
+    PersistenceUnsupported,
+
+    /// The target key of `RESTORE` already exists.
+    BusyKey,
+
+    /// The payload given to `RESTORE` was not produced by `DUMP`.
+    BadDumpPayload,
+
+    /// The server runs in read-only mode and the command writes.
+    ReadOnly,
+
+    /// Memory usage is above the configured limit and no key can be evicted
+    /// to make room for the command.
+    OutOfMemory,
+
+    /// `CONFIG SET` was given a parameter that does not exist or cannot be
+    /// changed at runtime.
+    UnknownConfig(String),
+
+    /// `CONFIG SET` was given an invalid value for the named parameter. The
+    /// second field explains what the value should look like.
+    InvalidConfig(&'static str, &'static str),
+
+    /// The named command cannot be run while the connection is subscribed to
+    /// channels.
+    NotAllowedWhileSubscribed(String),
+
+    /// The key given to a command which requires it to exist does not exist.
+    NoSuchKey,
+
+    /// `OBJECT FREQ` was called while the eviction policy is not
+    /// `allkeys-lfu`.
+    LfuNotSelected,
+
+    /// The table `LCS` needs to compare the two strings would be larger than
+    /// the bulk string limit.
+    LcsTooLarge,
+
+    /// The request could not be parsed into a command, e.g. an argument is
+    /// not a number or an option is not supported. The message describes the
+    /// problem.
+    InvalidRequest(String),
+}
+
+impl CommandError {
+    /// Returns the error code the message starts with, e.g. `"WRONGTYPE"`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CommandError::WrongType => "WRONGTYPE",
+            CommandError::BusyKey => "BUSYKEY",
+            CommandError::ReadOnly => "READONLY",
+            CommandError::OutOfMemory => "OOM",
+            _ => "ERR",
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{} ", self.code())?;
+
+        match self {
+            CommandError::WrongType => {
+                "Operation against a key holding the wrong kind of value".fmt(fmt)
+            }
+            CommandError::UnknownCommand(name, args) => {
+                write!(
+                    fmt,
+                    "unknown command '{}', with args beginning with: ",
+                    name
+                )?;
+
+                for arg in args {
+                    write!(fmt, "'{}' ", arg)?;
+                }
+
+                Ok(())
+            }
+            CommandError::WrongArity(name) => {
+                write!(fmt, "wrong number of arguments for '{}' command", name)
+            }
+            CommandError::InvalidExpireTime(command) => {
+                write!(fmt, "invalid expire time in '{}' command", command)
+            }
+            CommandError::NotAFloat => "value is not a valid float".fmt(fmt),
+            CommandError::NanOrInfinity => "increment would produce NaN or Infinity".fmt(fmt),
+            CommandError::InvalidClientName => {
+                "Client names cannot contain spaces, newlines or special characters.".fmt(fmt)
+            }
+            CommandError::PersistenceUnsupported => "persistence is not supported".fmt(fmt),
+            CommandError::BusyKey => "Target key name already exists.".fmt(fmt),
+            CommandError::BadDumpPayload => "DUMP payload version or format is wrong".fmt(fmt),
+            CommandError::ReadOnly => "You can't write against a read only replica.".fmt(fmt),
+            CommandError::OutOfMemory => {
+                "command not allowed when used memory > 'maxmemory'.".fmt(fmt)
+            }
+            CommandError::UnknownConfig(name) => write!(
+                fmt,
+                "Unknown option or number of arguments for CONFIG SET - '{}'",
+                name
+            ),
+            CommandError::InvalidConfig(name, reason) => write!(
+                fmt,
+                "CONFIG SET failed (possibly related to argument '{}') - {}",
+                name, reason
+            ),
+            CommandError::NotAllowedWhileSubscribed(name) => write!(
+                fmt,
+                "Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+                name
+            ),
+            CommandError::NoSuchKey => "no such key".fmt(fmt),
+            CommandError::LfuNotSelected => {
+                "An LFU maxmemory policy is not selected, access frequency not tracked.".fmt(fmt)
+            }
+            CommandError::LcsTooLarge => {
+                "Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len".fmt(fmt)
+            }
+            CommandError::InvalidRequest(msg) => msg.fmt(fmt),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<CommandError> for Frame {
+    fn from(err: CommandError) -> Frame {
+        Frame::Error(err.to_string())
+    }
+}
+
+impl From<RestoreError> for CommandError {
+    fn from(err: RestoreError) -> CommandError {
+        match err {
+            RestoreError::BusyKey => CommandError::BusyKey,
+            RestoreError::BadPayload => CommandError::BadDumpPayload,
+        }
+    }
+}
+
+impl From<WrongType> for CommandError {
+    fn from(_: WrongType) -> CommandError {
+        CommandError::WrongType
+    }
+}
+
+impl From<LfuNotSelected> for CommandError {
+    fn from(_: LfuNotSelected) -> CommandError {
+        CommandError::LfuNotSelected
+    }
+}
+
+impl From<IncrError> for CommandError {
+    fn from(err: IncrError) -> CommandError {
+        match err {
+            IncrError::WrongType => CommandError::WrongType,
+            IncrError::NotAFloat => CommandError::NotAFloat,
+            IncrError::NotFinite => CommandError::NanOrInfinity,
+        }
+    }
+}
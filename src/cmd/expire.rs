@@ -0,0 +1,168 @@
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use std::convert::TryFrom;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, instrument};
+
+/// Set a timeout on key, after which the key is deleted.
+///
+/// An option makes the change conditional on the current expiration of the
+/// key. A key without an expiration is treated as never expiring, so `GT`
+/// never applies to it and `LT` always does. A timeout of zero or less
+/// deletes the key right away.
+///
+/// Returns 1 if the timeout was set, or 0 if the key does not exist or the
+/// option rejected the change.
+#[derive(Debug)]
+pub struct Expire {
+    /// the lookup key
+    key: String,
+
+    /// the timeout, in seconds
+    seconds: i64,
+
+    /// the condition the current expiration must satisfy, if any
+    condition: Option<ExpireCondition>,
+}
+
+/// The condition under which `EXPIRE` changes the expiration of its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    /// Only if the key has no expiration, set by `NX`.
+    Nx,
+
+    /// Only if the key already has an expiration, set by `XX`.
+    Xx,
+
+    /// Only if the new expiration is later than the current one, set by `GT`.
+    Gt,
+
+    /// Only if the new expiration is earlier than the current one, set by
+    /// `LT`.
+    Lt,
+}
+
+impl Expire {
+    /// Create a new `Expire` command which expires `key` after `seconds`,
+    /// subject to `condition`.
+    pub fn new(key: impl ToString, seconds: i64, condition: Option<ExpireCondition>) -> Expire {
+        Expire {
+            key: key.to_string(),
+            seconds,
+            condition,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the timeout, in seconds
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Get the condition
+    pub fn condition(&self) -> Option<ExpireCondition> {
+        self.condition
+    }
+
+    /// Parse an `Expire` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `EXPIRE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Expire` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three or four entries.
+    ///
+    /// ```text
+    /// EXPIRE key seconds [NX | XX | GT | LT]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Expire> {
+        let key = parse.next_string()?;
+        let seconds = parse.next_i64()?;
+
+        let condition = match parse.next_string() {
+            Ok(s) => Some(match &s.to_uppercase()[..] {
+                "NX" => ExpireCondition::Nx,
+                "XX" => ExpireCondition::Xx,
+                "GT" => ExpireCondition::Gt,
+                "LT" => ExpireCondition::Lt,
+                _ => {
+                    return Err(
+                        format!("protocol error; unsupported `EXPIRE` option `{}`", s).into(),
+                    )
+                }
+            }),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Expire {
+            key,
+            seconds,
+            condition,
+        })
+    }
+
+    /// Apply the `Expire` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let now = Instant::now();
+
+        // A timeout of zero or less expires the key immediately.
+        let expires_at = match u64::try_from(self.seconds) {
+            Ok(seconds) => now.checked_add(Duration::from_secs(seconds)),
+            Err(_) => Some(now),
+        };
+
+        let response = match expires_at {
+            Some(expires_at) => {
+                let condition = self.condition;
+                let set = db.expire(&self.key, expires_at, |current| match condition {
+                    None => true,
+                    Some(condition) => condition.allows(current, expires_at),
+                });
+
+                Frame::Integer(set as i64)
+            }
+            // The expiration is too far in the future to represent.
+            None => CommandError::InvalidExpireTime("expire").into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl ExpireCondition {
+    /// Returns `true` if a key whose expiration is `current` may be given the
+    /// new expiration `new`. `None` means the key never expires.
+    fn allows(self, current: Option<Instant>, new: Instant) -> bool {
+        match (self, current) {
+            (ExpireCondition::Nx, current) => current.is_none(),
+            (ExpireCondition::Xx, current) => current.is_some(),
+            (ExpireCondition::Gt, Some(current)) => new > current,
+            (ExpireCondition::Gt, None) => false,
+            (ExpireCondition::Lt, Some(current)) => new < current,
+            (ExpireCondition::Lt, None) => true,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::cmd::CommandError;
 use crate::{Connection, Db, Frame, Parse};
 
 use bytes::Bytes;
@@ -63,13 +64,14 @@ impl Get {
     #[instrument(skip(self, db, dst))]
     pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
         // Get the value from the shared database state
-        let response = if let Some(value) = db.get(&self.key) {
+        let response = match db.get(&self.key) {
             // If a value is present, it is written to the client in "bulk"
             // format.
-            Frame::Bulk(value)
-        } else {
+            Ok(Some(value)) => Frame::Bulk(value),
             // If there is no value, `Null` is written.
-            Frame::Null
+            Ok(None) => Frame::Null,
+            // The key holds a value that is not a string.
+            Err(err) => CommandError::from(err).into(),
         };
 
         debug!(?response);
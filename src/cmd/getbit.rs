@@ -0,0 +1,97 @@
+use crate::cmd::setbit::MAX_BIT_OFFSET;
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Returns the bit at `offset` in the string value stored at key.
+///
+/// Offsets past the end of the string, and missing keys, are treated as if
+/// the string were padded with zero bits, so the reply is `0`.
+#[derive(Debug)]
+pub struct GetBit {
+    /// the lookup key
+    key: String,
+
+    /// offset of the bit, counted from the most significant bit of the first
+    /// byte
+    offset: u64,
+}
+
+impl GetBit {
+    /// Create a new `GetBit` command which reads the bit at `offset` in the
+    /// value stored at `key`.
+    pub fn new(key: impl ToString, offset: u64) -> GetBit {
+        GetBit {
+            key: key.to_string(),
+            offset,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the bit offset
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Parse a `GetBit` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETBIT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetBit` value on success. If the frame is malformed or the
+    /// offset is out of range, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// GETBIT key offset
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetBit> {
+        let key = parse.next_string()?;
+
+        let offset = parse.next_int()?;
+        if offset > MAX_BIT_OFFSET {
+            return Err("protocol error; bit offset is out of range".into());
+        }
+
+        Ok(GetBit { key, offset })
+    }
+
+    /// Apply the `GetBit` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let byte = (self.offset / 8) as usize;
+        let mask = 0x80 >> (self.offset % 8);
+
+        let response = match db.get(&self.key) {
+            Ok(value) => {
+                let bit = value
+                    .and_then(|value| value.get(byte).map(|b| b & mask != 0))
+                    .unwrap_or(false);
+
+                Frame::Integer(bit as i64)
+            }
+            Err(err) => CommandError::from(err).into(),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+use tracing::{debug, instrument};
+
+/// Get the value of key and optionally change its expiration.
+///
+/// Without an option this behaves exactly like `GET`. The value is read and
+/// the expiration updated together, so no other command can run in between.
+/// An error is returned if the value stored at key is not a string.
+#[derive(Debug)]
+pub struct GetEx {
+    /// Name of the key to get
+    key: String,
+
+    /// How to change the expiration, if at all
+    expiry: Option<Expiry>,
+}
+
+/// The change `GETEX` makes to the expiration of its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    /// Expire once the duration has elapsed, set by `EX` and `PX`.
+    After(Duration),
+
+    /// Expire at a point in time, set by `EXAT` and `PXAT`. A time in the past
+    /// expires the key immediately.
+    At(SystemTime),
+
+    /// Remove the expiration, set by `PERSIST`.
+    Persist,
+}
+
+impl GetEx {
+    /// Create a new `GetEx` command which fetches `key` and applies `expiry`.
+    pub fn new(key: impl ToString, expiry: Option<Expiry>) -> GetEx {
+        GetEx {
+            key: key.to_string(),
+            expiry,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the expiration change
+    pub fn expiry(&self) -> Option<Expiry> {
+        self.expiry
+    }
+
+    /// Parse a `GetEx` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETEX` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetEx` value on success. If the frame is malformed or the
+    /// expire time is not a positive integer, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two to four entries.
+    ///
+    /// ```text
+    /// GETEX key [EX seconds | PX milliseconds | EXAT timestamp | PXAT timestamp | PERSIST]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetEx> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        let expiry = match parse.next_string() {
+            Ok(s) => Some(match &s.to_uppercase()[..] {
+                "EX" => Expiry::After(Duration::from_secs(next_expire_time(parse)?)),
+                "PX" => Expiry::After(Duration::from_millis(next_expire_time(parse)?)),
+                "EXAT" => Expiry::At(unix_time(Duration::from_secs(next_expire_time(parse)?))?),
+                "PXAT" => Expiry::At(unix_time(Duration::from_millis(next_expire_time(parse)?))?),
+                "PERSIST" => Expiry::Persist,
+                _ => {
+                    return Err(
+                        format!("protocol error; unsupported `GETEX` option `{}`", s).into(),
+                    )
+                }
+            }),
+            // No option was given, the expiration is left untouched.
+            Err(EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(GetEx { key, expiry })
+    }
+
+    /// Apply the `GetEx` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let value = match self.expiry {
+            None => db.get(&self.key),
+            Some(Expiry::Persist) => db.get_and_expire(&self.key, None),
+            Some(expiry) => match expiry.deadline() {
+                Some(when) => db.get_and_expire(&self.key, Some(when)),
+                None => {
+                    // The expiration is too far in the future to represent.
+                    let response = Frame::from(CommandError::InvalidExpireTime("getex"));
+                    debug!(?response);
+
+                    dst.write_frame(&response).await?;
+                    return Ok(());
+                }
+            },
+        };
+
+        let response = match value {
+            Ok(Some(value)) => Frame::Bulk(value),
+            Ok(None) => Frame::Null,
+            Err(err) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+impl Expiry {
+    /// Returns the instant at which the key should expire, or `None` if there
+    /// is no such instant: either the expiration is being removed or it is too
+    /// far in the future to represent.
+    fn deadline(self) -> Option<Instant> {
+        match self {
+            Expiry::After(duration) => Instant::now().checked_add(duration),
+            Expiry::At(time) => {
+                let duration = time.duration_since(SystemTime::now()).unwrap_or_default();
+                Instant::now().checked_add(duration)
+            }
+            Expiry::Persist => None,
+        }
+    }
+}
+
+/// Read an expire time, which must be a positive integer as in Redis.
+fn next_expire_time(parse: &mut Parse) -> crate::Result<u64> {
+    match parse.next_int()? {
+        0 => Err("protocol error; invalid expire time in `GETEX`".into()),
+        time => Ok(time),
+    }
+}
+
+/// Convert a duration since the Unix epoch into a `SystemTime`.
+fn unix_time(since_epoch: Duration) -> crate::Result<SystemTime> {
+    UNIX_EPOCH
+        .checked_add(since_epoch)
+        .ok_or_else(|| "protocol error; invalid expire time in `GETEX`".into())
+}
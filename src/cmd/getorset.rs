@@ -0,0 +1,103 @@
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Get the value of `key`, setting it to `default` first if it does not exist.
+///
+/// The value is always returned: either the existing one or `default`, which
+/// is then stored without an expiration. The lookup and the insert happen
+/// atomically, so clients racing on the same key all get the same value. This
+/// suits cache-aside patterns where only one client should populate a key.
+///
+/// An error is returned if the value stored at key is not a string.
+///
+/// This is not a Redis command.
+#[derive(Debug)]
+pub struct GetOrSet {
+    /// Name of the key to get
+    key: String,
+
+    /// Value stored if the key does not exist
+    default: Bytes,
+}
+
+impl GetOrSet {
+    /// Create a new `GetOrSet` command which fetches `key`, storing `default`
+    /// if it does not exist.
+    pub fn new(key: impl ToString, default: Bytes) -> GetOrSet {
+        GetOrSet {
+            key: key.to_string(),
+            default,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the default value
+    pub fn default(&self) -> &Bytes {
+        &self.default
+    }
+
+    /// Parse a `GetOrSet` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETORSET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetOrSet` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// GETORSET key default
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetOrSet> {
+        let key = parse.next_string()?;
+        let default = parse.next_bytes()?;
+
+        Ok(GetOrSet { key, default })
+    }
+
+    /// Apply the `GetOrSet` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.get_or_set(&self.key, self.default) {
+            Ok(value) => Frame::Bulk(value),
+            // The key holds a value that is not a string.
+            Err(err) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `GetOrSet` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("getorset".as_bytes()));
+        frame.push_bulk(Bytes::from(self.key.into_bytes()));
+        frame.push_bulk(self.default);
+        frame
+    }
+}
@@ -0,0 +1,105 @@
+use crate::cmd::bitcount::byte_range;
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the substring of the string value stored at key between the
+/// inclusive offsets `start` and `end`.
+///
+/// Negative offsets count from the end of the string, `-1` being the last
+/// byte. The range is clamped to the string. A missing key, or a range that
+/// selects nothing, results in an empty string rather than nil.
+#[derive(Debug)]
+pub struct GetRange {
+    /// the lookup key
+    key: String,
+
+    /// offset of the first byte to return
+    start: i64,
+
+    /// offset of the last byte to return
+    end: i64,
+}
+
+impl GetRange {
+    /// Create a new `GetRange` command which returns the bytes between `start`
+    /// and `end` of the value stored at `key`.
+    pub fn new(key: impl ToString, start: i64, end: i64) -> GetRange {
+        GetRange {
+            key: key.to_string(),
+            start,
+            end,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the start offset
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the end offset
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+
+    /// Parse a `GetRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `GETRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `GetRange` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// GETRANGE key start end
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<GetRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_i64()?;
+        let end = parse.next_i64()?;
+
+        Ok(GetRange { key, start, end })
+    }
+
+    /// Apply the `GetRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.get(&self.key) {
+            Ok(value) => {
+                let value = value.unwrap_or_default();
+
+                // Slicing `Bytes` is shallow, the data is not copied.
+                match byte_range(self.start, self.end, value.len()) {
+                    Some((start, end)) => Frame::Bulk(value.slice(start..=end)),
+                    None => Frame::Bulk(Bytes::new()),
+                }
+            }
+            Err(err) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Increments the floating-point number stored at key by `increment`.
+///
+/// A missing key is treated as `0`. Replies with the new value as a bulk
+/// string, without trailing zeros or scientific notation. An error is replied
+/// if the stored value is not a valid float or the result would be NaN or
+/// infinite. Any existing expiration is kept.
+#[derive(Debug)]
+pub struct IncrByFloat {
+    /// the lookup key
+    key: String,
+
+    /// the amount to add
+    increment: f64,
+}
+
+impl IncrByFloat {
+    /// Create a new `IncrByFloat` command which adds `increment` to the value
+    /// stored at `key`.
+    pub fn new(key: impl ToString, increment: f64) -> IncrByFloat {
+        IncrByFloat {
+            key: key.to_string(),
+            increment,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the increment
+    pub fn increment(&self) -> f64 {
+        self.increment
+    }
+
+    /// Parse an `IncrByFloat` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `INCRBYFLOAT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `IncrByFloat` value on success. If the frame is malformed or
+    /// the increment is not a valid float, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// INCRBYFLOAT key increment
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<IncrByFloat> {
+        let key = parse.next_string()?;
+        let increment = parse.next_f64()?;
+
+        Ok(IncrByFloat { key, increment })
+    }
+
+    /// Apply the `IncrByFloat` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.incr_by_float(&self.key, self.increment) {
+            Ok(value) => Frame::Bulk(value),
+            Err(err) => CommandError::from(err).into(),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame, Session};
+
+use bytes::Bytes;
+use std::fmt::Write;
+use tracing::{debug, instrument};
+
+/// Return information and statistics about the server.
+///
+/// The reply is a bulk string made of sections. Each section starts with a
+/// `# Name` header followed by one `field:value` line per statistic, in the
+/// same format used by Redis.
+///
+/// The following sections are supported:
+///
+/// * server -- the server version, process id and uptime.
+/// * clients -- the number of connected clients.
+/// * memory -- the approximate memory used by the keyspace.
+/// * stats -- totals of connections, commands, network traffic and error
+///   replies, and pub/sub channels.
+/// * commandstats -- the number of calls of each command.
+/// * keyspace -- the number of keys, and of keys with an expiration.
+///
+/// Without an argument, or with `default`, every section except
+/// `commandstats` is returned. `all` and `everything` return every section.
+/// Unknown sections produce an empty reply.
+#[derive(Debug)]
+pub struct Info {
+    /// The requested section, if any
+    section: Option<String>,
+}
+
+/// Every supported section, in the order they are reported.
+const SECTIONS: &[&str] = &[
+    "server",
+    "clients",
+    "memory",
+    "stats",
+    "commandstats",
+    "keyspace",
+];
+
+/// Sections only reported when requested explicitly or with `all`, as in
+/// Redis.
+const NON_DEFAULT_SECTIONS: &[&str] = &["commandstats"];
+
+impl Info {
+    /// Create a new `Info` command which returns `section`, or every section
+    /// if `None`.
+    pub fn new(section: Option<String>) -> Info {
+        Info { section }
+    }
+
+    /// Get the requested section
+    pub fn section(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+
+    /// Parse an `Info` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `INFO` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Info` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing one or two entries.
+    ///
+    /// ```text
+    /// INFO [section]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Info> {
+        match parse.next_string() {
+            Ok(section) => Ok(Info::new(Some(section.to_lowercase()))),
+            Err(ParseError::EndOfStream) => Ok(Info::new(None)),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Apply the `Info` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, session))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        session: &Session,
+    ) -> crate::Result<()> {
+        let sections: Vec<&str> = match self.section.as_deref() {
+            None | Some("default") => SECTIONS
+                .iter()
+                .copied()
+                .filter(|name| !NON_DEFAULT_SECTIONS.contains(name))
+                .collect(),
+            Some("all") | Some("everything") => SECTIONS.to_vec(),
+            Some(section) => SECTIONS
+                .iter()
+                .copied()
+                .filter(|name| *name == section)
+                .collect(),
+        };
+
+        let mut info = String::new();
+
+        for (i, section) in sections.into_iter().enumerate() {
+            // Sections are separated by an empty line
+            if i > 0 {
+                info.push_str("\r\n");
+            }
+
+            write_section(&mut info, section, db, session);
+        }
+
+        let response = Frame::Bulk(Bytes::from(info.into_bytes()));
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Append the section named `section` to `dst`.
+///
+/// Writing to a `String` cannot fail, so the results of `write!` are ignored.
+fn write_section(dst: &mut String, section: &str, db: &Db, session: &Session) {
+    match section {
+        "server" => {
+            let uptime = session.stats().uptime().as_secs();
+
+            let _ = write!(dst, "# Server\r\n");
+            let _ = write!(dst, "redis_version:{}\r\n", env!("CARGO_PKG_VERSION"));
+            let _ = write!(dst, "process_id:{}\r\n", std::process::id());
+            let _ = write!(dst, "uptime_in_seconds:{}\r\n", uptime);
+            let _ = write!(dst, "uptime_in_days:{}\r\n", uptime / (24 * 60 * 60));
+        }
+        "clients" => {
+            let _ = write!(dst, "# Clients\r\n");
+            let _ = write!(
+                dst,
+                "connected_clients:{}\r\n",
+                session.stats().active_connections()
+            );
+        }
+        "memory" => {
+            let _ = write!(dst, "# Memory\r\n");
+            let _ = write!(dst, "used_memory:{}\r\n", db.approx_memory_usage());
+        }
+        "stats" => {
+            let stats = session.stats();
+            let db_stats = db.stats();
+
+            let _ = write!(dst, "# Stats\r\n");
+            let _ = write!(
+                dst,
+                "total_connections_received:{}\r\n",
+                stats.connections_accepted()
+            );
+            let _ = write!(
+                dst,
+                "total_commands_processed:{}\r\n",
+                stats.total_commands()
+            );
+            let _ = write!(dst, "total_net_input_bytes:{}\r\n", stats.net_input_bytes());
+            let _ = write!(
+                dst,
+                "total_net_output_bytes:{}\r\n",
+                stats.net_output_bytes()
+            );
+            let _ = write!(dst, "total_error_replies:{}\r\n", stats.error_replies());
+            let _ = write!(dst, "evicted_keys:{}\r\n", db_stats.evicted_keys);
+            let _ = write!(dst, "pubsub_channels:{}\r\n", db_stats.channels);
+            let _ = write!(dst, "pubsub_subscriptions:{}\r\n", db_stats.subscribers);
+        }
+        "commandstats" => {
+            let _ = write!(dst, "# Commandstats\r\n");
+
+            for (name, calls) in session.stats().commands() {
+                let _ = write!(dst, "cmdstat_{}:calls={}\r\n", name, calls);
+            }
+        }
+        "keyspace" => {
+            let stats = db.stats();
+
+            let _ = write!(dst, "# Keyspace\r\n");
+
+            // As in Redis, empty databases are not listed
+            if stats.keys > 0 {
+                let _ = write!(dst, "db0:keys={},expires={}\r\n", stats.keys, stats.expires);
+            }
+        }
+        _ => unreachable!(),
+    }
+}
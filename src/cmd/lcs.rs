@@ -0,0 +1,262 @@
+use crate::cmd::{CommandError, Parse};
+use crate::{Connection, Db, Frame, Session};
+
+use bytes::Bytes;
+use std::mem;
+use tracing::{debug, instrument};
+
+/// Finds the longest common subsequence between the string values stored at
+/// two keys.
+///
+/// A missing key counts as an empty string. By default the reply is the
+/// subsequence itself. With `LEN`, only its length is returned.
+///
+/// With `IDX`, the reply describes where the subsequence is found in both
+/// strings, as an array of `matches` followed by the `len` of the
+/// subsequence. Each match is a run of consecutive bytes common to both
+/// strings, given as the inclusive `[start, end]` ranges in the first and the
+/// second string. Matches are listed from the end of the strings towards
+/// their start. `MINMATCHLEN` omits matches shorter than the given length and
+/// `WITHMATCHLEN` appends the length to each match.
+///
+/// Comparing strings of `m` and `n` bytes takes `O(m * n)` time and memory.
+/// The command is rejected if the memory it needs exceeds the bulk string
+/// limit, `proto-max-bulk-len`.
+#[derive(Debug)]
+pub struct Lcs {
+    /// key of the first string
+    key1: String,
+
+    /// key of the second string
+    key2: String,
+
+    /// reply with the length of the subsequence only
+    len: bool,
+
+    /// reply with the positions of the matches
+    idx: bool,
+
+    /// shortest match reported with `IDX`
+    min_match_len: usize,
+
+    /// include the length of each match reported with `IDX`
+    with_match_len: bool,
+}
+
+/// A run of consecutive bytes common to both strings, as inclusive ranges.
+#[derive(Debug)]
+struct Match {
+    /// positions in the first string
+    a: (usize, usize),
+
+    /// positions in the second string
+    b: (usize, usize),
+}
+
+impl Lcs {
+    /// Create a new `Lcs` command which replies with the longest common
+    /// subsequence between the values of `key1` and `key2`.
+    pub fn new(key1: impl ToString, key2: impl ToString) -> Lcs {
+        Lcs {
+            key1: key1.to_string(),
+            key2: key2.to_string(),
+            len: false,
+            idx: false,
+            min_match_len: 0,
+            with_match_len: false,
+        }
+    }
+
+    /// Get the key of the first string
+    pub fn key(&self) -> &str {
+        &self.key1
+    }
+
+    /// Parse a `Lcs` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `LCS` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Lcs` value on success. If the frame is malformed, an
+    /// option is not supported or both `LEN` and `IDX` are given, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// LCS key1 key2 [LEN] [IDX] [MINMATCHLEN len] [WITHMATCHLEN]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Lcs> {
+        let mut lcs = Lcs::new(parse.next_string()?, parse.next_string()?);
+
+        while parse.remaining() > 0 {
+            let option = parse.next_string()?;
+
+            match &option.to_uppercase()[..] {
+                "LEN" => lcs.len = true,
+                "IDX" => lcs.idx = true,
+                "MINMATCHLEN" => lcs.min_match_len = parse.next_int()? as usize,
+                "WITHMATCHLEN" => lcs.with_match_len = true,
+                _ => {
+                    return Err(
+                        format!("protocol error; unsupported `LCS` option `{}`", option).into(),
+                    )
+                }
+            }
+        }
+
+        if lcs.len && lcs.idx {
+            return Err("protocol error; `LCS` takes either `LEN` or `IDX`, not both".into());
+        }
+
+        Ok(lcs)
+    }
+
+    /// Apply the `Lcs` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst, session))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        session: &mut Session,
+    ) -> crate::Result<()> {
+        let max_bulk_len = session.config().lock().unwrap().max_bulk_len;
+
+        let response = match db.get_pair(&self.key1, &self.key2) {
+            Ok((a, b)) => {
+                let a = a.unwrap_or_default();
+                let b = b.unwrap_or_default();
+
+                // The table holds one entry per pair of prefixes.
+                let table_size = (a.len() + 1)
+                    .checked_mul(b.len() + 1)
+                    .and_then(|entries| entries.checked_mul(mem::size_of::<u32>()));
+
+                match table_size {
+                    Some(size) if size <= max_bulk_len => self.reply(&a, &b),
+                    _ => CommandError::LcsTooLarge.into(),
+                }
+            }
+            Err(err) => CommandError::from(err).into(),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Compute the reply for the strings `a` and `b`.
+    fn reply(&self, a: &[u8], b: &[u8]) -> Frame {
+        // `table[i * width + j]` is the length of the longest common
+        // subsequence between the first `i` bytes of `a` and the first `j`
+        // bytes of `b`.
+        let width = b.len() + 1;
+        let mut table = vec![0u32; (a.len() + 1) * width];
+
+        for i in 1..=a.len() {
+            for j in 1..=b.len() {
+                table[i * width + j] = if a[i - 1] == b[j - 1] {
+                    table[(i - 1) * width + j - 1] + 1
+                } else {
+                    table[(i - 1) * width + j].max(table[i * width + j - 1])
+                };
+            }
+        }
+
+        let len = table[a.len() * width + b.len()];
+
+        if self.len {
+            return Frame::Integer(len as i64);
+        }
+
+        // Walk the table back from the end of both strings, collecting the
+        // subsequence in reverse along with the runs of consecutive matches.
+        let mut subsequence = Vec::with_capacity(len as usize);
+        let mut matches = Vec::new();
+        let mut current: Option<Match> = None;
+        let (mut i, mut j) = (a.len(), b.len());
+
+        while i > 0 && j > 0 {
+            if a[i - 1] == b[j - 1] {
+                subsequence.push(a[i - 1]);
+
+                current = Some(match current {
+                    Some(m) => Match {
+                        a: (i - 1, m.a.1),
+                        b: (j - 1, m.b.1),
+                    },
+                    None => Match {
+                        a: (i - 1, i - 1),
+                        b: (j - 1, j - 1),
+                    },
+                });
+
+                i -= 1;
+                j -= 1;
+            } else {
+                // Ties are broken the same way as Redis, so the same matches
+                // are reported.
+                if table[(i - 1) * width + j] > table[i * width + j - 1] {
+                    i -= 1;
+                } else {
+                    j -= 1;
+                }
+
+                matches.extend(current.take());
+            }
+        }
+        matches.extend(current);
+
+        if !self.idx {
+            subsequence.reverse();
+            return Frame::Bulk(Bytes::from(subsequence));
+        }
+
+        let range = |(start, end): (usize, usize)| {
+            Frame::Array(vec![
+                Frame::Integer(start as i64),
+                Frame::Integer(end as i64),
+            ])
+        };
+
+        let matches = matches
+            .into_iter()
+            .filter(|m| m.len() >= self.min_match_len)
+            .map(|m| {
+                let len = m.len();
+                let mut entry = vec![range(m.a), range(m.b)];
+
+                if self.with_match_len {
+                    entry.push(Frame::Integer(len as i64));
+                }
+
+                Frame::Array(entry)
+            })
+            .collect();
+
+        Frame::Array(vec![
+            Frame::Bulk(Bytes::from("matches")),
+            Frame::Array(matches),
+            Frame::Bulk(Bytes::from("len")),
+            Frame::Integer(len as i64),
+        ])
+    }
+}
+
+impl Match {
+    /// Number of bytes in the match.
+    fn len(&self) -> usize {
+        self.a.1 - self.a.0 + 1
+    }
+}
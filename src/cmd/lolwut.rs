@@ -0,0 +1,92 @@
+use crate::{Connection, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Width of the generated art, in characters.
+const WIDTH: usize = 40;
+
+/// Height of the generated art, in lines.
+const HEIGHT: usize = 10;
+
+/// Display a piece of generated art followed by the server version.
+///
+/// Redis draws a different picture for each major version. `mini-redis`
+/// always draws the same small pattern, so the optional `VERSION` argument is
+/// accepted and ignored.
+#[derive(Debug, Default)]
+pub struct Lolwut;
+
+impl Lolwut {
+    /// Create a new `Lolwut` command.
+    pub fn new() -> Lolwut {
+        Lolwut
+    }
+
+    /// Parse a `Lolwut` instance from a received frame.
+    ///
+    /// The `LOLWUT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `LOLWUT` and an optional version.
+    ///
+    /// ```text
+    /// LOLWUT [VERSION version]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Lolwut> {
+        match parse.next_string() {
+            Ok(s) if s.to_lowercase() == "version" => {
+                // The version must still be a number, even though it does not
+                // change the output.
+                parse.next_int()?;
+                Ok(Lolwut)
+            }
+            Ok(_) => Err("currently `LOLWUT` only supports the `VERSION` option".into()),
+            Err(ParseError::EndOfStream) => Ok(Lolwut),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Apply the `Lolwut` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let mut output = art();
+        output.push_str(&format!("mini-redis ver. {}\n", env!("CARGO_PKG_VERSION")));
+
+        let response = Frame::Bulk(Bytes::from(output));
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Draw overlapping ripples, shading each cell by the distance from two
+/// centers.
+fn art() -> String {
+    const SHADES: [char; 4] = [' ', '░', '▒', '▓'];
+
+    let mut output = String::new();
+
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            // Characters are about twice as tall as they are wide.
+            let (x, y) = (x as f64 / 2.0, y as f64);
+            let a = ((x - 5.0).powi(2) + (y - 3.0).powi(2)).sqrt();
+            let b = ((x - 15.0).powi(2) + (y - 6.0).powi(2)).sqrt();
+
+            let shade = (a + b) as usize % SHADES.len();
+            output.push(SHADES[shade]);
+        }
+
+        output.push('\n');
+    }
+
+    output
+}
@@ -0,0 +1,88 @@
+use crate::cmd::ParseError;
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Report on the memory used by the server.
+///
+/// # Subcommands
+///
+/// Currently, the following subcommands are supported:
+///
+/// * USAGE `key` [SAMPLES `count`] -- the approximate number of bytes used by
+///   the key and its value, or nil if the key does not exist. The estimate is
+///   the same one used for `maxmemory` and includes the key itself, its
+///   expiration and its slot in the keyspace. By default every member of a
+///   sorted set is counted; with `SAMPLES`, only the first `count` members
+///   are, and the total is extrapolated from them. A `count` of `0` counts
+///   every member.
+#[derive(Debug)]
+pub enum Memory {
+    /// `MEMORY USAGE key [SAMPLES count]`
+    Usage(String, Option<usize>),
+}
+
+impl Memory {
+    /// Parse a `Memory` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `MEMORY` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Memory` value on success. If the frame is malformed or the
+    /// subcommand or option is not supported, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three or five entries.
+    ///
+    /// ```text
+    /// MEMORY USAGE key [SAMPLES count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Memory> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        if subcommand != "usage" {
+            return Err(format!("unknown subcommand '{}' for `MEMORY`", subcommand).into());
+        }
+
+        let key = parse.next_string()?;
+
+        let samples = match parse.next_string() {
+            Ok(s) if s.eq_ignore_ascii_case("samples") => Some(parse.next_int()? as usize),
+            Ok(s) => {
+                return Err(
+                    format!("protocol error; unsupported `MEMORY USAGE` option `{}`", s).into(),
+                )
+            }
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Memory::Usage(key, samples))
+    }
+
+    /// Apply the `Memory` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match self {
+            Memory::Usage(key, samples) => match db.memory_usage(&key, samples) {
+                Some(usage) => Frame::Integer(usage as i64),
+                None => Frame::Null,
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
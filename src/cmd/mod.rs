@@ -13,10 +13,128 @@ pub use subscribe::{Subscribe, Unsubscribe};
 mod ping;
 pub use ping::Ping;
 
+mod random_key;
+pub use random_key::RandomKey;
+
+mod touch;
+pub use touch::Touch;
+
+mod object;
+pub use object::Object;
+
+mod wait;
+pub use wait::Wait;
+
+mod debug;
+pub use debug::DebugCommand;
+
+mod client;
+pub use client::{ClientCommand, KillFilter};
+
+mod command;
+pub use command::CommandCommand;
+pub(crate) use command::{CommandSpec, COMMAND_TABLE};
+
+mod info;
+pub use info::Info;
+
+mod reset;
+pub use reset::Reset;
+
+mod setbit;
+pub use setbit::SetBit;
+
+mod getbit;
+pub use getbit::GetBit;
+
+mod bitcount;
+pub use bitcount::BitCount;
+
+mod getrange;
+pub use getrange::GetRange;
+
+mod setrange;
+pub use setrange::SetRange;
+
+mod zadd;
+pub use zadd::ZAdd;
+
+mod zscore;
+pub use zscore::ZScore;
+
+mod zrank;
+pub use zrank::ZRank;
+
+mod zrange;
+pub use zrange::ZRange;
+
+mod zrangebyscore;
+pub use zrangebyscore::ZRangeByScore;
+
+mod zrem;
+pub use zrem::ZRem;
+
+mod incrbyfloat;
+pub use incrbyfloat::IncrByFloat;
+
+mod getex;
+pub use getex::{Expiry, GetEx};
+
+mod shutdown;
+pub use shutdown::ShutdownCommand;
+
+mod getorset;
+pub use getorset::GetOrSet;
+
+mod zscan;
+pub use zscan::ZScan;
+
+mod dump;
+pub use dump::Dump;
+
+mod restore;
+pub use restore::Restore;
+
+mod lolwut;
+pub use lolwut::Lolwut;
+
+mod slowlog;
+pub use slowlog::SlowLogCommand;
+
+mod config;
+pub use config::ConfigCommand;
+
+mod zrandmember;
+pub use zrandmember::ZRandMember;
+
+mod expire;
+pub use expire::{Expire, ExpireCondition};
+
+mod role;
+pub use role::Role;
+
+mod bitpos;
+pub use bitpos::BitPos;
+
+mod bitop;
+pub use bitop::{BitOp, BitOperation};
+
+mod memory;
+pub use memory::Memory;
+
+mod lcs;
+pub use lcs::Lcs;
+
+mod time;
+pub use time::Time;
+
 mod unknown;
 pub use unknown::Unknown;
 
-use crate::{Connection, Db, Frame, Parse, ParseError, Shutdown};
+mod error;
+pub use error::CommandError;
+
+use crate::{Connection, Db, Frame, Parse, ParseError, Session, Shutdown};
 
 /// Enumeration of supported Redis commands.
 ///
@@ -29,6 +147,44 @@ pub enum Command {
     Subscribe(Subscribe),
     Unsubscribe(Unsubscribe),
     Ping(Ping),
+    RandomKey(RandomKey),
+    Touch(Touch),
+    Object(Object),
+    Wait(Wait),
+    Debug(DebugCommand),
+    Client(ClientCommand),
+    Command(CommandCommand),
+    Info(Info),
+    Reset(Reset),
+    SetBit(SetBit),
+    GetBit(GetBit),
+    BitCount(BitCount),
+    GetRange(GetRange),
+    SetRange(SetRange),
+    ZAdd(ZAdd),
+    ZScore(ZScore),
+    ZRank(ZRank),
+    ZRange(ZRange),
+    ZRangeByScore(ZRangeByScore),
+    ZRem(ZRem),
+    IncrByFloat(IncrByFloat),
+    GetEx(GetEx),
+    Shutdown(ShutdownCommand),
+    GetOrSet(GetOrSet),
+    ZScan(ZScan),
+    Dump(Dump),
+    Restore(Restore),
+    Lolwut(Lolwut),
+    SlowLog(SlowLogCommand),
+    Config(ConfigCommand),
+    ZRandMember(ZRandMember),
+    Expire(Expire),
+    Role(Role),
+    BitPos(BitPos),
+    BitOp(BitOp),
+    Memory(Memory),
+    Lcs(Lcs),
+    Time(Time),
     Unknown(Unknown),
 }
 
@@ -41,6 +197,10 @@ impl Command {
     /// # Returns
     ///
     /// On success, the command value is returned, otherwise, `Err` is returned.
+    ///
+    /// If a known command is called with the wrong number of arguments, the
+    /// error is a `CommandError::WrongArity`. The caller should reply with it
+    /// and keep the connection open, as Redis does.
     pub fn from_frame(frame: Frame) -> crate::Result<Command> {
         // The frame value is decorated with `Parse`. `Parse` provides a
         // "cursor" like API which makes parsing the command easier.
@@ -52,28 +212,46 @@ impl Command {
         // All redis commands begin with the command name as a string. The name
         // is read and converted to lower cases in order to do case sensitive
         // matching.
-        let command_name = parse.next_string()?.to_lowercase();
-
-        // Match the command name, delegating the rest of the parsing to the
-        // specific command.
-        let command = match &command_name[..] {
-            "get" => Command::Get(Get::parse_frames(&mut parse)?),
-            "publish" => Command::Publish(Publish::parse_frames(&mut parse)?),
-            "set" => Command::Set(Set::parse_frames(&mut parse)?),
-            "subscribe" => Command::Subscribe(Subscribe::parse_frames(&mut parse)?),
-            "unsubscribe" => Command::Unsubscribe(Unsubscribe::parse_frames(&mut parse)?),
-            "ping" => Command::Ping(Ping::parse_frames(&mut parse)?),
-            _ => {
-                // The command is not recognized and an Unknown command is
-                // returned.
-                //
-                // `return` is called here to skip the `finish()` call below. As
-                // the command is not recognized, there is most likely
-                // unconsumed fields remaining in the `Parse` instance.
-                return Ok(Command::Unknown(Unknown::new(command_name)));
+        let name = parse.next_string()?;
+        let command_name = name.to_lowercase();
+
+        // Look up the command in the command table. Unknown commands are
+        // returned as is, together with their arguments, and the caller
+        // replies with an error echoing them. The name keeps the case used by
+        // the client.
+        //
+        // `return` is called here to skip the `finish()` call below, as the
+        // arguments have already been consumed.
+        let spec = match CommandSpec::lookup(&command_name) {
+            Some(spec) => spec,
+            None => {
+                let mut args = Vec::new();
+                while parse.remaining() > 0 {
+                    args.push(parse.next_bytes()?);
+                }
+
+                return Ok(Command::Unknown(Unknown::new(name, args)));
             }
         };
 
+        // Reject a known command called with the wrong number of arguments
+        // before parsing it. The name itself counts as an argument.
+        if !spec.accepts_arg_count(parse.remaining() + 1) {
+            return Err(CommandError::WrongArity(command_name).into());
+        }
+
+        // Delegate the rest of the parsing to the specific command.
+        //
+        // Some commands take a variable number of arguments but only in
+        // specific shapes, e.g. `GETEX key EX` lacks the expire time. Running
+        // out of arguments while parsing is reported as a wrong arity too.
+        let command = match (spec.parse)(&mut parse) {
+            Err(err) if is_end_of_stream(&err) => {
+                return Err(CommandError::WrongArity(command_name).into())
+            }
+            res => res?,
+        };
+
         // Check if there is any remaining unconsumed fields in the `Parse`
         // value. If fields remain, this indicates an unexpected frame format
         // and an error is returned.
@@ -92,15 +270,54 @@ impl Command {
         db: &Db,
         dst: &mut Connection,
         shutdown: &mut Shutdown,
+        session: &mut Session,
     ) -> crate::Result<()> {
-        use Command::*;
+        use self::Command::*;
 
         match self {
             Get(cmd) => cmd.apply(db, dst).await,
             Publish(cmd) => cmd.apply(db, dst).await,
             Set(cmd) => cmd.apply(db, dst).await,
-            Subscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            Subscribe(cmd) => cmd.apply(db, dst, shutdown, session).await,
             Ping(cmd) => cmd.apply(dst).await,
+            RandomKey(cmd) => cmd.apply(db, dst).await,
+            Touch(cmd) => cmd.apply(db, dst).await,
+            Object(cmd) => cmd.apply(db, dst).await,
+            Wait(cmd) => cmd.apply(dst).await,
+            Debug(cmd) => cmd.apply(db, dst, shutdown).await,
+            Client(cmd) => cmd.apply(dst, session).await,
+            Command(cmd) => cmd.apply(dst).await,
+            Info(cmd) => cmd.apply(db, dst, session).await,
+            Reset(cmd) => cmd.apply(dst).await,
+            SetBit(cmd) => cmd.apply(db, dst).await,
+            GetBit(cmd) => cmd.apply(db, dst).await,
+            BitCount(cmd) => cmd.apply(db, dst).await,
+            GetRange(cmd) => cmd.apply(db, dst).await,
+            SetRange(cmd) => cmd.apply(db, dst).await,
+            ZAdd(cmd) => cmd.apply(db, dst).await,
+            ZScore(cmd) => cmd.apply(db, dst).await,
+            ZRank(cmd) => cmd.apply(db, dst).await,
+            ZRange(cmd) => cmd.apply(db, dst).await,
+            ZRangeByScore(cmd) => cmd.apply(db, dst).await,
+            ZRem(cmd) => cmd.apply(db, dst).await,
+            IncrByFloat(cmd) => cmd.apply(db, dst).await,
+            GetEx(cmd) => cmd.apply(db, dst).await,
+            Shutdown(cmd) => cmd.apply(dst, shutdown).await,
+            GetOrSet(cmd) => cmd.apply(db, dst).await,
+            ZScan(cmd) => cmd.apply(db, dst).await,
+            Dump(cmd) => cmd.apply(db, dst).await,
+            Restore(cmd) => cmd.apply(db, dst).await,
+            Lolwut(cmd) => cmd.apply(dst).await,
+            SlowLog(cmd) => cmd.apply(dst, session).await,
+            Config(cmd) => cmd.apply(db, dst, session).await,
+            ZRandMember(cmd) => cmd.apply(db, dst).await,
+            Expire(cmd) => cmd.apply(db, dst).await,
+            Role(cmd) => cmd.apply(dst).await,
+            BitPos(cmd) => cmd.apply(db, dst).await,
+            BitOp(cmd) => cmd.apply(db, dst).await,
+            Memory(cmd) => cmd.apply(db, dst).await,
+            Lcs(cmd) => cmd.apply(db, dst, session).await,
+            Time(cmd) => cmd.apply(dst).await,
             Unknown(cmd) => cmd.apply(dst).await,
             // `Unsubscribe` cannot be applied. It may only be received from the
             // context of a `Subscribe` command.
@@ -112,12 +329,106 @@ impl Command {
     pub(crate) fn get_name(&self) -> &str {
         match self {
             Command::Get(_) => "get",
-            Command::Publish(_) => "pub",
+            Command::Publish(_) => "publish",
             Command::Set(_) => "set",
             Command::Subscribe(_) => "subscribe",
             Command::Unsubscribe(_) => "unsubscribe",
             Command::Ping(_) => "ping",
+            Command::RandomKey(_) => "randomkey",
+            Command::Touch(_) => "touch",
+            Command::Object(_) => "object",
+            Command::Wait(_) => "wait",
+            Command::Debug(_) => "debug",
+            Command::Client(_) => "client",
+            Command::Command(_) => "command",
+            Command::Info(_) => "info",
+            Command::Reset(_) => "reset",
+            Command::SetBit(_) => "setbit",
+            Command::GetBit(_) => "getbit",
+            Command::BitCount(_) => "bitcount",
+            Command::GetRange(_) => "getrange",
+            Command::SetRange(_) => "setrange",
+            Command::ZAdd(_) => "zadd",
+            Command::ZScore(_) => "zscore",
+            Command::ZRank(_) => "zrank",
+            Command::ZRange(_) => "zrange",
+            Command::ZRangeByScore(_) => "zrangebyscore",
+            Command::ZRem(_) => "zrem",
+            Command::IncrByFloat(_) => "incrbyfloat",
+            Command::GetEx(_) => "getex",
+            Command::Shutdown(_) => "shutdown",
+            Command::GetOrSet(_) => "getorset",
+            Command::ZScan(_) => "zscan",
+            Command::Dump(_) => "dump",
+            Command::Restore(_) => "restore",
+            Command::Lolwut(_) => "lolwut",
+            Command::SlowLog(_) => "slowlog",
+            Command::Config(_) => "config",
+            Command::ZRandMember(_) => "zrandmember",
+            Command::Expire(_) => "expire",
+            Command::Role(_) => "role",
+            Command::BitPos(_) => "bitpos",
+            Command::BitOp(_) => "bitop",
+            Command::Memory(_) => "memory",
+            Command::Lcs(_) => "lcs",
+            Command::Time(_) => "time",
             Command::Unknown(cmd) => cmd.get_name(),
         }
     }
+
+    /// Returns `true` if the command's entry in `COMMAND_TABLE` has the flag
+    /// `flag`, e.g. `"write"` for commands that modify the data set.
+    pub(crate) fn has_flag(&self, flag: &str) -> bool {
+        CommandSpec::lookup(self.get_name()).is_some_and(|spec| spec.has_flag(flag))
+    }
+
+    /// Returns `true` if the command modifies the data set, and so is rejected
+    /// by a read-only server.
+    ///
+    /// This is the `write` flag of the command's table entry, except for
+    /// `DEBUG POPULATE`. The flags describe `DEBUG` as a whole, and its other
+    /// subcommands do not write.
+    pub(crate) fn is_write(&self) -> bool {
+        match self {
+            Command::Debug(DebugCommand::Populate { .. }) => true,
+            _ => self.has_flag("write"),
+        }
+    }
+
+    /// Returns the key the command operates on, if it operates on a single
+    /// key. For `TOUCH`, which takes several keys, the first one is returned,
+    /// and for `BITOP` the destination key.
+    ///
+    /// This is used to annotate logs and traces.
+    pub(crate) fn key(&self) -> Option<&str> {
+        match self {
+            Command::Get(cmd) => Some(cmd.key()),
+            Command::Set(cmd) => Some(cmd.key()),
+            Command::Touch(cmd) => cmd.keys().first().map(String::as_str),
+            Command::SetBit(cmd) => Some(cmd.key()),
+            Command::GetBit(cmd) => Some(cmd.key()),
+            Command::BitCount(cmd) => Some(cmd.key()),
+            Command::BitPos(cmd) => Some(cmd.key()),
+            Command::BitOp(cmd) => Some(cmd.destination()),
+            Command::GetRange(cmd) => Some(cmd.key()),
+            Command::SetRange(cmd) => Some(cmd.key()),
+            Command::ZAdd(cmd) => Some(cmd.key()),
+            Command::ZScore(cmd) => Some(cmd.key()),
+            Command::ZRank(cmd) => Some(cmd.key()),
+            Command::ZRange(cmd) => Some(cmd.key()),
+            Command::ZRangeByScore(cmd) => Some(cmd.key()),
+            Command::ZRem(cmd) => Some(cmd.key()),
+            Command::ZRandMember(cmd) => Some(cmd.key()),
+            Command::Expire(cmd) => Some(cmd.key()),
+            Command::IncrByFloat(cmd) => Some(cmd.key()),
+            Command::GetEx(cmd) => Some(cmd.key()),
+            Command::Lcs(cmd) => Some(cmd.key()),
+            _ => None,
+        }
+    }
+}
+
+/// Returns `true` if `err` was caused by a command running out of arguments.
+fn is_end_of_stream(err: &crate::Error) -> bool {
+    matches!(err.downcast_ref(), Some(ParseError::EndOfStream))
 }
@@ -0,0 +1,107 @@
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Inspect the internals of the value stored at a key.
+///
+/// This is mostly useful for debugging. `mini-redis` stores every value as a
+/// plain byte string, so the reported details describe how real Redis would
+/// encode the same value rather than any special internal representation.
+///
+/// # Subcommands
+///
+/// Currently, the following subcommands are supported:
+///
+/// * ENCODING `key` -- the internal encoding of the value, `int` or `raw`.
+/// * REFCOUNT `key` -- the number of references to the value. `mini-redis`
+///   never shares values, so this is always `1`.
+/// * IDLETIME `key` -- the number of seconds since the key was last read or
+///   written by a command. `OBJECT` itself does not count as an access.
+/// * FREQ `key` -- the logarithmic access frequency counter of the key, as
+///   used by the `allkeys-lfu` eviction policy. As in Redis, an error is
+///   returned when another policy is selected.
+#[derive(Debug)]
+pub enum Object {
+    /// `OBJECT ENCODING key`
+    Encoding(String),
+
+    /// `OBJECT REFCOUNT key`
+    RefCount(String),
+
+    /// `OBJECT IDLETIME key`
+    IdleTime(String),
+
+    /// `OBJECT FREQ key`
+    Freq(String),
+}
+
+impl Object {
+    /// Parse an `Object` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `OBJECT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Object` value on success. If the frame is malformed or the
+    /// subcommand is not supported, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// OBJECT ENCODING|REFCOUNT|IDLETIME|FREQ key
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Object> {
+        // The subcommand name is matched case insensitively, the same as the
+        // command name itself.
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "encoding" => Ok(Object::Encoding(parse.next_string()?)),
+            "refcount" => Ok(Object::RefCount(parse.next_string()?)),
+            "idletime" => Ok(Object::IdleTime(parse.next_string()?)),
+            "freq" => Ok(Object::Freq(parse.next_string()?)),
+            _ => Err(format!("unknown subcommand '{}' for `OBJECT`", subcommand).into()),
+        }
+    }
+
+    /// Apply the `Object` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // A missing key results in nil for every subcommand.
+        let response = match self {
+            Object::Encoding(key) => match db.encoding(&key) {
+                Some(encoding) => Frame::Simple(encoding.to_string()),
+                None => Frame::Null,
+            },
+            Object::RefCount(key) => match db.encoding(&key) {
+                Some(_) => Frame::Integer(1),
+                None => Frame::Null,
+            },
+            Object::IdleTime(key) => match db.idle_time(&key) {
+                Some(idle) => Frame::Integer(idle.as_secs() as i64),
+                None => Frame::Null,
+            },
+            Object::Freq(key) => match db.frequency(&key) {
+                Ok(Some(frequency)) => Frame::Integer(frequency as i64),
+                Ok(None) => Frame::Null,
+                Err(err) => CommandError::from(err).into(),
+            },
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
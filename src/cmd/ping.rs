@@ -7,6 +7,11 @@ use tracing::{debug, instrument};
 ///
 /// This command is often used to test if a connection
 /// is still alive, or to measure latency.
+///
+/// In subscribe mode, the reply is instead an array of `pong` and the
+/// argument, or an empty bulk string if none was given. This lets clients
+/// keep a subscribed connection alive without confusing the reply with a
+/// published message.
 #[derive(Debug, Default)]
 pub struct Ping {
     /// optional message to be returned
@@ -66,6 +71,23 @@ impl Ping {
         Ok(())
     }
 
+    /// Apply the `Ping` command on a connection in subscribe mode.
+    ///
+    /// The response is written to `dst`. This is called by `Subscribe` when
+    /// the client sends `PING` while subscribed.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply_subscribed(self, dst: &mut Connection) -> crate::Result<()> {
+        let mut response = Frame::array();
+        response.push_bulk(Bytes::from_static(b"pong"));
+        response.push_bulk(self.msg.unwrap_or_default());
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
     /// Converts the command into an equivalent `Frame`.
     ///
     /// This is called by the client when encoding a `Ping` command to send
@@ -0,0 +1,60 @@
+use crate::{Connection, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Return the connection to its initial state.
+///
+/// If the connection is subscribed to any channels, it is unsubscribed from
+/// all of them and leaves subscribe mode. The reply is always the simple
+/// string `RESET`.
+///
+/// `mini-redis` has no transactions, databases, protocol negotiation or
+/// authentication, so there is no other per-connection state to discard. The
+/// connection name set with `CLIENT SETNAME` is kept, as it is in Redis.
+#[derive(Debug, Default)]
+pub struct Reset;
+
+impl Reset {
+    /// Create a new `Reset` command.
+    pub fn new() -> Reset {
+        Reset
+    }
+
+    /// Parse a `Reset` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `RESET` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Reset` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// RESET
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Reset> {
+        Ok(Reset)
+    }
+
+    /// Apply the `Reset` command.
+    ///
+    /// Subscriptions are discarded by `Subscribe::apply` before this is
+    /// called, so all that is left is to acknowledge the reset.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Simple("RESET".to_string());
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
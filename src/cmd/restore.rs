@@ -0,0 +1,137 @@
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, instrument};
+
+/// Create a key from a payload produced by `DUMP`.
+///
+/// `ttl` is the time to live of the key in milliseconds, relative to the
+/// moment the command runs. `0` creates the key without an expiration.
+///
+/// An error is returned if the key already exists, unless `REPLACE` is given,
+/// or if the payload was not produced by `DUMP`. The `ABSTTL`, `IDLETIME` and
+/// `FREQ` options of Redis are not supported.
+#[derive(Debug)]
+pub struct Restore {
+    /// Name of the key to create
+    key: String,
+
+    /// Time to live of the key, `None` for no expiration
+    ttl: Option<Duration>,
+
+    /// Payload produced by `DUMP`
+    payload: Bytes,
+
+    /// Whether an existing key is replaced
+    replace: bool,
+}
+
+impl Restore {
+    /// Create a new `Restore` command which creates `key` from `payload`.
+    pub fn new(
+        key: impl ToString,
+        ttl: Option<Duration>,
+        payload: Bytes,
+        replace: bool,
+    ) -> Restore {
+        Restore {
+            key: key.to_string(),
+            ttl,
+            payload,
+            replace,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the time to live
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Get the payload
+    pub fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    /// Returns `true` if an existing key is replaced
+    pub fn replace(&self) -> bool {
+        self.replace
+    }
+
+    /// Parse a `Restore` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `RESTORE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Restore` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four or five entries.
+    ///
+    /// ```text
+    /// RESTORE key ttl payload [REPLACE]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Restore> {
+        let key = parse.next_string()?;
+
+        let ttl = match parse.next_i64()? {
+            0 => None,
+            ms if ms > 0 => Some(Duration::from_millis(ms as u64)),
+            _ => return Err(CommandError::InvalidExpireTime("restore").into()),
+        };
+
+        let payload = parse.next_bytes()?;
+
+        let replace = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "REPLACE" => true,
+            Ok(_) => {
+                return Err(
+                    "protocol error; currently `RESTORE` only supports the `REPLACE` option".into(),
+                )
+            }
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Restore {
+            key,
+            ttl,
+            payload,
+            replace,
+        })
+    }
+
+    /// Apply the `Restore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // The TTL is anchored to the moment the key is created.
+        let expires_at = self.ttl.map(|ttl| Instant::now() + ttl);
+
+        let response = match db.restore(&self.key, &self.payload, expires_at, self.replace) {
+            Ok(()) => Frame::Simple("OK".to_string()),
+            Err(err) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
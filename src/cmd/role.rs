@@ -0,0 +1,62 @@
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Report the replication role of the server.
+///
+/// `mini-redis` is always a standalone master without replicas, so the reply
+/// is always `["master", 0, []]`: the role, the replication offset and the
+/// list of connected replicas. Client libraries which probe the topology on
+/// connect expect this shape.
+#[derive(Debug, Default)]
+pub struct Role;
+
+impl Role {
+    /// Create a new `Role` command.
+    pub fn new() -> Role {
+        Role
+    }
+
+    /// Parse a `Role` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ROLE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Role` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// ROLE
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Role> {
+        Ok(Role)
+    }
+
+    /// Apply the `Role` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Array(vec![
+            Frame::Bulk(Bytes::from_static(b"master")),
+            Frame::Integer(0),
+            Frame::Array(vec![]),
+        ]);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
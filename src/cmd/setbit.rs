@@ -0,0 +1,111 @@
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// The largest bit offset accepted by `SETBIT` and `GETBIT`.
+///
+/// This limits strings grown by `SETBIT` to 512MB, the same limit Redis
+/// applies.
+pub(crate) const MAX_BIT_OFFSET: u64 = (1 << 32) - 1;
+
+/// Sets or clears the bit at `offset` in the string value stored at key.
+///
+/// The string is grown with zero bytes if it is not long enough to hold the
+/// bit, and created if the key does not exist. Replies with the original
+/// value of the bit. Any existing expiration is kept.
+#[derive(Debug)]
+pub struct SetBit {
+    /// the lookup key
+    key: String,
+
+    /// offset of the bit, counted from the most significant bit of the first
+    /// byte
+    offset: u64,
+
+    /// the new value of the bit
+    value: bool,
+}
+
+impl SetBit {
+    /// Create a new `SetBit` command which sets the bit at `offset` in the
+    /// value stored at `key` to `value`.
+    pub fn new(key: impl ToString, offset: u64, value: bool) -> SetBit {
+        SetBit {
+            key: key.to_string(),
+            offset,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the bit offset
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the new value of the bit
+    pub fn value(&self) -> bool {
+        self.value
+    }
+
+    /// Parse a `SetBit` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SETBIT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SetBit` value on success. If the frame is malformed, the
+    /// offset is out of range or the value is not `0` or `1`, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETBIT key offset value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetBit> {
+        let key = parse.next_string()?;
+
+        let offset = parse.next_int()?;
+        if offset > MAX_BIT_OFFSET {
+            return Err("protocol error; bit offset is out of range".into());
+        }
+
+        let value = match parse.next_int()? {
+            0 => false,
+            1 => true,
+            _ => return Err("protocol error; bit must be `0` or `1`".into()),
+        };
+
+        Ok(SetBit { key, offset, value })
+    }
+
+    /// Apply the `SetBit` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // The offset was validated when parsing, so it fits in a `usize`.
+        let response = match db.setbit(&self.key, self.offset as usize, self.value) {
+            Ok(old) => Frame::Integer(old as i64),
+            Err(err) => CommandError::from(err).into(),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
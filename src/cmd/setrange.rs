@@ -0,0 +1,103 @@
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// The largest string `SETRANGE` may produce, 512MB, the same limit Redis
+/// applies.
+const MAX_STRING_LEN: u64 = 512 * 1024 * 1024;
+
+/// Overwrites part of the string value stored at key, starting at `offset`.
+///
+/// If the string is shorter than `offset`, it is padded with zero bytes. A
+/// missing key is treated as an empty string. Replies with the length of the
+/// string after it was modified. Any existing expiration is kept.
+#[derive(Debug)]
+pub struct SetRange {
+    /// the lookup key
+    key: String,
+
+    /// offset of the first byte to overwrite
+    offset: u64,
+
+    /// the bytes to write
+    value: Bytes,
+}
+
+impl SetRange {
+    /// Create a new `SetRange` command which writes `value` at `offset` in the
+    /// value stored at `key`.
+    pub fn new(key: impl ToString, offset: u64, value: Bytes) -> SetRange {
+        SetRange {
+            key: key.to_string(),
+            offset,
+            value,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the offset
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the value
+    pub fn value(&self) -> &Bytes {
+        &self.value
+    }
+
+    /// Parse a `SetRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SETRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SetRange` value on success. If the frame is malformed or the
+    /// resulting string would exceed 512MB, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four entries.
+    ///
+    /// ```text
+    /// SETRANGE key offset value
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SetRange> {
+        let key = parse.next_string()?;
+        let offset = parse.next_int()?;
+        let value = parse.next_bytes()?;
+
+        if offset.saturating_add(value.len() as u64) > MAX_STRING_LEN {
+            return Err("protocol error; string exceeds maximum allowed size".into());
+        }
+
+        Ok(SetRange { key, offset, value })
+    }
+
+    /// Apply the `SetRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // The offset was validated when parsing, so it fits in a `usize`.
+        let response = match db.setrange(&self.key, self.offset as usize, &self.value) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => CommandError::from(err).into(),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::cmd::CommandError;
+use crate::{Connection, Frame, Parse, ParseError, Shutdown};
+
+use tracing::{debug, instrument};
+
+/// Shut the server down gracefully.
+///
+/// This takes the same path as Ctrl-C: every connection, including the one
+/// that sent the command, is notified and closed once it reaches a safe
+/// state. On success no reply is sent, the connection is simply closed.
+///
+/// `mini-redis` has no persistence. `NOSAVE` is accepted and is the default,
+/// while `SAVE` is rejected with an error and the server keeps running.
+#[derive(Debug, Default)]
+pub struct ShutdownCommand {
+    /// `true` if `SAVE` was given.
+    save: bool,
+}
+
+impl ShutdownCommand {
+    /// Create a new `ShutdownCommand` which shuts down without saving.
+    pub fn new() -> ShutdownCommand {
+        ShutdownCommand { save: false }
+    }
+
+    /// Parse a `ShutdownCommand` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SHUTDOWN` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ShutdownCommand` value on success. If the frame is
+    /// malformed or the option is not supported, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing `SHUTDOWN` and an optional option.
+    ///
+    /// ```text
+    /// SHUTDOWN [NOSAVE|SAVE]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ShutdownCommand> {
+        match parse.next_string() {
+            Ok(option) if option.eq_ignore_ascii_case("nosave") => Ok(ShutdownCommand::new()),
+            Ok(option) if option.eq_ignore_ascii_case("save") => Ok(ShutdownCommand { save: true }),
+            Ok(option) => Err(format!("unsupported option '{}' for `SHUTDOWN`", option).into()),
+            Err(ParseError::EndOfStream) => Ok(ShutdownCommand::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Apply the `ShutdownCommand`.
+    ///
+    /// Requests the shutdown and returns without writing a response. The
+    /// handler then observes the shutdown and terminates the connection.
+    #[instrument(skip(self, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        dst: &mut Connection,
+        shutdown: &Shutdown,
+    ) -> crate::Result<()> {
+        if self.save {
+            let response = Frame::from(CommandError::PersistenceUnsupported);
+            debug!(?response);
+
+            dst.write_frame(&response).await?;
+
+            return Ok(());
+        }
+
+        debug!("shutdown requested");
+        shutdown.initiate();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use crate::slowlog::SlowLogEntry;
+use crate::{Connection, Frame, Parse, Session};
+
+use bytes::Bytes;
+use std::convert::TryFrom;
+use tracing::{debug, instrument};
+
+/// Read and clear the log of commands which exceeded the configured execution
+/// time.
+///
+/// Only the time spent executing a command is measured, not the time spent
+/// reading the request or writing the reply to the socket. The threshold and
+/// the number of entries kept are set with
+/// `Config::slowlog_log_slower_than` and `Config::slowlog_max_len`.
+///
+/// # Subcommands
+///
+/// * GET [`count`] -- the `count` most recent entries, newest first. The
+///   default count is 10, and a negative count returns every entry. Each
+///   entry is an array of the entry id, the Unix timestamp at which the
+///   command started, its execution time in microseconds, the command
+///   arguments, and the address and name of the client which issued it.
+/// * LEN -- the number of entries in the log.
+/// * RESET -- remove every entry from the log.
+#[derive(Debug)]
+pub enum SlowLogCommand {
+    /// `SLOWLOG GET [count]`, `None` returns every entry
+    Get(Option<usize>),
+
+    /// `SLOWLOG LEN`
+    Len,
+
+    /// `SLOWLOG RESET`
+    Reset,
+}
+
+/// Number of entries returned by `SLOWLOG GET` without a count, as in Redis.
+const DEFAULT_COUNT: usize = 10;
+
+impl SlowLogCommand {
+    /// Parse a `SlowLogCommand` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `SLOWLOG` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `SlowLogCommand` value on success. If the frame is
+    /// malformed or the subcommand is not supported, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// SLOWLOG GET [count]
+    /// SLOWLOG LEN
+    /// SLOWLOG RESET
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<SlowLogCommand> {
+        let subcommand = parse.next_string()?.to_lowercase();
+
+        match &subcommand[..] {
+            "get" => {
+                let count = if parse.remaining() > 0 {
+                    // A negative count returns the whole log.
+                    usize::try_from(parse.next_i64()?).ok()
+                } else {
+                    Some(DEFAULT_COUNT)
+                };
+
+                Ok(SlowLogCommand::Get(count))
+            }
+            "len" => Ok(SlowLogCommand::Len),
+            "reset" => Ok(SlowLogCommand::Reset),
+            _ => Err(format!("unknown subcommand '{}' for `SLOWLOG`", subcommand).into()),
+        }
+    }
+
+    /// Apply the `SlowLogCommand` to the server's slow log.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst, session))]
+    pub(crate) async fn apply(
+        self,
+        dst: &mut Connection,
+        session: &mut Session,
+    ) -> crate::Result<()> {
+        let slowlog = session.slowlog();
+
+        let response = match self {
+            SlowLogCommand::Get(count) => Frame::Array(
+                slowlog
+                    .get(count.unwrap_or(usize::MAX))
+                    .into_iter()
+                    .map(entry_to_frame)
+                    .collect(),
+            ),
+            SlowLogCommand::Len => Frame::Integer(slowlog.len() as i64),
+            SlowLogCommand::Reset => {
+                slowlog.reset();
+                Frame::Simple("OK".to_string())
+            }
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Converts a slow log entry into the array reported by `SLOWLOG GET`.
+fn entry_to_frame(entry: SlowLogEntry) -> Frame {
+    Frame::Array(vec![
+        Frame::Integer(entry.id as i64),
+        Frame::Integer(entry.timestamp as i64),
+        Frame::Integer(entry.duration.as_micros() as i64),
+        Frame::Array(entry.args.into_iter().map(Frame::Bulk).collect()),
+        Frame::Bulk(Bytes::from(entry.addr.to_string())),
+        Frame::Bulk(Bytes::from(entry.name.unwrap_or_default())),
+    ])
+}
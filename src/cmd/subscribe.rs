@@ -1,17 +1,25 @@
-use crate::cmd::{Parse, ParseError, Unknown};
-use crate::{Command, Connection, Db, Frame, Shutdown};
+use crate::cmd::{CommandError, Parse, ParseError};
+use crate::{Command, Connection, Db, Frame, Session, Shutdown};
 
 use bytes::Bytes;
 use std::pin::Pin;
 use tokio::select;
 use tokio::sync::broadcast;
 use tokio_stream::{Stream, StreamExt, StreamMap};
+use tracing::warn;
 
 /// Subscribes the client to one or more channels.
 ///
 /// Once the client enters the subscribed state, it is not supposed to issue any
 /// other commands, except for additional SUBSCRIBE, PSUBSCRIBE, UNSUBSCRIBE,
 /// PUNSUBSCRIBE, PING and QUIT commands.
+///
+/// Each channel buffers up to `Config::pubsub_capacity` messages (1024 by
+/// default) not yet seen by all of its subscribers. A subscriber that falls
+/// further behind, for example because the client is not reading from its
+/// socket, misses the oldest messages: they are skipped, a warning is logged
+/// and delivery resumes with the oldest message still buffered. The
+/// connection stays subscribed.
 #[derive(Debug)]
 pub struct Subscribe {
     channels: Vec<String>,
@@ -80,8 +88,8 @@ impl Subscribe {
                 // The `EndOfStream` error indicates there is no further data to
                 // parse.
                 Err(EndOfStream) => break,
-                // All other errors are bubbled up, resulting in the connection
-                // being terminated.
+                // All other errors are bubbled up and answered with an error
+                // reply.
                 Err(err) => return Err(err.into()),
             }
         }
@@ -96,12 +104,17 @@ impl Subscribe {
     /// commands may be received from the client and the list of subscriptions
     /// are updated accordingly.
     ///
+    /// If the server shuts down while the client is subscribed, an unsubscribe
+    /// confirmation is sent for every channel before the connection is
+    /// closed.
+    ///
     /// [here]: https://redis.io/topics/pubsub
     pub(crate) async fn apply(
         mut self,
         db: &Db,
         dst: &mut Connection,
         shutdown: &mut Shutdown,
+        session: &mut Session,
     ) -> crate::Result<()> {
         // Each individual channel subscription is handled using a
         // `sync::broadcast` channel. Messages are then fanned out to all
@@ -127,10 +140,19 @@ impl Subscribe {
             // - Receive a message from one of the subscribed channels.
             // - Receive a subscribe or unsubscribe command from the client.
             // - A server shutdown signal.
+            // - The connection being terminated by `CLIENT KILL`.
             select! {
                 // Receive messages from subscribed channels
                 Some((channel_name, msg)) = subscriptions.next() => {
+                    // Messages are pushed rather than requested, so they are
+                    // sent right away instead of waiting for the next read.
+                    // Only what the socket accepts without waiting is
+                    // written here, the rest is flushed by `read_frame`
+                    // while waiting for the next message. A subscriber that
+                    // stops reading then hits the output buffer limit, if
+                    // one is configured, instead of stalling this loop.
                     dst.write_frame(&make_message_frame(channel_name, msg)).await?;
+                    dst.try_flush()?;
                 }
                 res = dst.read_frame() => {
                     let frame = match res? {
@@ -139,14 +161,27 @@ impl Subscribe {
                         None => return Ok(())
                     };
 
-                    handle_command(
+                    let subscribed = handle_command(
                         frame,
                         &mut self.channels,
                         &mut subscriptions,
                         dst,
                     ).await?;
+
+                    // `RESET` leaves subscribe mode. The connection goes back to
+                    // processing regular commands.
+                    if !subscribed {
+                        return Ok(());
+                    }
                 }
                 _ = shutdown.recv() => {
+                    // Tell the client that its subscriptions ended, so it can
+                    // tell a server shutdown apart from a dropped connection
+                    // before the socket is closed.
+                    unsubscribe_all(&mut subscriptions, dst).await?;
+                    return Ok(());
+                }
+                _ = session.killed() => {
                     return Ok(());
                 }
             };
@@ -173,50 +208,76 @@ async fn subscribe_to_channel(
     db: &Db,
     dst: &mut Connection,
 ) -> crate::Result<()> {
-    let mut rx = db.subscribe(channel_name.clone());
-
-    // Subscribe to the channel.
-    let rx = Box::pin(async_stream::stream! {
-        loop {
-            match rx.recv().await {
-                Ok(msg) => yield msg,
-                // If we lagged in consuming messages, just resume.
-                Err(broadcast::error::RecvError::Lagged(_)) => {}
-                Err(_) => break,
+    // As in Redis, subscribing again to a channel only confirms it. The
+    // existing subscription is kept, along with the messages it buffered.
+    if !subscriptions.contains_key(&channel_name) {
+        let mut rx = db.subscribe(channel_name.clone());
+        let channel = channel_name.clone();
+
+        // Subscribe to the channel.
+        let rx = Box::pin(async_stream::stream! {
+            loop {
+                match rx.recv().await {
+                    Ok(msg) => yield msg,
+                    // If we lagged in consuming messages, the skipped messages
+                    // are lost. Log it and resume with the oldest buffered one.
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(channel = %channel, skipped, "subscriber lagged, messages dropped");
+                    }
+                    Err(_) => break,
+                }
             }
-        }
-    });
+        });
 
-    // Track subscription in this client's subscription set.
-    subscriptions.insert(channel_name.clone(), rx);
+        // Track subscription in this client's subscription set.
+        subscriptions.insert(channel_name.clone(), rx);
+    }
 
-    // Respond with the successful subscription
+    // Respond with the successful subscription. The count is cumulative, so
+    // each channel of a single `SUBSCRIBE` gets its own confirmation.
     let response = make_subscribe_frame(channel_name, subscriptions.len());
     dst.write_frame(&response).await?;
 
     Ok(())
 }
 
-/// Handle a command received while inside `Subscribe::apply`. Only subscribe
-/// and unsubscribe commands are permitted in this context.
+/// Handle a command received while inside `Subscribe::apply`. Only subscribe,
+/// unsubscribe and reset commands are permitted in this context.
 ///
 /// Any new subscriptions are appended to `subscribe_to` instead of modifying
 /// `subscriptions`.
+///
+/// Returns `false` if the connection should leave subscribe mode.
 async fn handle_command(
     frame: Frame,
     subscribe_to: &mut Vec<String>,
     subscriptions: &mut StreamMap<String, Messages>,
     dst: &mut Connection,
-) -> crate::Result<()> {
+) -> crate::Result<bool> {
     // A command has been received from the client.
     //
-    // Only `SUBSCRIBE` and `UNSUBSCRIBE` commands are permitted
-    // in this context.
-    match Command::from_frame(frame)? {
+    // Only `SUBSCRIBE`, `UNSUBSCRIBE`, `PING` and `RESET` commands are
+    // permitted in this context.
+    let command = match Command::from_frame(frame) {
+        Ok(command) => command,
+        // Malformed commands are answered with an error, staying subscribed,
+        // the same as outside of subscribe mode.
+        Err(err) => {
+            let err = match err.downcast::<CommandError>() {
+                Ok(err) => *err,
+                Err(err) => CommandError::InvalidRequest(err.to_string()),
+            };
+
+            dst.write_frame(&Frame::from(err)).await?;
+            return Ok(true);
+        }
+    };
+
+    match command {
         Command::Subscribe(subscribe) => {
             // The `apply` method will subscribe to the channels we add to this
             // vector.
-            subscribe_to.extend(subscribe.channels.into_iter());
+            subscribe_to.extend(subscribe.channels);
         }
         Command::Unsubscribe(mut unsubscribe) => {
             // If no channels are specified, this requests unsubscribing from
@@ -237,12 +298,31 @@ async fn handle_command(
                 dst.write_frame(&response).await?;
             }
         }
+        Command::Ping(ping) => {
+            // Answered in the subscribe mode form, `*2 pong <message>`.
+            ping.apply_subscribed(dst).await?;
+        }
+        Command::Reset(reset) => {
+            // Drop every subscription, including any pending ones, without
+            // sending unsubscribe confirmations. `RESET` is the only reply.
+            subscribe_to.clear();
+            subscriptions.clear();
+
+            reset.apply(dst).await?;
+
+            return Ok(false);
+        }
+        Command::Unknown(unknown) => {
+            unknown.apply(dst).await?;
+        }
         command => {
-            let cmd = Unknown::new(command.get_name());
-            cmd.apply(dst).await?;
+            // As in Redis, commands which exist but are not allowed in this
+            // context are named in the error.
+            let err = CommandError::NotAllowedWhileSubscribed(command.get_name().to_string());
+            dst.write_frame(&Frame::from(err)).await?;
         }
     }
-    Ok(())
+    Ok(true)
 }
 
 /// Creates the response to a subcribe request.
@@ -255,16 +335,34 @@ fn make_subscribe_frame(channel_name: String, num_subs: usize) -> Frame {
     let mut response = Frame::array();
     response.push_bulk(Bytes::from_static(b"subscribe"));
     response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
+    response.push_int(num_subs as i64);
     response
 }
 
+/// Removes every subscription, sending an unsubscribe confirmation for each
+/// channel as if the client had sent `UNSUBSCRIBE` without arguments.
+async fn unsubscribe_all(
+    subscriptions: &mut StreamMap<String, Messages>,
+    dst: &mut Connection,
+) -> crate::Result<()> {
+    let channels: Vec<String> = subscriptions.keys().cloned().collect();
+
+    for channel_name in channels {
+        subscriptions.remove(&channel_name);
+
+        let response = make_unsubscribe_frame(channel_name, subscriptions.len());
+        dst.write_frame(&response).await?;
+    }
+
+    Ok(())
+}
+
 /// Creates the response to an unsubcribe request.
 fn make_unsubscribe_frame(channel_name: String, num_subs: usize) -> Frame {
     let mut response = Frame::array();
     response.push_bulk(Bytes::from_static(b"unsubscribe"));
     response.push_bulk(Bytes::from(channel_name));
-    response.push_int(num_subs as u64);
+    response.push_int(num_subs as i64);
     response
 }
 
@@ -323,8 +421,8 @@ impl Unsubscribe {
                 // The `EndOfStream` error indicates there is no further data to
                 // parse.
                 Err(EndOfStream) => break,
-                // All other errors are bubbled up, resulting in the connection
-                // being terminated.
+                // All other errors are bubbled up and answered with an error
+                // reply.
                 Err(err) => return Err(err),
             }
         }
@@ -0,0 +1,67 @@
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument};
+
+/// Return the current server time.
+///
+/// The reply is a two element array of bulk strings: the Unix time in seconds
+/// and the microseconds already elapsed in the current second. The time is
+/// read from the wall clock, so clients can use it to estimate the skew
+/// between their clock and the server's.
+#[derive(Debug, Default)]
+pub struct Time;
+
+impl Time {
+    /// Create a new `Time` command.
+    pub fn new() -> Time {
+        Time
+    }
+
+    /// Parse a `Time` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `TIME` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Time` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing a single entry.
+    ///
+    /// ```text
+    /// TIME
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Time> {
+        Ok(Time)
+    }
+
+    /// Apply the `Time` command.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        // A clock set before the epoch is reported as the epoch itself.
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let response = Frame::Array(vec![
+            Frame::Bulk(Bytes::from(now.as_secs().to_string())),
+            Frame::Bulk(Bytes::from(now.subsec_micros().to_string())),
+        ]);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::cmd::{Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use tracing::{debug, instrument};
+
+/// Alters the last access time of one or more keys.
+///
+/// Returns the number of the specified keys that exist. The values themselves
+/// are never returned, which makes `TOUCH` a cheap way to keep hot keys alive.
+#[derive(Debug)]
+pub struct Touch {
+    /// The keys to touch
+    keys: Vec<String>,
+}
+
+impl Touch {
+    /// Create a new `Touch` command which touches `keys`.
+    pub fn new(keys: Vec<String>) -> Touch {
+        Touch { keys }
+    }
+
+    /// Get the keys
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Parse a `Touch` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `TOUCH` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Touch` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two or more entries.
+    ///
+    /// ```text
+    /// TOUCH key [key ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Touch> {
+        use ParseError::EndOfStream;
+
+        // At least one key is required.
+        let mut keys = vec![parse.next_string()?];
+
+        // Consume the remaining keys until the frame is exhausted.
+        loop {
+            match parse.next_string() {
+                Ok(key) => keys.push(key),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Touch { keys })
+    }
+
+    /// Apply the `Touch` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let touched = db.touch(&self.keys);
+
+        let response = Frame::Integer(touched as i64);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
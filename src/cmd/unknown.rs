@@ -1,19 +1,52 @@
+use crate::cmd::CommandError;
 use crate::{Connection, Frame};
 
+use bytes::Bytes;
 use tracing::{debug, instrument};
 
 /// Represents an "unknown" command. This is not a real `Redis` command.
 #[derive(Debug)]
 pub struct Unknown {
     command_name: String,
+
+    /// The leading arguments echoed back in the error reply.
+    args: Vec<String>,
 }
 
+/// Maximum length, in bytes, of the command name and of the arguments echoed
+/// back in the error reply, as in Redis.
+const MAX_ECHO_LEN: usize = 128;
+
 impl Unknown {
     /// Create a new `Unknown` command which responds to unknown commands
     /// issued by clients
-    pub(crate) fn new(key: impl ToString) -> Unknown {
+    ///
+    /// As in Redis, the error reply echoes the name and the first arguments,
+    /// up to `MAX_ECHO_LEN` bytes each. Line breaks are replaced with spaces
+    /// as they cannot appear in an error reply.
+    pub(crate) fn new(key: impl ToString, args: Vec<Bytes>) -> Unknown {
+        let mut echoed = Vec::new();
+        let mut len = 0;
+
+        for arg in args {
+            if len >= MAX_ECHO_LEN {
+                break;
+            }
+
+            let arg = &arg[..arg.len().min(MAX_ECHO_LEN - len)];
+            let arg = sanitize(&String::from_utf8_lossy(arg));
+
+            // Each argument is rendered as `'arg' `.
+            len += arg.len() + 3;
+            echoed.push(arg);
+        }
+
+        let command_name = key.to_string();
+        let command_name = &command_name.as_bytes()[..command_name.len().min(MAX_ECHO_LEN)];
+
         Unknown {
-            command_name: key.to_string(),
+            command_name: sanitize(&String::from_utf8_lossy(command_name)),
+            args: echoed,
         }
     }
 
@@ -27,7 +60,7 @@ impl Unknown {
     /// This usually means the command is not yet implemented by `mini-redis`.
     #[instrument(skip(self, dst))]
     pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
-        let response = Frame::Error(format!("ERR unknown command '{}'", self.command_name));
+        let response = Frame::from(CommandError::UnknownCommand(self.command_name, self.args));
 
         debug!(?response);
 
@@ -35,3 +68,8 @@ impl Unknown {
         Ok(())
     }
 }
+
+/// Replaces the line breaks in `s` with spaces.
+fn sanitize(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
@@ -0,0 +1,84 @@
+use crate::{Connection, Frame, Parse};
+
+use tracing::{debug, instrument};
+
+/// Blocks until previous writes are acknowledged by `numreplicas` replicas.
+///
+/// `mini-redis` has no replication, so there is never anything to wait for.
+/// The arguments are validated and the command immediately replies that zero
+/// replicas acknowledged the writes. This keeps client libraries that issue
+/// `WAIT` after writes working.
+#[derive(Debug)]
+pub struct Wait {
+    /// Number of replicas that should acknowledge the writes
+    num_replicas: u64,
+
+    /// Maximum time to block, in milliseconds. `0` blocks forever.
+    timeout: u64,
+}
+
+impl Wait {
+    /// Create a new `Wait` command.
+    pub fn new(num_replicas: u64, timeout: u64) -> Wait {
+        Wait {
+            num_replicas,
+            timeout,
+        }
+    }
+
+    /// Get the number of replicas to wait for
+    pub fn num_replicas(&self) -> u64 {
+        self.num_replicas
+    }
+
+    /// Get the timeout, in milliseconds
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+
+    /// Parse a `Wait` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `WAIT` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `Wait` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing three entries.
+    ///
+    /// ```text
+    /// WAIT numreplicas timeout
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Wait> {
+        let num_replicas = parse.next_int()?;
+
+        // The timeout is parsed so malformed values are rejected, even though
+        // it is never used.
+        let timeout = parse.next_int()?;
+
+        Ok(Wait {
+            num_replicas,
+            timeout,
+        })
+    }
+
+    /// Apply the `Wait` command.
+    ///
+    /// There are no replicas, so the response is always `0`.
+    #[instrument(skip(self, dst))]
+    pub(crate) async fn apply(self, dst: &mut Connection) -> crate::Result<()> {
+        let response = Frame::Integer(0);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use crate::cmd::{CommandError, Parse};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Adds members with their scores to the sorted set stored at key.
+///
+/// If a member is already present, its score is updated. If the key does not
+/// exist, a new sorted set is created. Replies with the number of members that
+/// were newly added, not counting members whose score was updated.
+#[derive(Debug)]
+pub struct ZAdd {
+    /// the lookup key
+    key: String,
+
+    /// `(score, member)` pairs to add
+    members: Vec<(f64, Bytes)>,
+}
+
+impl ZAdd {
+    /// Create a new `ZAdd` command which adds `members` to the sorted set
+    /// stored at `key`.
+    pub fn new(key: impl ToString, members: Vec<(f64, Bytes)>) -> ZAdd {
+        ZAdd {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the `(score, member)` pairs
+    pub fn members(&self) -> &[(f64, Bytes)] {
+        &self.members
+    }
+
+    /// Parse a `ZAdd` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZADD` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZAdd` value on success. If the frame is malformed or a score
+    /// is not a valid float, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// ZADD key score member [score member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZAdd> {
+        let key = parse.next_string()?;
+
+        // Redis accepts option flags before the first score. Look ahead so
+        // these are reported as unsupported rather than as an invalid score.
+        if let Some(option) = parse.peek_string() {
+            let option = option.to_uppercase();
+            if ["NX", "XX", "GT", "LT", "CH", "INCR"].contains(&&option[..]) {
+                return Err(
+                    format!("currently `ZADD` does not support the `{}` option", option).into(),
+                );
+            }
+        }
+
+        // At least one `score member` pair is required, and every score must
+        // be followed by a member.
+        let remaining = parse.remaining();
+        if remaining == 0 || !remaining.is_multiple_of(2) {
+            return Err("protocol error; `ZADD` expects `score member` pairs".into());
+        }
+
+        let mut members = Vec::with_capacity(remaining / 2);
+        for _ in 0..remaining / 2 {
+            members.push((parse.next_f64()?, parse.next_bytes()?));
+        }
+
+        Ok(ZAdd { key, members })
+    }
+
+    /// Apply the `ZAdd` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zadd(&self.key, self.members) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use crate::cmd::zrange::members_frame;
+use crate::cmd::CommandError;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use tracing::{debug, instrument};
+
+/// Largest number of members a negative count may request. Members may repeat
+/// then, so the reply is not bounded by the size of the set.
+const MAX_REPEATED_COUNT: u64 = 1024 * 1024;
+
+/// Returns random members of the sorted set stored at key.
+///
+/// Without a count, a single member is returned as a bulk string, or nil if
+/// the key does not exist. With a positive count, up to `count` distinct
+/// members are returned. With a negative count, exactly `-count` members are
+/// returned and the same member may appear several times. With a count, the
+/// reply is always an array, empty if the key does not exist, and
+/// `WITHSCORES` follows each member with its score.
+///
+/// As in Redis, a count outside `±(i64::MAX / 2)` is rejected. A negative count
+/// is also limited to `MAX_REPEATED_COUNT` members.
+#[derive(Debug)]
+pub struct ZRandMember {
+    /// the lookup key
+    key: String,
+
+    /// how many members to return, `None` for a single member
+    count: Option<i64>,
+
+    /// whether to include the scores in the reply
+    with_scores: bool,
+}
+
+impl ZRandMember {
+    /// Create a new `ZRandMember` command which returns random members of the
+    /// sorted set stored at `key`.
+    ///
+    /// `with_scores` is only honored together with a `count`.
+    pub fn new(key: impl ToString, count: Option<i64>, with_scores: bool) -> ZRandMember {
+        ZRandMember {
+            key: key.to_string(),
+            count,
+            with_scores: with_scores && count.is_some(),
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the count
+    pub fn count(&self) -> Option<i64> {
+        self.count
+    }
+
+    /// Returns `true` if the scores are included in the reply
+    pub fn with_scores(&self) -> bool {
+        self.with_scores
+    }
+
+    /// Parse a `ZRandMember` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZRANDMEMBER` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRandMember` value on success. If the frame is malformed
+    /// or the count is out of range, `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing two to four entries.
+    ///
+    /// ```text
+    /// ZRANDMEMBER key [count [WITHSCORES]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRandMember> {
+        let key = parse.next_string()?;
+
+        let count = match parse.next_i64() {
+            Ok(count) => Some(count),
+            Err(ParseError::EndOfStream) => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        if let Some(count) = count {
+            if !(-(i64::MAX / 2)..=i64::MAX / 2).contains(&count) {
+                return Err("protocol error; value is out of range".into());
+            }
+
+            if count < 0 && count.unsigned_abs() > MAX_REPEATED_COUNT {
+                return Err("protocol error; `ZRANDMEMBER` count is too large".into());
+            }
+        }
+
+        let with_scores = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "WITHSCORES" => true,
+            Ok(_) => {
+                return Err(
+                    "protocol error; `ZRANDMEMBER` only supports the `WITHSCORES` option".into(),
+                )
+            }
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ZRandMember {
+            key,
+            count,
+            with_scores,
+        })
+    }
+
+    /// Apply the `ZRandMember` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match (
+            db.zrandmember(&self.key, self.count.unwrap_or(1)),
+            self.count,
+        ) {
+            (Ok(members), Some(_)) => members_frame(members, self.with_scores),
+            (Ok(members), None) => match members.into_iter().next() {
+                Some((member, _)) => Frame::Bulk(member),
+                None => Frame::Null,
+            },
+            (Err(err), _) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use crate::cmd::CommandError;
+use crate::sorted_set::format_score;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Returns the members of the sorted set stored at key with a rank between
+/// `start` and `stop`, inclusive.
+///
+/// Members are ordered by ascending score, members with equal scores by their
+/// bytes. Negative indices count from the end, `-1` being the last member.
+/// With `WITHSCORES`, each member is followed by its score in the reply.
+#[derive(Debug)]
+pub struct ZRange {
+    /// the lookup key
+    key: String,
+
+    /// rank of the first member to return
+    start: i64,
+
+    /// rank of the last member to return
+    stop: i64,
+
+    /// whether to include the scores in the reply
+    with_scores: bool,
+}
+
+impl ZRange {
+    /// Create a new `ZRange` command which returns the members of the sorted
+    /// set stored at `key` ranked between `start` and `stop`.
+    pub fn new(key: impl ToString, start: i64, stop: i64, with_scores: bool) -> ZRange {
+        ZRange {
+            key: key.to_string(),
+            start,
+            stop,
+            with_scores,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the start rank
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    /// Get the stop rank
+    pub fn stop(&self) -> i64 {
+        self.stop
+    }
+
+    /// Returns `true` if the scores are included in the reply
+    pub fn with_scores(&self) -> bool {
+        self.with_scores
+    }
+
+    /// Parse a `ZRange` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZRANGE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRange` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing four or five entries.
+    ///
+    /// ```text
+    /// ZRANGE key start stop [WITHSCORES]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRange> {
+        let key = parse.next_string()?;
+        let start = parse.next_i64()?;
+        let stop = parse.next_i64()?;
+
+        let with_scores = match parse.next_string() {
+            Ok(s) if s.to_uppercase() == "WITHSCORES" => true,
+            Ok(_) => {
+                return Err(
+                    "protocol error; currently `ZRANGE` only supports the `WITHSCORES` option"
+                        .into(),
+                )
+            }
+            Err(ParseError::EndOfStream) => false,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(ZRange {
+            key,
+            start,
+            stop,
+            with_scores,
+        })
+    }
+
+    /// Apply the `ZRange` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrange(&self.key, self.start, self.stop) {
+            Ok(members) => members_frame(members, self.with_scores),
+            Err(err) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Converts `(member, score)` pairs into the array reply used by the sorted set
+/// range commands. With `with_scores`, each member is followed by its score.
+pub(crate) fn members_frame(members: Vec<(Bytes, f64)>, with_scores: bool) -> Frame {
+    let mut frame = Frame::array();
+
+    for (member, score) in members {
+        frame.push_bulk(member);
+
+        if with_scores {
+            frame.push_bulk(format_score(score));
+        }
+    }
+
+    frame
+}
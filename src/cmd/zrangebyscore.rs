@@ -0,0 +1,209 @@
+use crate::cmd::zrange::members_frame;
+use crate::cmd::CommandError;
+use crate::parse::parse_f64;
+use crate::{Connection, Db, Parse, ParseError};
+
+use std::ops::Bound;
+use tracing::{debug, instrument};
+
+/// Returns the members of the sorted set stored at key with a score between
+/// `min` and `max`.
+///
+/// Both bounds are inclusive unless prefixed with `(`, and `-inf` and `+inf`
+/// may be used for unbounded ranges. Members are ordered by ascending score.
+/// With `WITHSCORES`, each member is followed by its score in the reply.
+/// `LIMIT offset count` skips the first `offset` matching members and returns
+/// at most `count` members. A negative `count` returns all remaining members.
+#[derive(Debug)]
+pub struct ZRangeByScore {
+    /// the lookup key
+    key: String,
+
+    /// lower bound of the scores to return
+    min: Bound<f64>,
+
+    /// upper bound of the scores to return
+    max: Bound<f64>,
+
+    /// whether to include the scores in the reply
+    with_scores: bool,
+
+    /// `(offset, count)` given with `LIMIT`, if any
+    limit: Option<(i64, i64)>,
+}
+
+impl ZRangeByScore {
+    /// Create a new `ZRangeByScore` command which returns the members of the
+    /// sorted set stored at `key` with a score between `min` and `max`.
+    pub fn new(key: impl ToString, min: Bound<f64>, max: Bound<f64>) -> ZRangeByScore {
+        ZRangeByScore {
+            key: key.to_string(),
+            min,
+            max,
+            with_scores: false,
+            limit: None,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the lower bound
+    pub fn min(&self) -> Bound<f64> {
+        self.min
+    }
+
+    /// Get the upper bound
+    pub fn max(&self) -> Bound<f64> {
+        self.max
+    }
+
+    /// Returns `true` if the scores are included in the reply
+    pub fn with_scores(&self) -> bool {
+        self.with_scores
+    }
+
+    /// Get the `(offset, count)` limit
+    pub fn limit(&self) -> Option<(i64, i64)> {
+        self.limit
+    }
+
+    /// Parse a `ZRangeByScore` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZRANGEBYSCORE` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRangeByScore` value on success. If the frame is malformed,
+    /// `Err` is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least four entries.
+    ///
+    /// ```text
+    /// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRangeByScore> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+        let min = parse_score_bound(&parse.next_string()?)?;
+        let max = parse_score_bound(&parse.next_string()?)?;
+
+        let mut cmd = ZRangeByScore::new(key, min, max);
+
+        // The options may be given in any order.
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "WITHSCORES" => cmd.with_scores = true,
+                Ok(s) if s.to_uppercase() == "LIMIT" => {
+                    let offset = parse.next_i64()?;
+                    let count = parse.next_i64()?;
+                    cmd.limit = Some((offset, count));
+                }
+                Ok(_) => return Err("protocol error; currently `ZRANGEBYSCORE` only supports the `WITHSCORES` and `LIMIT` options".into()),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(cmd)
+    }
+
+    /// Apply the `ZRangeByScore` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        // As in Redis, a negative offset selects nothing and a negative count
+        // selects everything after the offset.
+        let limit = self.limit.map(|(offset, count)| {
+            let offset = if offset < 0 {
+                usize::MAX
+            } else {
+                offset as usize
+            };
+            let count = if count < 0 {
+                usize::MAX
+            } else {
+                count as usize
+            };
+            (offset, count)
+        });
+
+        let response = match db.zrangebyscore(&self.key, self.min, self.max, limit) {
+            Ok(members) => members_frame(members, self.with_scores),
+            Err(err) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
+
+/// Parse a score range bound such as `1.5`, `(1.5`, `-inf` or `+inf`.
+///
+/// A leading `(` makes the bound exclusive. Infinite bounds are inclusive
+/// unless prefixed with `(`, so that members scored `-inf` or `+inf` are
+/// matched by `-inf` and `+inf`.
+fn parse_score_bound(src: &str) -> crate::Result<Bound<f64>> {
+    let (exclusive, score) = match src.strip_prefix('(') {
+        Some(score) => (true, score),
+        None => (false, src),
+    };
+
+    let score =
+        parse_f64(score.as_bytes()).map_err(|_| "protocol error; min or max is not a float")?;
+
+    if exclusive {
+        Ok(Bound::Excluded(score))
+    } else {
+        Ok(Bound::Included(score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_score_bound;
+    use std::ops::Bound;
+
+    #[test]
+    fn inclusive_and_exclusive_bounds() {
+        assert_eq!(parse_score_bound("1.5").unwrap(), Bound::Included(1.5));
+        assert_eq!(parse_score_bound("(1.5").unwrap(), Bound::Excluded(1.5));
+    }
+
+    #[test]
+    fn infinite_bounds() {
+        assert_eq!(
+            parse_score_bound("-inf").unwrap(),
+            Bound::Included(f64::NEG_INFINITY)
+        );
+        assert_eq!(
+            parse_score_bound("+inf").unwrap(),
+            Bound::Included(f64::INFINITY)
+        );
+        assert_eq!(
+            parse_score_bound("(+inf").unwrap(),
+            Bound::Excluded(f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn invalid_bounds() {
+        for src in &["(", "nan", "(nan", "abc", "1.5x", "((1", ""] {
+            assert!(parse_score_bound(src).is_err(), "{:?}", src);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::cmd::{CommandError, Parse, ParseError};
+use crate::{Connection, Db, Frame};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Removes members from the sorted set stored at key.
+///
+/// Members that do not exist are ignored. Replies with the number of members
+/// that were removed. If the sorted set becomes empty, the key is deleted.
+#[derive(Debug)]
+pub struct ZRem {
+    /// the lookup key
+    key: String,
+
+    /// the members to remove
+    members: Vec<Bytes>,
+}
+
+impl ZRem {
+    /// Create a new `ZRem` command which removes `members` from the sorted set
+    /// stored at `key`.
+    pub fn new(key: impl ToString, members: Vec<Bytes>) -> ZRem {
+        ZRem {
+            key: key.to_string(),
+            members,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the members
+    pub fn members(&self) -> &[Bytes] {
+        &self.members
+    }
+
+    /// Parse a `ZRem` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZREM` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZRem` value on success. If the frame is malformed, `Err` is
+    /// returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// ZREM key member [member ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZRem> {
+        use ParseError::EndOfStream;
+
+        let key = parse.next_string()?;
+
+        // At least one member is required.
+        let mut members = vec![parse.next_bytes()?];
+
+        // Consume the remaining members until the frame is exhausted.
+        loop {
+            match parse.next_bytes() {
+                Ok(member) => members.push(member),
+                Err(EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(ZRem { key, members })
+    }
+
+    /// Apply the `ZRem` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let response = match db.zrem(&self.key, &self.members) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
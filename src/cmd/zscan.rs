@@ -0,0 +1,144 @@
+use crate::cmd::CommandError;
+use crate::sorted_set::format_score;
+use crate::{Connection, Db, Frame, Parse, ParseError};
+
+use bytes::Bytes;
+use tracing::{debug, instrument};
+
+/// Number of members examined per call if `COUNT` is not given.
+const DEFAULT_COUNT: usize = 10;
+
+/// Incrementally iterates the members of the sorted set stored at key.
+///
+/// Start with a cursor of `0` and pass the returned cursor to the next call,
+/// until it is `0` again. Each call examines about `COUNT` members, 10 by
+/// default, and returns those matching the `MATCH` glob pattern, if given. A
+/// call may therefore return no members while the iteration is not over.
+///
+/// The reply is `[cursor, [member, score, ...]]`, each member followed by its
+/// score.
+///
+/// The cursor is the offset of the next member in score order. Members added
+/// or removed during the iteration may cause other members to be skipped or
+/// returned twice.
+#[derive(Debug)]
+pub struct ZScan {
+    /// the lookup key
+    key: String,
+
+    /// where to resume the iteration
+    cursor: u64,
+
+    /// only return members matching this glob pattern
+    pattern: Option<Bytes>,
+
+    /// number of members to examine
+    count: usize,
+}
+
+impl ZScan {
+    /// Create a new `ZScan` command which resumes iterating the sorted set
+    /// stored at `key` from `cursor`.
+    pub fn new(key: impl ToString, cursor: u64) -> ZScan {
+        ZScan {
+            key: key.to_string(),
+            cursor,
+            pattern: None,
+            count: DEFAULT_COUNT,
+        }
+    }
+
+    /// Get the key
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the cursor
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Get the `MATCH` pattern
+    pub fn pattern(&self) -> Option<&Bytes> {
+        self.pattern.as_ref()
+    }
+
+    /// Get the number of members to examine
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Parse a `ZScan` instance from a received frame.
+    ///
+    /// The `Parse` argument provides a cursor-like API to read fields from the
+    /// `Frame`. At this point, the entire frame has already been received from
+    /// the socket.
+    ///
+    /// The `ZSCAN` string has already been consumed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the `ZScan` value on success. If the frame is malformed, `Err`
+    /// is returned.
+    ///
+    /// # Format
+    ///
+    /// Expects an array frame containing at least three entries.
+    ///
+    /// ```text
+    /// ZSCAN key cursor [MATCH pattern] [COUNT count]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<ZScan> {
+        let mut zscan = ZScan::new(parse.next_string()?, parse.next_int()?);
+
+        loop {
+            match parse.next_string() {
+                Ok(s) if s.to_uppercase() == "MATCH" => {
+                    zscan.pattern = Some(parse.next_bytes()?);
+                }
+                Ok(s) if s.to_uppercase() == "COUNT" => match parse.next_int()? {
+                    0 => return Err("protocol error; `ZSCAN` count must be positive".into()),
+                    count => zscan.count = count as usize,
+                },
+                Ok(_) => {
+                    return Err(
+                        "protocol error; `ZSCAN` only supports the `MATCH` and `COUNT` options"
+                            .into(),
+                    )
+                }
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(zscan)
+    }
+
+    /// Apply the `ZScan` command to the specified `Db` instance.
+    ///
+    /// The response is written to `dst`. This is called by the server in order
+    /// to execute a received command.
+    #[instrument(skip(self, db, dst))]
+    pub(crate) async fn apply(self, db: &Db, dst: &mut Connection) -> crate::Result<()> {
+        let pattern = self.pattern.as_deref();
+
+        let response = match db.zscan(&self.key, self.cursor, pattern, self.count) {
+            Ok((cursor, members)) => {
+                let mut elements = Frame::array();
+                for (member, score) in members {
+                    elements.push_bulk(member);
+                    elements.push_bulk(format_score(score));
+                }
+
+                Frame::Array(vec![Frame::Bulk(Bytes::from(cursor.to_string())), elements])
+            }
+            Err(err) => CommandError::from(err).into(),
+        };
+
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+//! 将 `Frame` 与 `tokio_util` 的编解码器集成。
+//!
+//! 使用 `Framed::new(stream, FrameCodec)` 可以在任何 `AsyncRead + AsyncWrite` 之上获得帧的 `Stream` 和 `Sink`，而无需使用 `Connection`。
+
+use crate::frame::{self, Frame};
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Redis 协议帧的编解码器。
+///
+/// 解码使用 `Frame::parse_from`，编码使用 `Frame::write_to`，因此线路上的字节与 `Connection` 读取和写入的字节完全相同。
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameCodec;
+
+impl Decoder for FrameCodec {
+    type Item = Frame;
+    type Error = crate::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> crate::Result<Option<Frame>> {
+        match Frame::parse_from(&src[..]) {
+            Ok((frame, len)) => {
+                // 从缓冲区中丢弃已解析的数据。
+                src.advance(len);
+                Ok(Some(frame))
+            }
+            // 缓冲区中还没有完整的帧。返回 `None` 使 `Framed` 在读取更多数据后再次调用 `decode`。
+            Err(frame::Error::Incomplete) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl Encoder<Frame> for FrameCodec {
+    type Error = crate::Error;
+
+    fn encode(&mut self, frame: Frame, dst: &mut BytesMut) -> crate::Result<()> {
+        frame.write_to(dst);
+        Ok(())
+    }
+}
+
+impl Encoder<&Frame> for FrameCodec {
+    type Error = crate::Error;
+
+    fn encode(&mut self, frame: &Frame, dst: &mut BytesMut) -> crate::Result<()> {
+        frame.write_to(dst);
+        Ok(())
+    }
+}
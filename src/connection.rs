@@ -1,9 +1,14 @@
 use crate::frame::{self, Frame};
 
 use bytes::{Buf, BytesMut};
-use std::io::{self, Cursor};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
 use tokio::net::TcpStream;
+use tracing::warn;
 
 /// 从远程对等体发送和接收 `Frame` 值。
 ///
@@ -11,26 +16,157 @@ use tokio::net::TcpStream;
 ///
 /// 要读取帧，`Connection` 使用内部缓冲区，该缓冲区被填充直到有足够的字节来创建完整的帧为止。一旦发生这种情况，`Connection` 就会创建帧并将其返回给调用方。
 ///
-/// 当发送帧时，帧首先被编码到写缓冲区中。然后将写缓冲区的内容写入套接字。
+/// 当发送帧时，帧首先被编码到输出缓冲区中。然后将输出缓冲区的内容写入套接字。
+///
+/// 写入的帧会在输出缓冲区中累积，直到缓冲的字节数达到高水位线，或者 `read_frame` 需要等待对等方的数据时才刷新到套接字。这样，流水线中的多个回复只需要一次系统调用。不读取回复的调用者在写入最后一帧后应调用 `flush`。
+///
+/// 默认情况下，输出缓冲区达到高水位线后，`write_frame` 会等待对等方读取数据。设置了输出缓冲区限制后，`write_frame` 从不等待对等方，而是在缓冲的数据超过限制时返回错误，见 `set_output_buffer_limit`。
 #[derive(Debug)]
 pub struct Connection {
-    // `TcpStream`。它装饰了一个 `BufWriter`，提供写级别的缓冲。Tokio 提供的 `BufWriter` 实现对我们的需求已经足够了。
-    stream: BufWriter<TcpStream>,
+    // `TcpStream`。写入的帧由 `Connection` 自己缓冲，而不是使用 `BufWriter`，这样可以知道有多少数据尚未被对等方接受。
+    stream: TcpStream,
 
     // 用于读取帧的缓冲区。
     buffer: BytesMut,
+
+    // 已编码但尚未写入套接字的帧。
+    output: BytesMut,
+
+    // 输出缓冲区中累积的字节数达到此值时，`write_frame` 刷新到套接字。
+    write_high_water: usize,
+
+    // 输出缓冲区允许的最大字节数，`0` 表示没有限制。
+    output_buffer_limit: usize,
+
+    // 自上次调用 `take_counts` 以来的 I/O 计数。
+    counts: IoCounts,
+
+    // 读取的帧中单个数组允许的最大元素数。
+    max_array_len: usize,
+
+    // 读取的帧中单行允许的最大字节数。
+    max_line_len: usize,
+
+    // 读取的帧中单个字符串块允许的最大字节数。
+    max_bulk_len: usize,
+
+    // 读取的单个顶层帧允许的最大字节数。
+    max_frame_len: usize,
+
+    // 最近一次通过 `write_frame` 写入的帧的类型，由 `take_last_reply_kind` 取出。
+    last_reply_kind: Option<&'static str>,
+}
+
+/// 连接上的 I/O 计数，由服务器累加到其统计信息中。
+#[derive(Debug, Default)]
+pub(crate) struct IoCounts {
+    /// 从套接字读取的字节数。
+    pub(crate) bytes_read: u64,
+
+    /// 写入流的字节数。
+    pub(crate) bytes_written: u64,
+
+    /// 写入的错误帧数。
+    pub(crate) error_replies: u64,
+}
+
+/// `read_frame` 可能返回的、与 I/O 和帧格式无关的连接错误。
+///
+/// 调用者可以通过 `downcast_ref` 从 `crate::Error` 中取出它，以区分对等方异常断开的情况，例如服务器以较低的级别记录这些错误。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionError {
+    /// 对等方在发送帧的过程中关闭了连接，读缓冲区中留下了不完整的帧。
+    ///
+    /// 在帧之间关闭连接是正常的结束，`read_frame` 返回 `Ok(None)` 而不是此错误。
+    ResetMidFrame,
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionError::ResetMidFrame => "对等方重置了连接".fmt(fmt),
+        }
+    }
 }
 
+impl std::error::Error for ConnectionError {}
+
+/// `poll_read_frame` 每次从套接字读取的最大字节数。
+const POLL_READ_CHUNK: usize = 4 * 1024;
+
+/// `Connection::new` 使用的输出缓冲区的初始容量，与 `BufWriter` 的默认值相同。
+pub(crate) const DEFAULT_WRITE_BUFFER_CAPACITY: usize = 8 * 1024;
+
 impl Connection {
     /// 创建一个新的 `Connection`，由 `socket` 支持。初始化读取和写入缓冲区。
+    ///
+    /// 输出缓冲区的初始容量为 8 KiB。
     pub fn new(socket: TcpStream) -> Connection {
+        Connection::with_capacity(socket, DEFAULT_WRITE_BUFFER_CAPACITY)
+    }
+
+    /// 创建一个新的 `Connection`，其输出缓冲区的初始容量为 `write_capacity` 字节。
+    ///
+    /// 累积的回复达到初始容量的一半时刷新到套接字，因此较大的容量可以让更长的流水线只用一次系统调用写出，代价是每个连接占用更多内存。
+    pub fn with_capacity(socket: TcpStream, write_capacity: usize) -> Connection {
         Connection {
-            stream: BufWriter::new(socket),
+            stream: socket,
             // 默认使用 4KB 读取缓冲区。对于 mini redis 的用例，这是可以的。但是，真实的应用程序将希望调整此值以适应其特定的用例。很可能更大的读取缓冲区效果更好。
             buffer: BytesMut::with_capacity(4 * 1024),
+            output: BytesMut::with_capacity(write_capacity),
+            write_high_water: write_capacity / 2,
+            output_buffer_limit: 0,
+            counts: IoCounts::default(),
+            max_array_len: frame::DEFAULT_MAX_ARRAY_LEN,
+            max_line_len: frame::DEFAULT_MAX_LINE_LEN,
+            max_bulk_len: frame::DEFAULT_MAX_BULK_LEN,
+            max_frame_len: frame::DEFAULT_MAX_FRAME_LEN,
+            last_reply_kind: None,
         }
     }
 
+    /// 设置读取的帧中单个数组允许的最大元素数，默认为 `frame::DEFAULT_MAX_ARRAY_LEN`。
+    ///
+    /// 收到超过限制的数组时，`read_frame` 返回错误，连接应被关闭。
+    pub fn set_max_array_len(&mut self, max_array_len: usize) {
+        self.max_array_len = max_array_len;
+    }
+
+    /// 设置读取的帧中单行允许的最大字节数，默认为 `frame::DEFAULT_MAX_LINE_LEN`。
+    ///
+    /// 在收到 `\r\n` 之前，未结束的行会一直留在读缓冲区中。一旦它超过限制，`read_frame` 就返回错误，连接应被关闭，而不是继续缓冲。
+    pub fn set_max_line_len(&mut self, max_line_len: usize) {
+        self.max_line_len = max_line_len;
+    }
+
+    /// 设置读取的帧中单个字符串块允许的最大字节数，默认为 `frame::DEFAULT_MAX_BULK_LEN`。
+    ///
+    /// 收到声明的长度超过限制的字符串块时，`read_frame` 立即返回错误，连接应被关闭，而不是缓冲其内容。
+    pub fn set_max_bulk_len(&mut self, max_bulk_len: usize) {
+        self.max_bulk_len = max_bulk_len;
+    }
+
+    /// 设置读取的单个顶层帧（即一条命令）允许的最大字节数，默认为 `frame::DEFAULT_MAX_FRAME_LEN`。
+    ///
+    /// 帧的总字节数在检查时逐步累计，一旦超过限制，`read_frame` 就返回错误，连接应被关闭，而不是继续缓冲剩余的元素。
+    pub fn set_max_frame_len(&mut self, max_frame_len: usize) {
+        self.max_frame_len = max_frame_len;
+    }
+
+    /// 设置输出缓冲区允许的最大字节数，默认为 `0`，表示没有限制。
+    ///
+    /// 设置了限制时，`write_frame` 只写入套接字当前能够接受的数据，不等待对等方读取，其余的留在输出缓冲区中。缓冲的数据超过限制时，`write_frame` 记录警告并返回错误，连接应被关闭。这样，从不读取的订阅者不会让服务器无限地缓冲消息，也不会让连接处理程序一直阻塞。
+    pub fn set_output_buffer_limit(&mut self, limit: usize) {
+        self.output_buffer_limit = limit;
+    }
+
+    /// 返回连接的远程对等方地址。
+    ///
+    /// 直接委托给底层的 `TcpStream`。
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
     /// 从底层流中读取单个 `Frame` 值。
     ///
     /// 该函数等待直到它已检索足够的数据来解析一帧。在解析帧之后，保留在读缓冲区中的任何剩余数据将保留在那里，供下一次调用 `read_frame` 使用。
@@ -45,41 +181,80 @@ impl Connection {
                 return Ok(Some(frame));
             }
 
-            // 缓冲区中没有足够的数据来读取帧。尝试从套接字中读取更多数据。
+            // 缓冲区中没有足够的数据来读取帧。在等待对等方之前，先将累积的回复写入套接字，否则对等方可能正在等待这些回复，双方都无法继续。
+            if !self.output.is_empty() {
+                self.flush().await?;
+            }
+
+            // 尝试从套接字中读取更多数据。
             //
             // 成功时，返回读取的字节数。`0` 表示“流结束”。
-            if 0 == self.stream.read_buf(&mut self.buffer).await? {
+            let n = self.stream.read_buf(&mut self.buffer).await?;
+            self.counts.bytes_read += n as u64;
+
+            if 0 == n {
                 // 远程关闭了连接。为了使其成为正常关闭，读缓冲区中不应有数据。如果有，这意味着对等体在发送帧时关闭了套接字。
                 if self.buffer.is_empty() {
                     return Ok(None);
                 } else {
-                    return Err("对等方重置了连接".into());
+                    return Err(ConnectionError::ResetMidFrame.into());
                 }
             }
         }
     }
 
-    /// 尝试从缓冲区解析帧。如果缓冲区包含足够的数据，则返回帧并从缓冲区中删除数据。如果尚未缓冲足够的数据，则返回 `Ok(None)`。如果缓冲的数据不表示有效的帧，则返回 `Err`。
-    fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
-        use frame::Error::Incomplete;
+    /// `read_frame` 的轮询版本，供手动实现 `Future` 或 `Stream` 的类型使用，例如 `Subscriber`。
+    ///
+    /// 返回 `Poll::Pending` 时，已读取的数据保留在读缓冲区中，下次调用时继续解析，因此可以在任意两次调用之间放弃等待。返回值的含义与 `read_frame` 相同。
+    ///
+    /// 与 `read_frame` 不同，数据先读入缓冲区末尾新增的已初始化区域，而不是直接读入未初始化的容量，以避免 `unsafe` 代码。每次最多读取 `POLL_READ_CHUNK` 字节，因此适合频繁的小帧，而不适合大的字符串块。
+    pub(crate) fn poll_read_frame(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<crate::Result<Option<Frame>>> {
+        loop {
+            if let Some(frame) = self.parse_frame()? {
+                return Poll::Ready(Ok(Some(frame)));
+            }
 
-        // 使用 Cursor 跟踪缓冲区中的“当前”位置。Cursor 还实现了来自 `bytes` crate 的 `Buf`，为使用字节提供了许多有用的工具。
-        let mut buf = Cursor::new(&self.buffer[..]);
+            // 与 `read_frame` 一样，在等待对等方之前先写出累积的回复。
+            ready!(self.poll_flush(cx))?;
 
-        // 首先检查是否已经缓冲足够的数据来解析单个帧。这一步通常比对帧的完整解析要快得多，并且允许我们跳过分配数据结构以保存帧数据，除非我们知道已接收到完整的帧。
-        match Frame::check(&mut buf) {
-            Ok(_) => {
-                // `check` 函数将使光标前进到帧的末尾。由于在调用 `Frame::check` 之前，光标的位置被设置为零，因此通过检查光标位置来获取帧的长度。
-                let len = buf.position() as usize;
+            let len = self.buffer.len();
+            self.buffer.resize(len + POLL_READ_CHUNK, 0);
 
-                // 在传递光标给 `Frame::parse` 之前，将位置重置为零。
-                buf.set_position(0);
+            let mut buf = ReadBuf::new(&mut self.buffer[len..]);
+            let res = Pin::new(&mut self.stream).poll_read(cx, &mut buf);
+            let n = buf.filled().len();
 
-                // 从缓冲区解析帧。这将分配用于表示帧的必要结构，并返回帧值。
-                //
-                // 如果编码的帧表示无效，则返回错误。这应该终止**当前**连接，但不应影响任何其他连接的客户端。
-                let frame = Frame::parse(&mut buf)?;
+            // 无论读取是否完成，都要丢弃未填充的部分，使读缓冲区只包含收到的数据。
+            self.buffer.truncate(len + n);
+            ready!(res)?;
+            self.counts.bytes_read += n as u64;
 
+            if 0 == n {
+                if self.buffer.is_empty() {
+                    return Poll::Ready(Ok(None));
+                } else {
+                    return Poll::Ready(Err(ConnectionError::ResetMidFrame.into()));
+                }
+            }
+        }
+    }
+
+    /// 尝试从缓冲区解析帧。如果缓冲区包含足够的数据，则返回帧并从缓冲区中删除数据。如果尚未缓冲足够的数据，则返回 `Ok(None)`。如果缓冲的数据不表示有效的帧，则返回 `Err`。
+    fn parse_frame(&mut self) -> crate::Result<Option<Frame>> {
+        use frame::Error::Incomplete;
+
+        // 如果编码的帧表示无效，则返回错误。这应该终止**当前**连接，但不应影响任何其他连接的客户端。
+        match Frame::parse_from_with_limits(
+            &self.buffer[..],
+            self.max_array_len,
+            self.max_line_len,
+            self.max_bulk_len,
+            self.max_frame_len,
+        ) {
+            Ok((frame, len)) => {
                 // 从读缓冲区中丢弃已解析的数据。
                 //
                 // 当在读缓冲区上调用 `advance` 时，所有数据都将被丢弃，直到 `len`。关于这是如何工作的详细信息留给了 `BytesMut`。通常，这是通过移动内部光标来完成的，但也可以通过重新分配和复制数据来完成。
@@ -99,78 +274,92 @@ impl Connection {
 
     /// 将单个 `Frame` 值写入底层流。
     ///
-    /// 使用 `AsyncWrite` 提供的各种 `write_*` 函数将 `Frame` 值写入套接字。直接在 `TcpStream` 上调用这些函数**不**是建议的，因为这将导致大量的系统调用。但是，在*缓冲*写流上调用这些函数是可以的。数据将被写入缓冲区。一旦缓冲区满，它就会刷新到底层套接字。
+    /// 帧由 `Frame::write_to` 编码到输出缓冲区中。直接在 `TcpStream` 上写入每一帧**不**是建议的，因为这将导致大量的系统调用。只有在缓冲的字节数达到高水位线时才刷新到底层套接字。
+    ///
+    /// 因此返回时帧不一定已经发送。之后的 `read_frame` 或 `flush` 会将其发送出去。
+    ///
+    /// 如果设置了输出缓冲区限制，并且缓冲的数据超过了限制，则返回错误。
     pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
-        // 数组通过编码每个条目来进行编码。所有其他帧类型都被视为字面值。目前，mini-redis 不能编码递归帧结构。有关更多详细信息，请参见下文。
-        match frame {
-            Frame::Array(val) => {
-                // 编码帧类型前缀。对于数组，它是 `*`。
-                self.stream.write_u8(b'*').await?;
-
-                // 编码数组的长度。
-                self.write_decimal(val.len() as u64).await?;
-
-                // 迭代并编码数组中的每个条目。
-                for entry in &**val {
-                    self.write_value(entry).await?;
-                }
+        self.last_reply_kind = Some(frame.kind());
+        self.counts.error_replies += frame.error_count();
+
+        let len = self.output.len();
+        frame.write_to(&mut self.output);
+        self.counts.bytes_written += (self.output.len() - len) as u64;
+
+        // 累积的回复足够多时才将其写入套接字，其余的等到读取下一个请求之前或调用 `flush` 时再写入。
+        if self.output_buffer_limit == 0 {
+            if self.output.len() >= self.write_high_water {
+                self.flush().await?;
+            }
+        } else if self.output.len() >= self.write_high_water.min(self.output_buffer_limit) {
+            // 有限制时不等待对等方，未能写出的数据留在输出缓冲区中。
+            self.try_flush()?;
+
+            if self.output.len() > self.output_buffer_limit {
+                warn!(
+                    pending = self.output.len(),
+                    limit = self.output_buffer_limit,
+                    "output buffer limit exceeded, closing connection"
+                );
+                return Err(io::Error::other("输出缓冲区超过限制"));
             }
-            // 帧类型是字面值。直接编码该值。
-            _ => self.write_value(frame).await?,
         }
 
-        // 确保编码的帧被写入套接字。上面的调用是对缓冲流和写入的调用。调用 `flush` 将缓冲区的剩余内容写入套接字。
-        self.stream.flush().await
+        Ok(())
     }
 
-    /// 将帧字面值写入流
-    async fn write_value(&mut self, frame: &Frame) -> io::Result<()> {
-        match frame {
-            Frame::Simple(val) => {
-                self.stream.write_u8(b'+').await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Error(val) => {
-                self.stream.write_u8(b'-').await?;
-                self.stream.write_all(val.as_bytes()).await?;
-                self.stream.write_all(b"\r\n").await?;
-            }
-            Frame::Integer(val) => {
-                self.stream.write_u8(b':').await?;
-                self.write_decimal(*val).await?;
-            }
-            Frame::Null => {
-                self.stream.write_all(b"$-1\r\n").await?;
-            }
-            Frame::Bulk(val) => {
-                let len = val.len();
+    /// 将输出缓冲区中累积的所有帧写入套接字，必要时等待对等方读取。
+    ///
+    /// 服务器在处理完一批流水线请求后调用此函数。写入帧后不再读取的调用者也必须调用它，否则帧可能永远不会被发送。
+    pub async fn flush(&mut self) -> io::Result<()> {
+        std::future::poll_fn(|cx| self.poll_flush(cx)).await
+    }
 
-                self.stream.write_u8(b'$').await?;
-                self.write_decimal(len as u64).await?;
-                self.stream.write_all(val).await?;
-                self.stream.write_all(b"\r\n").await?;
+    /// 将输出缓冲区中的数据写入套接字，直到全部写出或套接字暂时无法接受更多数据为止，不等待对等方。
+    ///
+    /// 未能写出的数据留在输出缓冲区中，由之后的 `flush` 或 `read_frame` 写出。
+    pub(crate) fn try_flush(&mut self) -> io::Result<()> {
+        while !self.output.is_empty() {
+            match self.stream.try_write(&self.output) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => self.output.advance(n),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
             }
-            // 不能使用递归策略从值中编码 `Array`。一般来说，异步 fn 不支持递归。mini-redis 尚未需要编码嵌套数组，因此目前跳过。
-            Frame::Array(_val) => unreachable!(),
         }
 
         Ok(())
     }
 
-    /// 将十进制帧写入流
-    async fn write_decimal(&mut self, val: u64) -> io::Result<()> {
-        use std::io::Write;
+    /// `flush` 的轮询版本。
+    ///
+    /// 每次写入后立即从输出缓冲区中丢弃已写出的数据，因此在返回 `Poll::Pending` 后放弃等待不会重复或丢失数据。
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.output.is_empty() {
+            let n = ready!(Pin::new(&mut self.stream).poll_write(cx, &self.output))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
 
-        // 将值转换为字符串
-        let mut buf = [0u8; 20];
-        let mut buf = Cursor::new(&mut buf[..]);
-        write!(&mut buf, "{}", val)?;
+            self.output.advance(n);
+        }
 
-        let pos = buf.position() as usize;
-        self.stream.write_all(&buf.get_ref()[..pos]).await?;
-        self.stream.write_all(b"\r\n").await?;
+        Poll::Ready(Ok(()))
+    }
 
-        Ok(())
+    /// 如果读缓冲区中还有尚未解析的数据，即对等方已经发送了下一个请求的一部分，则返回 `true`。
+    pub(crate) fn has_buffered_input(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// 返回自上次调用以来的 I/O 计数，并将其重置。
+    pub(crate) fn take_counts(&mut self) -> IoCounts {
+        std::mem::take(&mut self.counts)
+    }
+
+    /// 返回自上次调用以来最近一次写入的帧的类型，例如 `"bulk"` 或 `"error"`。如果没有写入帧，则返回 `None`。
+    pub(crate) fn take_last_reply_kind(&mut self) -> Option<&'static str> {
+        self.last_reply_kind.take()
     }
 }
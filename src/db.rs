@@ -1,8 +1,19 @@
 use tokio::sync::{broadcast, Notify};
+use tokio::task;
 use tokio::time::{self, Duration, Instant};
 
-use bytes::Bytes;
+use crate::glob;
+use crate::server::MAX_PUBSUB_CAPACITY;
+use crate::sorted_set::{member_memory_usage, SortedSet};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
 use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::mem;
+use std::ops::{Bound, Range};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use tracing::debug;
 
@@ -36,13 +47,32 @@ struct Shared {
 
     /// 通知处理条目过期的后台任务。后台任务等待此通知，然后检查过期值或关闭信号。
     background_task: Notify,
+
+    /// 之后创建的发布/订阅通道的容量，即为最慢的订阅者保留的消息数。可以通过 `CONFIG SET` 修改，已经存在的通道不受影响。
+    ///
+    /// 与内存上限一样，只是一个独立的数值，不需要与其他状态保持一致，因此使用原子变量而不是锁。
+    pubsub_capacity: AtomicUsize,
+
+    /// 后台任务每次持有锁时最多删除的过期键数。创建后不会改变。
+    expire_batch_size: usize,
+
+    /// 估算的内存使用量的上限（字节），`0` 表示没有限制。可以通过 `CONFIG SET` 修改。
+    maxmemory: AtomicUsize,
 }
 
 #[derive(Debug)]
 struct State {
     /// 键值数据。我们不打算做任何花哨的事情，所以 `std::collections::HashMap` 完全可以工作。
+    ///
+    /// 条目只通过 `insert` 和 `remove` 添加和删除，以便 `keys` 和 `used_memory` 保持一致。
     entries: HashMap<String, Entry>,
 
+    /// `entries` 中所有键的副本，顺序任意。`HashMap` 无法按位置访问，淘汰键时通过此列表在常数时间内随机抽样。每个条目的 `slot` 是其键在此列表中的位置。
+    keys: Vec<String>,
+
+    /// 所有条目的 `Entry::approx_memory_usage` 之和。每次添加、删除或修改条目时更新，因此估算内存使用量不需要遍历键空间。
+    used_memory: usize,
+
     /// 发布/订阅键空间。Redis 使用一个 **单独的** 键空间用于键值和发布/订阅。`mini-redis` 通过使用一个单独的 `HashMap` 来处理这一点。
     pub_sub: HashMap<String, broadcast::Sender<Bytes>>,
 
@@ -55,22 +85,193 @@ struct State {
 
     /// 当 Db 实例正在关闭时为 true。当所有 `Db` 值都被丢弃时会发生这种情况。将其设置为 `true` 会向后台任务发出退出信号。
     shutdown: bool,
+
+    /// 用于随机选择键的快速伪随机数生成器。它不是密码学安全的，只在创建 `Db` 时从系统熵播种一次。
+    rng: SmallRng,
+
+    /// 因内存限制而被删除的键数，由 `INFO stats` 报告。
+    evicted_keys: u64,
+
+    /// 内存使用量超过 `maxmemory` 时选择要删除的键的策略。只在持有锁删除键时读取，因此存储在这里。
+    maxmemory_policy: EvictionPolicy,
 }
 
 /// 键值存储中的条目
 #[derive(Debug)]
 struct Entry {
     /// 存储的数据
-    data: Bytes,
+    value: Value,
 
     /// 条目过期并应从数据库中删除的时刻。
     expires_at: Option<Instant>,
+
+    /// 条目最后一次被命令读取或修改的时刻，用于 `OBJECT IDLETIME` 和 LRU 淘汰。
+    last_access: Instant,
+
+    /// 键在 `State::keys` 中的位置，由 `State::insert` 设置。
+    slot: usize,
+
+    /// 近似的访问频率，用于 `OBJECT FREQ` 和 LFU 淘汰。
+    ///
+    /// 与 Redis 一样，这是一个 8 位的对数计数器：每次访问时以随计数增大而减小的概率递增，并且每空闲 `LFU_DECAY_PERIOD` 减一。新键从 `LFU_INIT_VAL` 开始，以免刚创建就被淘汰。
+    frequency: u8,
+}
+
+/// 内存使用量超过限制时选择要删除的键的策略。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// 不删除任何键。超过限制时，可能增加内存使用量的命令会被拒绝。
+    #[default]
+    NoEviction,
+
+    /// 删除最久未被访问的键。
+    AllKeysLru,
+
+    /// 删除访问频率最低的键，频率相同时删除最久未被访问的键。
+    AllKeysLfu,
+}
+
+impl EvictionPolicy {
+    /// 每个策略及其名称，名称与 Redis 的 `maxmemory-policy` 配置相同。
+    const NAMES: [(EvictionPolicy, &'static str); 3] = [
+        (EvictionPolicy::NoEviction, "noeviction"),
+        (EvictionPolicy::AllKeysLru, "allkeys-lru"),
+        (EvictionPolicy::AllKeysLfu, "allkeys-lfu"),
+    ];
+
+    /// 返回策略的名称，例如 `"allkeys-lru"`。
+    pub(crate) fn name(self) -> &'static str {
+        EvictionPolicy::NAMES
+            .iter()
+            .find(|(policy, _)| *policy == self)
+            .map(|(_, name)| *name)
+            .unwrap()
+    }
+
+    /// 返回名为 `name` 的策略，不区分大小写。
+    pub(crate) fn from_name(name: &str) -> Option<EvictionPolicy> {
+        EvictionPolicy::NAMES
+            .iter()
+            .find(|(_, candidate)| candidate.eq_ignore_ascii_case(name))
+            .map(|(policy, _)| *policy)
+    }
+}
+
+/// 新键的访问频率计数器的初始值。
+const LFU_INIT_VAL: u8 = 5;
+
+/// 访问频率计数器的对数因子。越大，计数器增长得越慢。
+const LFU_LOG_FACTOR: f64 = 10.0;
+
+/// 键每空闲这么长时间，其访问频率计数器减一。
+const LFU_DECAY_PERIOD: Duration = Duration::from_secs(60);
+
+/// 每淘汰一个键时随机抽样的键数，与 Redis 的 `maxmemory-samples` 默认值相同。
+const EVICTION_SAMPLES: usize = 5;
+
+/// 键所存储的值。
+#[derive(Debug)]
+enum Value {
+    /// 字符串，由 `GET`、`SET` 等命令使用。
+    String(Bytes),
+
+    /// 有序集合，由 `ZADD`、`ZRANGE` 等命令使用。
+    SortedSet(SortedSet),
+}
+
+/// `Db::stats` 返回的计数器快照。
+#[derive(Debug, Clone)]
+pub(crate) struct DbStats {
+    /// 键空间中的键数。逻辑上已过期但后台任务尚未清理的键也会被计算在内，这与 Redis 一致。
+    pub(crate) keys: usize,
+
+    /// 设置了过期时间的键数。
+    pub(crate) expires: usize,
+
+    /// 至少有一个订阅者的发布/订阅通道数。
+    pub(crate) channels: usize,
+
+    /// 所有通道的订阅总数。订阅了多个通道的客户端会被多次计算。
+    pub(crate) subscribers: usize,
+
+    /// 因内存限制而被删除的键数。
+    pub(crate) evicted_keys: u64,
+}
+
+/// `Db::object_info` 返回的键的内部信息。
+#[derive(Debug, Clone)]
+pub(crate) struct ObjectInfo {
+    /// 值的内部编码名称，与 `OBJECT ENCODING` 相同。
+    pub(crate) encoding: &'static str,
+
+    /// 值的 `DUMP` 载荷的字节数。
+    pub(crate) serialized_length: usize,
+
+    /// 键自上次被访问以来经过的时间。
+    pub(crate) idle: Duration,
+
+    /// 集合类型的元素数。字符串为 `None`。
+    pub(crate) len: Option<usize>,
+}
+
+/// 对存储了其他类型值的键执行命令时返回的错误，例如对有序集合执行 `GET`。
+#[derive(Debug)]
+pub(crate) struct WrongType;
+
+/// 淘汰策略不是 `allkeys-lfu` 时查询访问频率返回的错误，此时访问频率不用于淘汰，与 Redis 一样不报告它。
+#[derive(Debug)]
+pub(crate) struct LfuNotSelected;
+
+/// `Db::restore` 可能返回的错误。
+#[derive(Debug)]
+pub(crate) enum RestoreError {
+    /// 键已存在，并且没有要求替换它。
+    BusyKey,
+
+    /// 载荷不是由 `Db::dump` 生成的，或者已损坏。
+    BadPayload,
+}
+
+/// `DUMP` 载荷格式的版本，写在载荷的最后一个字节。格式改变时必须递增，以便 `RESTORE` 拒绝旧的载荷。
+const DUMP_VERSION: u8 = 1;
+
+/// `DUMP` 载荷中字符串值的类型标记。
+const DUMP_STRING: u8 = 0;
+
+/// `DUMP` 载荷中有序集合值的类型标记。
+const DUMP_SORTED_SET: u8 = 1;
+
+/// 对字符串执行数值增量时可能发生的错误。
+#[derive(Debug)]
+pub(crate) enum IncrError {
+    /// 键存储的不是字符串。
+    WrongType,
+
+    /// 现有值不是有效的浮点数。
+    NotAFloat,
+
+    /// 结果为 NaN 或无穷大。
+    NotFinite,
 }
 
 impl DbDropGuard {
     /// 创建一个新的 `DbHolder`，包装一个 `Db` 实例。当此结构被丢弃时，`Db` 的清理任务将被关闭。
-    pub(crate) fn new() -> DbDropGuard {
-        DbDropGuard { db: Db::new() }
+    ///
+    /// 参数的含义参见 `Db::new`。
+    pub(crate) fn new(
+        pubsub_capacity: usize,
+        expire_batch_size: usize,
+        maxmemory: usize,
+        maxmemory_policy: EvictionPolicy,
+    ) -> DbDropGuard {
+        DbDropGuard {
+            db: Db::new(
+                pubsub_capacity,
+                expire_batch_size,
+                maxmemory,
+                maxmemory_policy,
+            ),
+        }
     }
 
     /// 获取共享数据库。内部是一个 `Arc`，因此克隆只会增加引用计数。
@@ -88,15 +289,48 @@ impl Drop for DbDropGuard {
 
 impl Db {
     /// 创建一个新的、空的 `Db` 实例。分配共享状态并启动一个后台任务来管理键的过期。
-    pub(crate) fn new() -> Db {
+    ///
+    /// 发布/订阅通道在第一次订阅时创建，每个通道最多保留 `pubsub_capacity` 条尚未被所有订阅者看到的消息。
+    ///
+    /// 后台任务每次持有锁时最多删除 `expire_batch_size` 个过期的键。
+    ///
+    /// 如果 `maxmemory` 不为零，`free_memory` 会按照 `maxmemory_policy` 删除键，使估算的内存使用量不超过 `maxmemory`。
+    ///
+    /// # Panics
+    ///
+    /// 如果 `pubsub_capacity` 为零或大于 `MAX_PUBSUB_CAPACITY`，或者 `expire_batch_size` 为零，则会 panic。
+    pub(crate) fn new(
+        pubsub_capacity: usize,
+        expire_batch_size: usize,
+        maxmemory: usize,
+        maxmemory_policy: EvictionPolicy,
+    ) -> Db {
+        assert!(
+            pubsub_capacity > 0 && pubsub_capacity <= MAX_PUBSUB_CAPACITY,
+            "pub/sub capacity must be between 1 and {}",
+            MAX_PUBSUB_CAPACITY
+        );
+        assert!(
+            expire_batch_size > 0,
+            "expire batch size must be greater than zero"
+        );
+
         let shared = Arc::new(Shared {
             state: Mutex::new(State {
                 entries: HashMap::new(),
+                keys: Vec::new(),
+                used_memory: 0,
                 pub_sub: HashMap::new(),
                 expirations: BTreeSet::new(),
                 shutdown: false,
+                rng: SmallRng::from_entropy(),
+                evicted_keys: 0,
+                maxmemory_policy,
             }),
             background_task: Notify::new(),
+            pubsub_capacity: AtomicUsize::new(pubsub_capacity),
+            expire_batch_size,
+            maxmemory: AtomicUsize::new(maxmemory),
         });
 
         // 启动后台任务。
@@ -107,18 +341,746 @@ impl Db {
 
     /// 获取与键关联的值。
     ///
-    /// 如果键没有关联的值，则返回 `None`。这可能是由于从未为键分配值，或者先前分配的值已过期。
-    pub(crate) fn get(&self, key: &str) -> Option<Bytes> {
+    /// 如果键没有关联的值，则返回 `None`。这可能是由于从未为键分配值，或者先前分配的值已过期。如果键存储的不是字符串，则返回 `WrongType`。
+    ///
+    /// 已过期但后台任务尚未清理的键被视为不存在，并在此时被删除，这与 Redis 的惰性过期一致。
+    pub(crate) fn get(&self, key: &str) -> Result<Option<Bytes>, WrongType> {
         // 获取锁，获取条目并克隆值。
         //
         // 由于使用 `Bytes` 存储数据，在这里的克隆是浅层克隆。数据不会被复制。
+        let mut state = self.shared.state.lock().unwrap();
+
+        state
+            .access(key, Instant::now())
+            .map(|entry| entry.value.expect_string().cloned())
+            .transpose()
+    }
+
+    /// 在同一次持有锁期间获取两个键的值，得到的是键空间的一致快照。
+    ///
+    /// 不存在的键返回 `None`。如果任何一个键存储的不是字符串，则返回 `WrongType`。
+    pub(crate) fn get_pair(
+        &self,
+        key1: &str,
+        key2: &str,
+    ) -> Result<(Option<Bytes>, Option<Bytes>), WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        let mut get = |key| {
+            state
+                .access(key, now)
+                .map(|entry| entry.value.expect_string().cloned())
+                .transpose()
+        };
+
+        Ok((get(key1)?, get(key2)?))
+    }
+
+    /// 获取与键关联的值，并将其到期时间更新为 `expires_at`。`None` 会移除到期时间，使键永久存在。
+    ///
+    /// 读取和更新在同一次持有锁期间完成。如果键不存在，则不做任何修改并返回 `None`。如果键存储的不是字符串，则返回 `WrongType`，到期时间保持不变。
+    pub(crate) fn get_and_expire(
+        &self,
+        key: &str,
+        expires_at: Option<Instant>,
+    ) -> Result<Option<Bytes>, WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        let value = match state.access(key, Instant::now()) {
+            Some(entry) => entry.value.expect_string()?.clone(),
+            None => return Ok(None),
+        };
+
+        let notify = state.set_expiration(key, expires_at);
+
+        // 与 `set` 一样，在通知后台任务之前释放互斥锁。
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        Ok(Some(value))
+    }
+
+    /// 如果 `allow` 接受键当前的到期时间，则将键的到期时间设置为 `expires_at`，并返回 `true`。
+    ///
+    /// `allow` 的参数是键当前的到期时间，键没有到期时间时为 `None`。比较和更新在同一次持有锁期间完成，因此不会与其他修改到期时间的命令交错。`expires_at` 不晚于当前时刻时，键被立即删除。如果键不存在，则返回 `false`。
+    pub(crate) fn expire(
+        &self,
+        key: &str,
+        expires_at: Instant,
+        allow: impl FnOnce(Option<Instant>) -> bool,
+    ) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        let current = match state.access(key, now) {
+            Some(entry) => entry.expires_at,
+            None => return false,
+        };
+
+        if !allow(current) {
+            return false;
+        }
+
+        if expires_at <= now {
+            state.remove(key);
+            return true;
+        }
+
+        let notify = state.set_expiration(key, Some(expires_at));
+
+        // 与 `set` 一样，在通知后台任务之前释放互斥锁。
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        true
+    }
+
+    /// 返回与键关联的值；如果键不存在，则将其设置为 `default` 并返回 `default`。
+    ///
+    /// 读取和插入在同一次持有锁期间完成，因此并发调用者对同一个键得到的结果一致：只有一个调用者的 `default` 会被存储。已过期的键视为不存在。新插入的键没有到期时间。如果键存储的不是字符串，则返回 `WrongType`。
+    pub(crate) fn get_or_set(&self, key: &str, default: Bytes) -> Result<Bytes, WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        if let Some(entry) = state.access(key, Instant::now()) {
+            return entry.value.expect_string().cloned();
+        }
+
+        state.insert(
+            key.to_string(),
+            Entry::new(Value::String(default.clone()), None, Instant::now()),
+        );
+
+        Ok(default)
+    }
+
+    /// 将键的值序列化为 `DUMP` 载荷，如果键不存在，则返回 `None`。
+    ///
+    /// 载荷包含值的类型和内容，但不包含到期时间，可以通过 `restore` 在另一个实例上重新创建该值。
+    pub(crate) fn dump(&self, key: &str) -> Option<Bytes> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        state
+            .access(key, Instant::now())
+            .map(|entry| entry.value.dump())
+    }
+
+    /// 从 `dump` 生成的载荷重新创建键的值，到期时间为 `expires_at`。
+    ///
+    /// 如果键已存在且 `replace` 为 `false`，则返回 `BusyKey`；如果 `replace` 为 `true`，则替换现有的值及其到期时间。如果载荷无效，则返回 `BadPayload`，键保持不变。
+    pub(crate) fn restore(
+        &self,
+        key: &str,
+        payload: &[u8],
+        expires_at: Option<Instant>,
+        replace: bool,
+    ) -> Result<(), RestoreError> {
+        // 在获取锁之前解析载荷，大的载荷不会阻塞其他连接。
+        let value = Value::restore(payload).ok_or(RestoreError::BadPayload)?;
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.remove_expired(key, Instant::now());
+
+        if state.entries.contains_key(key) {
+            if !replace {
+                return Err(RestoreError::BusyKey);
+            }
+
+            state.remove(key);
+        }
+
+        state.insert(key.to_string(), Entry::new(value, None, Instant::now()));
+        let notify = state.set_expiration(key, expires_at);
+
+        // 与 `set` 一样，在通知后台任务之前释放互斥锁。
+        drop(state);
+
+        if notify {
+            self.shared.background_task.notify_one();
+        }
+
+        Ok(())
+    }
+
+    /// 从键空间中均匀随机地返回一个键。
+    ///
+    /// 逻辑上已过期（但后台任务尚未清理）的键会被跳过。如果没有可用的键，则返回 `None`。
+    ///
+    /// `HashMap` 无法按索引直接访问，因此该操作是 O(N) 的：先统计存活的键，再迭代到随机选出的位置。
+    pub(crate) fn random_key(&self) -> Option<String> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        // 获取“真正的” `&mut State`，以便同时借用 `entries` 和 `rng`。
+        let state = &mut *state;
+        let now = Instant::now();
+
+        let live = state
+            .entries
+            .values()
+            .filter(|entry| !entry.is_expired(now))
+            .count();
+
+        if live == 0 {
+            return None;
+        }
+
+        let index = state.rng.gen_range(0..live);
+
+        state
+            .entries
+            .iter()
+            .filter(|(_, entry)| !entry.is_expired(now))
+            .nth(index)
+            .map(|(key, _)| key.clone())
+    }
+
+    /// 返回键自上次被访问以来经过的时间，供 `OBJECT IDLETIME` 使用。如果键不存在，则返回 `None`。
+    ///
+    /// 查询本身不算作访问，不会重置空闲时间。
+    pub(crate) fn idle_time(&self, key: &str) -> Option<Duration> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+        state.remove_expired(key, now);
+
+        state.entries.get(key).map(|entry| entry.idle_time(now))
+    }
+
+    /// 返回键经过衰减的访问频率计数器，供 `OBJECT FREQ` 使用。如果键不存在，则返回 `None`；如果键存在但淘汰策略不是 `allkeys-lfu`，则返回 `LfuNotSelected`。
+    ///
+    /// 与 `idle_time` 一样，查询本身不算作访问。
+    pub(crate) fn frequency(&self, key: &str) -> Result<Option<u8>, LfuNotSelected> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+        state.remove_expired(key, now);
+
+        let entry = match state.entries.get(key) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        if state.maxmemory_policy != EvictionPolicy::AllKeysLfu {
+            return Err(LfuNotSelected);
+        }
+
+        Ok(Some(entry.frequency(now)))
+    }
+
+    /// 返回键所存储值的内部编码名称，供 `OBJECT ENCODING` 使用。如果键不存在，则返回 `None`。
+    pub(crate) fn encoding(&self, key: &str) -> Option<&'static str> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.remove_expired(key, Instant::now());
+
+        state.entries.get(key).map(|entry| entry.value.encoding())
+    }
+
+    /// 返回键的内部信息，供 `DEBUG OBJECT` 使用。如果键不存在，则返回 `None`。
+    ///
+    /// 所有字段都在同一次锁获取中读取。与 `idle_time` 一样，查询本身不算作访问。
+    pub(crate) fn object_info(&self, key: &str) -> Option<ObjectInfo> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+        state.remove_expired(key, now);
+
+        state.entries.get(key).map(|entry| ObjectInfo {
+            encoding: entry.value.encoding(),
+            serialized_length: entry.value.dump().len(),
+            idle: entry.idle_time(now),
+            len: match &entry.value {
+                Value::String(_) => None,
+                Value::SortedSet(set) => Some(set.len()),
+            },
+        })
+    }
+
+    /// 估算键及其值使用的内存字节数，供 `MEMORY USAGE` 使用。如果键不存在，则返回 `None`。
+    ///
+    /// 估算方式与 `approx_memory_usage` 相同，并计入键在哈希表和 `keys` 中占用的槽位。`samples` 为 `Some(n)` 且 `n` 大于零时，有序集合只统计前 `n` 个成员，再按成员数推算总量；否则统计所有成员。与 `idle_time` 一样，查询本身不算作访问。
+    pub(crate) fn memory_usage(&self, key: &str, samples: Option<usize>) -> Option<usize> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.remove_expired(key, Instant::now());
+
+        state.entries.get(key).map(|entry| {
+            let slot = mem::size_of::<(String, Entry)>() + 1 + mem::size_of::<String>();
+            let value = match samples {
+                Some(samples) if samples > 0 => entry.value.sampled_memory_usage(samples),
+                _ => entry.value.approx_memory_usage(),
+            };
+
+            slot + entry.overhead(key) + value
+        })
+    }
+
+    /// 统计 `keys` 中存在的键的数量，不返回它们的值。
+    ///
+    /// 所有键都在同一次锁获取中检查。重复的键会被重复计数，这与 Redis 一致。逻辑上已过期的键被视为不存在，并被删除。
+    pub(crate) fn touch(&self, keys: &[String]) -> usize {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        keys.iter()
+            .filter(|key| state.access(key, now).is_some())
+            .count()
+    }
+
+    /// 返回数据库的计数器快照，供 `INFO` 和监控使用。
+    ///
+    /// 所有计数器在同一次持有锁期间读取，因此它们彼此一致。除订阅者数量外，每个计数器都可以直接从集合的长度获得，代价很小。
+    pub(crate) fn stats(&self) -> DbStats {
         let state = self.shared.state.lock().unwrap();
-        state.entries.get(key).map(|entry| entry.data.clone())
+
+        // 发布/订阅通道在最后一个订阅者离开后不会被删除，因此只统计仍有订阅者的通道。
+        let (channels, subscribers) = state
+            .pub_sub
+            .values()
+            .map(|tx| tx.receiver_count())
+            .filter(|&count| count > 0)
+            .fold((0, 0), |(channels, subscribers), count| {
+                (channels + 1, subscribers + count)
+            });
+
+        DbStats {
+            keys: state.entries.len(),
+            expires: state.expirations.len(),
+            channels,
+            subscribers,
+            evicted_keys: state.evicted_keys,
+        }
+    }
+
+    /// 估算键值数据使用的内存字节数，供 `INFO memory` 和内存限制使用。
+    ///
+    /// 结果是粗略的估计，但在相同的数据上是稳定的：它包括 `HashMap` 和 `keys` 按容量分配的槽位、每个键和值的字节数、集合中每个元素的固定开销，以及 `expirations` 中的条目。分配器的开销和 `Bytes` 的共享不被考虑。每个条目的估算值在修改时累计，因此这是常数时间的操作。
+    pub(crate) fn approx_memory_usage(&self) -> usize {
+        self.shared.state.lock().unwrap().approx_memory_usage()
+    }
+
+    /// 如果估算的内存使用量超过 `maxmemory`，则按照 `maxmemory_policy` 删除键，直到不再超过限制。
+    ///
+    /// 返回内存使用量最终是否在限制之内。没有设置限制时总是返回 `true`；策略为 `NoEviction` 时不删除任何键。服务器在执行可能增加内存使用量的命令之前调用此函数，返回 `false` 时拒绝该命令。
+    ///
+    /// 与 Redis 一样，每删除一个键只比较随机抽样的 `EVICTION_SAMPLES` 个键，参见 `State::eviction_candidate`。因此无论是否超过限制，代价都与键的数量无关。
+    pub(crate) fn free_memory(&self) -> bool {
+        let maxmemory = self.shared.maxmemory.load(Ordering::Relaxed);
+        if maxmemory == 0 {
+            return true;
+        }
+
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        while state.approx_memory_usage() > maxmemory {
+            let key = match state.eviction_candidate(now) {
+                Some(key) => key,
+                None => break,
+            };
+
+            debug!(key = &key[..], "evicting key");
+            state.remove(&key);
+            state.evicted_keys += 1;
+        }
+
+        state.approx_memory_usage() <= maxmemory
+    }
+
+    /// 修改内存使用量的上限，`0` 表示没有限制。新的上限在下一次调用 `free_memory` 时生效。
+    pub(crate) fn set_maxmemory(&self, maxmemory: usize) {
+        self.shared.maxmemory.store(maxmemory, Ordering::Relaxed);
+    }
+
+    /// 修改内存使用量超过上限时选择要删除的键的策略。
+    pub(crate) fn set_maxmemory_policy(&self, policy: EvictionPolicy) {
+        self.shared.state.lock().unwrap().maxmemory_policy = policy;
+    }
+
+    /// 修改之后创建的发布/订阅通道的容量。已经存在的通道保持原来的容量。
+    ///
+    /// # Panics
+    ///
+    /// 如果 `pubsub_capacity` 为零或大于 `MAX_PUBSUB_CAPACITY`，则会 panic。
+    pub(crate) fn set_pubsub_capacity(&self, pubsub_capacity: usize) {
+        assert!(
+            pubsub_capacity > 0 && pubsub_capacity <= MAX_PUBSUB_CAPACITY,
+            "pub/sub capacity must be between 1 and {}",
+            MAX_PUBSUB_CAPACITY
+        );
+        self.shared
+            .pubsub_capacity
+            .store(pubsub_capacity, Ordering::Relaxed);
+    }
+
+    /// 将键所存储字符串中 `offset` 处的位设置为 `on`，并返回该位原来的值。
+    ///
+    /// 位按大端顺序编号：偏移量 0 是第一个字节的最高位。如果字符串不够长，则先用零字节扩展。如果键不存在，则创建一个新字符串。调用者负责限制 `offset`，以避免分配过大的字符串。
+    pub(crate) fn setbit(&self, key: &str, offset: usize, on: bool) -> Result<bool, WrongType> {
+        let byte = offset / 8;
+        let mask = 0x80 >> (offset % 8);
+
+        self.modify_in_place(key, |data| {
+            if data.len() <= byte {
+                data.resize(byte + 1, 0);
+            }
+
+            let old = data[byte] & mask != 0;
+
+            if on {
+                data[byte] |= mask;
+            } else {
+                data[byte] &= !mask;
+            }
+
+            old
+        })
+    }
+
+    /// 从 `offset` 开始用 `value` 覆盖键所存储的字符串，并返回字符串的新长度。
+    ///
+    /// 如果 `offset` 超出字符串末尾，则先用零字节填充。如果键不存在且 `value` 为空，则不会创建键。调用者负责限制 `offset + value.len()`。
+    pub(crate) fn setrange(
+        &self,
+        key: &str,
+        offset: usize,
+        value: &[u8],
+    ) -> Result<usize, WrongType> {
+        self.modify_in_place(key, |data| {
+            // 写入空值不会修改字符串，即使 `offset` 超出了末尾。
+            if value.is_empty() {
+                return data.len();
+            }
+
+            let end = offset + value.len();
+            if data.len() < end {
+                data.resize(end, 0);
+            }
+
+            data[offset..end].copy_from_slice(value);
+            data.len()
+        })
+    }
+
+    /// 对 `sources` 中的键所存储的字符串执行位运算 `op`，并将结果存储到 `dest`，供 `BITOP` 使用。返回结果的字节数。
+    ///
+    /// 读取所有源键和写入目标键在同一次锁获取中完成。`op` 按 `sources` 的顺序接收每个源键的值，不存在的键为空字符串。结果与 `SET` 一样替换 `dest` 原有的值和到期时间，无论其类型如何；结果为空时删除 `dest`。如果任何源键存储的不是字符串，则返回 `WrongType`，并且不会调用 `op`。
+    pub(crate) fn bitop(
+        &self,
+        dest: &str,
+        sources: &[String],
+        op: impl FnOnce(&[Bytes]) -> Vec<u8>,
+    ) -> Result<usize, WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+
+        // 克隆 `Bytes` 是浅层的，不会复制源字符串。
+        let mut values = Vec::with_capacity(sources.len());
+        for key in sources {
+            let value = match state.access(key, now) {
+                Some(entry) => entry.value.expect_string()?.clone(),
+                None => Bytes::new(),
+            };
+            values.push(value);
+        }
+
+        let result = op(&values);
+        let len = result.len();
+
+        state.remove(dest);
+        if !result.is_empty() {
+            state.insert(
+                dest.to_string(),
+                Entry::new(Value::String(Bytes::from(result)), None, now),
+            );
+        }
+
+        Ok(len)
+    }
+
+    /// 将键所存储的字符串解释为浮点数并加上 `delta`，返回新值的文本形式。
+    ///
+    /// 如果键不存在，则将其视为 `0`。如果现有值不是有效的浮点数，或者结果不是有限的数，则返回错误并且值保持不变。读取和写入在同一次持有锁期间完成，因此并发的增量不会丢失。
+    pub(crate) fn incr_by_float(&self, key: &str, delta: f64) -> Result<Bytes, IncrError> {
+        self.modify_in_place(key, |data| {
+            let current = if data.is_empty() {
+                0.0
+            } else {
+                crate::parse::parse_f64(data).map_err(|_| IncrError::NotAFloat)?
+            };
+
+            let value = current + delta;
+            if !value.is_finite() {
+                return Err(IncrError::NotFinite);
+            }
+
+            // `to_string` 生成能够往返的最短表示，并且从不使用科学计数法。加上 `0.0` 可以避免输出 `-0`。
+            let value = Bytes::from((value + 0.0).to_string());
+            data.clear();
+            data.extend_from_slice(&value);
+            Ok(value)
+        })?
+    }
+
+    /// 将 `members` 中的每个 `(分数, 成员)` 添加到键所存储的有序集合中，并返回新添加的成员数量。
+    ///
+    /// 已存在的成员会更新其分数。如果键不存在，则创建一个新的有序集合。
+    pub(crate) fn zadd(&self, key: &str, members: Vec<(f64, Bytes)>) -> Result<usize, WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+        let now = Instant::now();
+        state.remove_expired(key, now);
+
+        if !state.entries.contains_key(key) {
+            state.insert(
+                key.to_string(),
+                Entry::new(Value::SortedSet(SortedSet::new()), None, now),
+            );
+        }
+
+        let zset = state
+            .access(key, now)
+            .expect("the key was just inserted")
+            .value
+            .expect_sorted_set_mut()?;
+
+        let before = zset.memory_usage();
+        let added = members
+            .into_iter()
+            .filter(|(score, member)| zset.insert(member.clone(), *score))
+            .count();
+        let after = zset.memory_usage();
+
+        state.resize(before, after);
+
+        Ok(added)
+    }
+
+    /// 返回键所存储的有序集合中成员的分数。如果键或成员不存在，则返回 `None`。
+    pub(crate) fn zscore(&self, key: &str, member: &[u8]) -> Result<Option<f64>, WrongType> {
+        Ok(self
+            .with_sorted_set(key, |zset| zset.score(member))?
+            .flatten())
+    }
+
+    /// 返回键所存储的有序集合中成员按分数升序排列的排名（从 0 开始）。如果键或成员不存在，则返回 `None`。
+    pub(crate) fn zrank(&self, key: &str, member: &[u8]) -> Result<Option<usize>, WrongType> {
+        Ok(self
+            .with_sorted_set(key, |zset| zset.rank(member))?
+            .flatten())
+    }
+
+    /// 返回键所存储的有序集合中排名在 `start` 和 `stop`（包含）之间的 `(成员, 分数)`，按分数升序排列。
+    ///
+    /// 负索引从末尾开始计数，`-1` 是最后一个成员。超出范围的索引会被截断，规则与 Redis 的 `ZRANGE` 相同。如果键不存在，则返回空列表。
+    pub(crate) fn zrange(
+        &self,
+        key: &str,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<(Bytes, f64)>, WrongType> {
+        let range = self.with_sorted_set(key, |zset| {
+            let len = zset.len() as i64;
+
+            let start = if start < 0 {
+                (start + len).max(0)
+            } else {
+                start
+            };
+            let stop = if stop < 0 {
+                stop + len
+            } else {
+                stop.min(len - 1)
+            };
+
+            if start > stop || start >= len {
+                return vec![];
+            }
+
+            zset.iter()
+                .skip(start as usize)
+                .take((stop - start + 1) as usize)
+                .map(|(member, score)| (member.clone(), score))
+                .collect()
+        })?;
+
+        Ok(range.unwrap_or_default())
+    }
+
+    /// 增量迭代键所存储的有序集合，由 `ZSCAN` 使用。
+    ///
+    /// `cursor` 是按分数排序的下一个成员的位置，从 `0` 开始。每次调用最多检查 `count` 个成员，并返回其中与 glob 模式 `pattern`（如果给出）匹配的 `(成员, 分数)`，以及下一次调用使用的游标。迭代结束时返回的游标为 `0`。如果键不存在，则返回 `(0, [])`。
+    ///
+    /// 游标只是一个偏移量：在两次调用之间添加或删除的成员可能导致其他成员被跳过或重复返回。
+    pub(crate) fn zscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        pattern: Option<&[u8]>,
+        count: usize,
+    ) -> Result<(u64, Vec<(Bytes, f64)>), WrongType> {
+        let scan = self.with_sorted_set(key, |zset| {
+            let start = cursor.min(zset.len() as u64) as usize;
+            let end = start.saturating_add(count).min(zset.len());
+
+            let members = zset
+                .iter()
+                .skip(start)
+                .take(end - start)
+                // 不使用 `Option::is_none_or`，它需要 Rust 1.82。
+                .filter(|(member, _)| {
+                    pattern
+                        .map(|pattern| glob::matches(pattern, member))
+                        .unwrap_or(true)
+                })
+                .map(|(member, score)| (member.clone(), score))
+                .collect();
+
+            let next = if end == zset.len() { 0 } else { end as u64 };
+
+            (next, members)
+        })?;
+
+        Ok(scan.unwrap_or_default())
+    }
+
+    /// 从键所存储的有序集合中随机返回 `(成员, 分数)`，规则与 Redis 的 `ZRANDMEMBER` 相同。
+    ///
+    /// `count` 为正数时返回最多 `count` 个不同的成员；为负数时返回恰好 `-count` 个成员，同一成员可能出现多次。成员的顺序是随机的。如果键不存在，则返回空列表。调用者负责限制 `-count`，以避免分配过大的结果。
+    pub(crate) fn zrandmember(
+        &self,
+        key: &str,
+        count: i64,
+    ) -> Result<Vec<(Bytes, f64)>, WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        // 先只取集合的大小，结束对条目的借用，然后才能使用 `rng`。
+        let len = match state.access(key, Instant::now()) {
+            Some(entry) => entry.value.expect_sorted_set()?.len(),
+            None => return Ok(vec![]),
+        };
+
+        if len == 0 {
+            return Ok(vec![]);
+        }
+
+        let rng = &mut state.rng;
+
+        let indices: Vec<usize> = if count >= 0 {
+            rand::seq::index::sample(rng, len, (count as usize).min(len)).into_vec()
+        } else {
+            (0..count.unsigned_abs())
+                .map(|_| rng.gen_range(0..len))
+                .collect()
+        };
+
+        // 按下标顺序遍历一次集合，只复制被选中的成员，再按抽样的顺序放回结果中。
+        let mut order: Vec<(usize, usize)> = indices
+            .iter()
+            .enumerate()
+            .map(|(position, &index)| (index, position))
+            .collect();
+        order.sort_unstable();
+
+        let zset = state.entries[key].value.expect_sorted_set()?;
+        let mut members = zset.iter();
+        let mut next = 0;
+        let mut last = (Bytes::new(), 0.0);
+        let mut picked = vec![(Bytes::new(), 0.0); indices.len()];
+
+        for (index, position) in order {
+            if index >= next {
+                let (member, score) = members.nth(index - next).expect("index within set");
+                last = (member.clone(), score);
+                next = index + 1;
+            }
+
+            picked[position] = last.clone();
+        }
+
+        Ok(picked)
+    }
+
+    /// 返回键所存储的有序集合中分数在 `min` 和 `max` 之间的 `(成员, 分数)`，按分数升序排列。
+    ///
+    /// 如果给出了 `limit`，则跳过前 `offset` 个匹配的成员，并最多返回 `count` 个成员。如果键不存在，则返回空列表。
+    pub(crate) fn zrangebyscore(
+        &self,
+        key: &str,
+        min: Bound<f64>,
+        max: Bound<f64>,
+        limit: Option<(usize, usize)>,
+    ) -> Result<Vec<(Bytes, f64)>, WrongType> {
+        let range = self.with_sorted_set(key, |zset| {
+            let (offset, count) = limit.unwrap_or((0, usize::MAX));
+
+            zset.range_by_score(min, max)
+                .skip(offset)
+                .take(count)
+                .map(|(member, score)| (member.clone(), score))
+                .collect()
+        })?;
+
+        Ok(range.unwrap_or_default())
+    }
+
+    /// 从键所存储的有序集合中删除 `members`，并返回实际删除的成员数量。
+    ///
+    /// 如果有序集合因此变为空，则删除该键，这与 Redis 一致。
+    pub(crate) fn zrem(&self, key: &str, members: &[Bytes]) -> Result<usize, WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        let (removed, is_empty) = match state.access(key, Instant::now()) {
+            Some(entry) => {
+                let zset = entry.value.expect_sorted_set_mut()?;
+                let before = zset.memory_usage();
+                let removed = members.iter().filter(|member| zset.remove(member)).count();
+                let after = zset.memory_usage();
+                let is_empty = zset.len() == 0;
+
+                state.resize(before, after);
+                (removed, is_empty)
+            }
+            None => return Ok(0),
+        };
+
+        if is_empty {
+            state.remove(key);
+        }
+
+        Ok(removed)
+    }
+
+    /// 创建字符串键 `prefix:<n>`，`n` 取自 `keys`，由 `DEBUG POPULATE` 使用。
+    ///
+    /// 每个值为 `value:<n>`；如果指定了 `size`，则用零字节填充或截断到 `size` 个字节。已存在的键保持不变。
+    ///
+    /// 所有键在同一次持有锁期间插入。调用方负责把大量的键分成多批，并在每批之间让出运行时，这样大量插入不会长时间阻塞其他连接。
+    pub(crate) fn populate(&self, prefix: &str, keys: Range<u64>, size: Option<usize>) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        for n in keys {
+            let key = format!("{}:{}", prefix, n);
+
+            if state.entries.contains_key(&key) {
+                continue;
+            }
+
+            let mut value = format!("value:{}", n).into_bytes();
+            if let Some(size) = size {
+                value.resize(size, 0);
+            }
+
+            state.insert(
+                key,
+                Entry::new(Value::String(Bytes::from(value)), None, Instant::now()),
+            );
+        }
     }
 
     /// 设置与键关联的值以及可选的过期持续时间。
     ///
-    /// 如果键已经关联了一个值，它将被删除。
+    /// 如果键已经关联了一个值，它将被删除。旧值的过期时间不会被保留：`expire` 为 `None` 时，键永不过期。需要保留过期时间的修改应使用 `modify_in_place`。
     pub(crate) fn set(&self, key: String, value: Bytes, expire: Option<Duration>) {
         let mut state = self.shared.state.lock().unwrap();
 
@@ -140,28 +1102,12 @@ impl Db {
             when
         });
 
-        // 将条目插入 `HashMap`。
-        let prev = state.entries.insert(
-            key.clone(),
-            Entry {
-                data: value,
-                expires_at,
-            },
+        // 将条目插入 `HashMap`。先前关联的值及其到期时间会被一起删除，到期时间记录到 `expirations` 中。
+        state.insert(
+            key,
+            Entry::new(Value::String(value), expires_at, Instant::now()),
         );
 
-        // 如果先前关联了键的值**并且**它有一个到期时间。必须还删除 `expirations` 映射中的关联条目。这样可以避免数据泄漏。
-        if let Some(prev) = prev {
-            if let Some(when) = prev.expires_at {
-                // 清除到期时间
-                state.expirations.remove(&(when, key.clone()));
-            }
-        }
-
-        // 跟踪到期时间。如果在移除之前插入，则当当前 `(when, key)` 等于先前的 `(when, key)` 时将导致错误。移除然后插入可以避免这种情况。
-        if let Some(when) = expires_at {
-            state.expirations.insert((when, key));
-        }
-
         // 通知后台任务之前释放互斥锁。这有助于减少争用，避免后台任务醒来只是因为此函数仍然保持着互斥锁而无法获取它。
         drop(state);
 
@@ -186,10 +1132,11 @@ impl Db {
             Entry::Vacant(e) => {
                 // 尚不存在广播通道，因此创建一个。
                 //
-                // 该通道的容量为 `1024` 条消息。消息存储在通道中，直到**所有**订阅者都看到它。这意味着慢的订阅者可能导致消息无限期地保持。
+                // 该通道的容量为 `pubsub_capacity` 条消息，默认为 `1024`。消息存储在通道中，直到**所有**订阅者都看到它。这意味着慢的订阅者可能导致消息无限期地保持。
                 //
                 // 当通道的容量填满时，发布将导致旧消息被丢弃。这可防止慢速消费者阻塞整个系统。
-                let (tx, rx) = broadcast::channel(1024);
+                let (tx, rx) =
+                    broadcast::channel(self.shared.pubsub_capacity.load(Ordering::Relaxed));
                 e.insert(tx);
                 rx
             }
@@ -209,6 +1156,64 @@ impl Db {
             .unwrap_or(0)
     }
 
+    /// 在持有锁的情况下，对键所存储字符串的可变副本调用 `f`，然后将结果写回。
+    ///
+    /// 修改字符串一部分的命令（`SETRANGE`、`SETBIT`、`INCRBYFLOAT`）都通过此函数写入，它们保留现有的过期时间，这与 Redis 一致；整体替换值的 `SET` 则使用 `set`，会替换过期时间。命令不应自行构造 `Entry`，以免意外地清除或保留过期时间。
+    ///
+    /// 如果键不存在或逻辑上已过期，`f` 会收到一个空缓冲区；此时只有当 `f` 写入了数据时才会创建键。如果键存储的不是字符串，则返回 `WrongType`，并且不会调用 `f`。
+    ///
+    /// `Bytes` 是不可变的，因此值会被复制到 `BytesMut` 中进行修改。
+    fn modify_in_place<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut BytesMut) -> T,
+    ) -> Result<T, WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        match state.access(key, Instant::now()) {
+            Some(entry) => {
+                let value = entry.value.expect_string_mut()?;
+                let before = value.len();
+                let mut data = BytesMut::from(&value[..]);
+                let ret = f(&mut data);
+                *value = data.freeze();
+                let after = value.len();
+
+                state.resize(before, after);
+                Ok(ret)
+            }
+            None => {
+                let mut data = BytesMut::new();
+                let ret = f(&mut data);
+
+                if !data.is_empty() {
+                    state.insert(
+                        key.to_string(),
+                        Entry::new(Value::String(data.freeze()), None, Instant::now()),
+                    );
+                }
+
+                Ok(ret)
+            }
+        }
+    }
+
+    /// 在持有锁的情况下，对键所存储的有序集合调用 `f`。
+    ///
+    /// 如果键不存在或逻辑上已过期，则返回 `Ok(None)`，已过期的键会被删除。如果键存储的不是有序集合，则返回 `WrongType`。
+    fn with_sorted_set<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&SortedSet) -> T,
+    ) -> Result<Option<T>, WrongType> {
+        let mut state = self.shared.state.lock().unwrap();
+
+        match state.access(key, Instant::now()) {
+            Some(entry) => Ok(Some(f(entry.value.expect_sorted_set()?))),
+            None => Ok(None),
+        }
+    }
+
     /// 通知清理后台任务关闭。由 `DbShutdown` 的 `Drop` 实现调用。
     fn shutdown_purge_task(&self) {
         // 必须通知后台任务关闭。这是通过将 `State::shutdown` 设置为 `true` 并发出信号来完成的。
@@ -222,13 +1227,15 @@ impl Db {
 }
 
 impl Shared {
-    /// 清除所有过期的键并返回**下一个**键将到期的 `Instant`。后台任务将休眠直到此时刻。
-    fn purge_expired_keys(&self) -> Option<Instant> {
+    /// 清除过期的键，最多 `expire_batch_size` 个。
+    ///
+    /// 返回**下一个**键将到期的 `Instant`，以及是否还有已过期但尚未删除的键。如果还有，后台任务应该让出执行权后立即再次调用此函数，否则将休眠直到返回的时刻。这样大量键同时过期时，锁不会被长时间持有，其他连接可以在批次之间获取锁。
+    fn purge_expired_keys(&self) -> (Option<Instant>, bool) {
         let mut state = self.state.lock().unwrap();
 
         if state.shutdown {
             // 数据库正在关闭。所有对共享状态的句柄都已经丢失。后台任务应该退出。
-            return None;
+            return (None, false);
         }
 
         // 为了使借用检查器满意，这是必需的。简而言之，`lock()` 返回一个 `MutexGuard` 而不是 `&mut State`。借用检查器无法“看透”互斥锁保护并确定可以安全地可变地访问 `state.expirations` 和 `state.entries`，因此我们在循环外部得到了“真正的”可变引用到 `State`。
@@ -237,18 +1244,26 @@ impl Shared {
         // 查找所有在现在之前计划过期的键。
         let now = Instant::now();
 
+        let mut purged = 0;
+
         while let Some(&(when, ref key)) = state.expirations.iter().next() {
             if when > now {
                 // 清理完成，`when` 是**下一个**键到期的瞬间。工作任务将等到此瞬间。
-                return Some(when);
+                return (Some(when), false);
+            }
+
+            if purged == self.expire_batch_size {
+                // 本批次已满，但还有过期的键。
+                return (Some(when), true);
             }
 
-            // 键过期，删除它
-            state.entries.remove(key);
-            state.expirations.remove(&(when, key.clone()));
+            // 键过期，删除它及其到期时间
+            let key = key.clone();
+            state.remove(&key);
+            purged += 1;
         }
 
-        None
+        (None, false)
     }
 
     /// 如果数据库正在关闭，则返回 `true`
@@ -259,7 +1274,382 @@ impl Shared {
     }
 }
 
+impl From<WrongType> for IncrError {
+    fn from(_: WrongType) -> IncrError {
+        IncrError::WrongType
+    }
+}
+
+impl Value {
+    /// 返回值的内部编码名称。
+    ///
+    /// 可以表示为 64 位有符号整数（规范形式，例如没有前导零或 `+` 号）的字符串报告为 `int`，其他字符串报告为 `raw`，有序集合报告为 `skiplist`。
+    fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(data) => {
+                let is_int = std::str::from_utf8(data)
+                    .ok()
+                    .and_then(|s| s.parse::<i64>().ok())
+                    .map(|n| n.to_string().as_bytes() == &data[..])
+                    .unwrap_or(false);
+
+                if is_int {
+                    "int"
+                } else {
+                    "raw"
+                }
+            }
+            Value::SortedSet(_) => "skiplist",
+        }
+    }
+
+    /// 将值序列化为 `DUMP` 载荷。
+    ///
+    /// 载荷以类型标记开头，以 `DUMP_VERSION` 结尾。字符串存储为长度前缀的字节；有序集合存储为成员数，然后按顺序存储每个长度前缀的成员及其分数。长度和成员数都是 64 位整数，因此任何长度都不会被截断。所有整数和分数都使用大端序。
+    fn dump(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+
+        match self {
+            Value::String(data) => {
+                buf.put_u8(DUMP_STRING);
+                buf.put_u64(data.len() as u64);
+                buf.put_slice(data);
+            }
+            Value::SortedSet(zset) => {
+                buf.put_u8(DUMP_SORTED_SET);
+                buf.put_u64(zset.len() as u64);
+
+                for (member, score) in zset.iter() {
+                    buf.put_u64(member.len() as u64);
+                    buf.put_slice(member);
+                    buf.put_f64(score);
+                }
+            }
+        }
+
+        buf.put_u8(DUMP_VERSION);
+        buf.freeze()
+    }
+
+    /// 从 `dump` 生成的载荷重新创建值。
+    ///
+    /// 如果版本不匹配、载荷被截断或包含多余的字节，或者内容无效（例如有序集合为空、成员重复或分数为 NaN），则返回 `None`。
+    fn restore(payload: &[u8]) -> Option<Value> {
+        let (&version, mut buf) = payload.split_last()?;
+        if version != DUMP_VERSION || !buf.has_remaining() {
+            return None;
+        }
+
+        let value = match buf.get_u8() {
+            DUMP_STRING => Value::String(take_dump_bytes(&mut buf)?),
+            DUMP_SORTED_SET => {
+                if buf.remaining() < 8 {
+                    return None;
+                }
+
+                let len = buf.get_u64();
+                if len == 0 {
+                    return None;
+                }
+
+                let mut zset = SortedSet::new();
+                for _ in 0..len {
+                    let member = take_dump_bytes(&mut buf)?;
+
+                    if buf.remaining() < 8 {
+                        return None;
+                    }
+
+                    let score = buf.get_f64();
+                    if score.is_nan() || !zset.insert(member, score) {
+                        return None;
+                    }
+                }
+
+                Value::SortedSet(zset)
+            }
+            _ => return None,
+        };
+
+        if buf.has_remaining() {
+            return None;
+        }
+
+        Some(value)
+    }
+
+    /// 如果值是字符串，则返回它，否则返回 `WrongType`。
+    ///
+    /// 所有字符串命令都通过此函数或 `expect_string_mut` 访问值，因此对其他类型的键执行字符串命令时，行为和错误都是一致的。
+    fn expect_string(&self) -> Result<&Bytes, WrongType> {
+        match self {
+            Value::String(data) => Ok(data),
+            _ => Err(WrongType),
+        }
+    }
+
+    /// 与 `expect_string` 相同，但返回可变引用。
+    fn expect_string_mut(&mut self) -> Result<&mut Bytes, WrongType> {
+        match self {
+            Value::String(data) => Ok(data),
+            _ => Err(WrongType),
+        }
+    }
+
+    /// 如果值是有序集合，则返回它，否则返回 `WrongType`。与 `expect_string` 对应，供有序集合命令使用。
+    fn expect_sorted_set(&self) -> Result<&SortedSet, WrongType> {
+        match self {
+            Value::SortedSet(zset) => Ok(zset),
+            _ => Err(WrongType),
+        }
+    }
+
+    /// 与 `expect_sorted_set` 相同，但返回可变引用。
+    fn expect_sorted_set_mut(&mut self) -> Result<&mut SortedSet, WrongType> {
+        match self {
+            Value::SortedSet(zset) => Ok(zset),
+            _ => Err(WrongType),
+        }
+    }
+
+    /// 估算值在 `Entry` 之外使用的内存字节数，参见 `Db::approx_memory_usage`。
+    fn approx_memory_usage(&self) -> usize {
+        match self {
+            Value::String(data) => data.len(),
+            Value::SortedSet(zset) => zset.memory_usage(),
+        }
+    }
+
+    /// 与 `approx_memory_usage` 相同，但有序集合只统计前 `samples` 个成员，再按成员数推算总量。
+    fn sampled_memory_usage(&self, samples: usize) -> usize {
+        match self {
+            Value::SortedSet(zset) if zset.len() > samples => {
+                let sampled: usize = zset
+                    .iter()
+                    .take(samples)
+                    .map(|(member, _)| member_memory_usage(member))
+                    .sum();
+
+                sampled * zset.len() / samples
+            }
+            _ => self.approx_memory_usage(),
+        }
+    }
+}
+
+/// 从 `DUMP` 载荷中读取长度前缀的字节。如果载荷被截断，则返回 `None`。
+fn take_dump_bytes(buf: &mut &[u8]) -> Option<Bytes> {
+    if buf.remaining() < 8 {
+        return None;
+    }
+
+    let len = usize::try_from(buf.get_u64()).ok()?;
+    if buf.remaining() < len {
+        return None;
+    }
+
+    let data = Bytes::copy_from_slice(&buf[..len]);
+    buf.advance(len);
+
+    Some(data)
+}
+
+impl Entry {
+    /// 如果条目在 `now` 时刻已经过期，则返回 `true`。
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.map(|when| when <= now).unwrap_or(false)
+    }
+
+    /// 创建一个在 `now` 时刻被访问过一次的新条目。
+    fn new(value: Value, expires_at: Option<Instant>, now: Instant) -> Entry {
+        Entry {
+            value,
+            expires_at,
+            last_access: now,
+            slot: 0,
+            frequency: LFU_INIT_VAL,
+        }
+    }
+
+    /// 返回从上次访问到 `now` 经过的时间。
+    fn idle_time(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_access)
+    }
+
+    /// 返回在 `now` 时刻衰减之后的访问频率计数器，不修改条目。
+    fn frequency(&self, now: Instant) -> u8 {
+        let periods = self.idle_time(now).as_secs() / LFU_DECAY_PERIOD.as_secs();
+        self.frequency
+            .saturating_sub(periods.min(u8::MAX as u64) as u8)
+    }
+
+    /// 记录在 `now` 时刻的一次访问：先按空闲时间衰减访问频率，然后以对数概率递增它。
+    fn record_access(&mut self, now: Instant, rng: &mut SmallRng) {
+        let mut frequency = self.frequency(now);
+
+        if frequency < u8::MAX {
+            let base = frequency.saturating_sub(LFU_INIT_VAL) as f64;
+            if rng.gen::<f64>() < 1.0 / (base * LFU_LOG_FACTOR + 1.0) {
+                frequency += 1;
+            }
+        }
+
+        self.frequency = frequency;
+        self.last_access = now;
+    }
+
+    /// 估算键 `key` 及其条目使用的内存字节数，包括 `expirations` 中的副本，参见 `Db::approx_memory_usage`。
+    fn approx_memory_usage(&self, key: &str) -> usize {
+        self.overhead(key) + self.value.approx_memory_usage()
+    }
+
+    /// 估算键 `key` 本身及其到期时间使用的内存字节数，不包括值。键名在 `entries` 和 `keys` 中各存储一份。
+    fn overhead(&self, key: &str) -> usize {
+        let expiration = match self.expires_at {
+            Some(_) => mem::size_of::<(Instant, String)>() + key.len(),
+            None => 0,
+        };
+
+        2 * key.len() + expiration
+    }
+}
+
 impl State {
+    /// 估算键值数据使用的内存字节数，参见 `Db::approx_memory_usage`。
+    fn approx_memory_usage(&self) -> usize {
+        // `HashMap` 为每个槽位存储一个 `(String, Entry)` 和一个控制字节，`keys` 为每个槽位存储一个 `String`，无论槽位是否被占用。
+        let table = self.entries.capacity() * (mem::size_of::<(String, Entry)>() + 1)
+            + self.keys.capacity() * mem::size_of::<String>();
+
+        table + self.used_memory
+    }
+
+    /// 插入键 `key` 的条目。先前关联的条目及其到期时间会被删除，新条目的到期时间记录到 `expirations` 中。
+    fn insert(&mut self, key: String, mut entry: Entry) {
+        // 先删除再插入：如果新的 `(when, key)` 与先前的相同，先插入再删除会丢失到期时间。
+        self.remove(&key);
+
+        if let Some(when) = entry.expires_at {
+            self.expirations.insert((when, key.clone()));
+        }
+
+        self.used_memory += entry.approx_memory_usage(&key);
+        entry.slot = self.keys.len();
+        self.keys.push(key.clone());
+        self.entries.insert(key, entry);
+    }
+
+    /// 在已有条目的估算内存使用量从 `before` 变为 `after` 之后更新 `used_memory`。
+    ///
+    /// 原地修改条目的操作在修改前后各测量一次条目中变化的部分，然后调用此函数。
+    fn resize(&mut self, before: usize, after: usize) {
+        self.used_memory = self.used_memory - before + after;
+    }
+
+    /// 从随机抽样的 `EVICTION_SAMPLES` 个键中，按照 `maxmemory_policy` 选出最应该被删除的键。策略为 `NoEviction` 或没有键时返回 `None`。
+    ///
+    /// LRU 选择最久未被访问的键，LFU 先比较 `now` 时刻的访问频率。与 Redis 一样只比较样本，因此被选中的键不一定是全局最优的，但代价与键的数量无关。
+    fn eviction_candidate(&mut self, now: Instant) -> Option<String> {
+        let policy = self.maxmemory_policy;
+        if policy == EvictionPolicy::NoEviction {
+            return None;
+        }
+
+        let samples = EVICTION_SAMPLES.min(self.keys.len());
+
+        rand::seq::index::sample(&mut self.rng, self.keys.len(), samples)
+            .into_iter()
+            .map(|slot| &self.keys[slot])
+            .min_by_key(|key| {
+                let entry = &self.entries[*key];
+
+                match policy {
+                    EvictionPolicy::AllKeysLfu => (entry.frequency(now), entry.last_access),
+                    _ => (0, entry.last_access),
+                }
+            })
+            .cloned()
+    }
+
+    /// 如果键在 `now` 时刻逻辑上已过期，则将其连同到期时间一起删除。
+    ///
+    /// 写操作在修改键之前调用此函数，以便新值不会继承旧值或旧的过期时间。读操作在访问键之前也调用此函数，因此已过期但后台任务尚未清理的键被视为不存在并被惰性删除，这与 Redis 一致。
+    fn remove_expired(&mut self, key: &str, now: Instant) {
+        if self
+            .entries
+            .get(key)
+            .map(|entry| entry.is_expired(now))
+            .unwrap_or(false)
+        {
+            self.remove(key);
+        }
+    }
+
+    /// 返回键的条目并将其记录为在 `now` 时刻被访问。已过期的键先被惰性删除，视为不存在。
+    ///
+    /// 读写已有键的操作都通过此函数查找键，因此 `last_access` 反映最后一次使用键的命令。
+    fn access(&mut self, key: &str, now: Instant) -> Option<&mut Entry> {
+        self.remove_expired(key, now);
+
+        let entry = self.entries.get_mut(key)?;
+        entry.record_access(now, &mut self.rng);
+        Some(entry)
+    }
+
+    /// 删除键及其到期时间（如果有）。
+    fn remove(&mut self, key: &str) {
+        let entry = match self.entries.remove(key) {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        if let Some(when) = entry.expires_at {
+            self.expirations.remove(&(when, key.to_string()));
+        }
+
+        self.used_memory -= entry.approx_memory_usage(key);
+
+        // 用最后一个键填补被删除的键在 `keys` 中的位置。
+        self.keys.swap_remove(entry.slot);
+        if let Some(moved) = self.keys.get(entry.slot) {
+            self.entries.get_mut(moved).unwrap().slot = entry.slot;
+        }
+    }
+
+    /// 将已存在的键的到期时间设置为 `expires_at`，并相应地更新 `expirations`。`None` 表示键永不过期。
+    ///
+    /// 如果新的到期时间是**下一个**要驱逐的键，则返回 `true`，此时调用者需要通知后台任务。
+    fn set_expiration(&mut self, key: &str, expires_at: Option<Instant>) -> bool {
+        let notify = match expires_at {
+            Some(when) => self
+                .next_expiration()
+                .map(|expiration| expiration > when)
+                .unwrap_or(true),
+            None => false,
+        };
+
+        let entry = match self.entries.get_mut(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        let before = entry.overhead(key);
+
+        // 先移除旧的到期时间再插入新的，原因与 `insert` 中相同。
+        if let Some(when) = std::mem::replace(&mut entry.expires_at, expires_at) {
+            self.expirations.remove(&(when, key.to_string()));
+        }
+
+        let after = entry.overhead(key);
+        self.used_memory = self.used_memory - before + after;
+
+        if let Some(when) = expires_at {
+            self.expirations.insert((when, key.to_string()));
+        }
+
+        notify
+    }
+
     fn next_expiration(&self) -> Option<Instant> {
         self.expirations
             .iter()
@@ -274,8 +1664,13 @@ impl State {
 async fn purge_expired_tasks(shared: Arc<Shared>) {
     // 如果设置了关闭标志，则任务应退出。
     while !shared.is_shutdown() {
-        // 清理所有过期的键。该函数返回**下一个**键将到期的瞬间。工作者应该等到这个瞬间过去然后再次清理。
-        if let Some(when) = shared.purge_expired_keys() {
+        // 清理一批过期的键。该函数返回**下一个**键将到期的瞬间。工作者应该等到这个瞬间过去然后再次清理。
+        let (next, pending) = shared.purge_expired_keys();
+
+        if pending {
+            // 还有过期的键。让出执行权，以便其他任务有机会获取锁，然后继续清理。
+            task::yield_now().await;
+        } else if let Some(when) = next {
             // 等到下一个键过期**或**后台任务被通知。如果任务被通知，则必须重新加载其状态，因为新的键被设置为提前到期。这是通过循环完成的。
             tokio::select! {
                 _ = time::sleep_until(when) => {}
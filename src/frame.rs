@@ -1,5 +1,5 @@
 //! 提供表示Redis协议帧的类型以及从字节数组解析帧的实用工具。
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::convert::TryInto;
 use std::fmt;
 use std::io::Cursor;
@@ -7,16 +7,38 @@ use std::num::TryFromIntError;
 use std::string::FromUtf8Error;
 
 /// Redis协议中的帧。
-#[derive(Clone, Debug)]
+///
+/// 帧按结构比较：变体和内容都相同时才相等，数组逐个元素递归比较。
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Frame {
     Simple(String),    // 简单字符串
     Error(String),     // 错误
-    Integer(u64),      // 整数
+    Integer(i64),      // 整数
     Bulk(Bytes),       // 字符串块
     Null,              // 空值
     Array(Vec<Frame>), // 数组
 }
 
+/// `Frame::check` 和 `Frame::parse_from` 允许的单个数组的默认最大元素数。
+///
+/// 数组长度由对等方提供，限制它可以防止恶意的对等方以很少的字节迫使服务器进行大量的分配和工作。
+pub const DEFAULT_MAX_ARRAY_LEN: usize = 1024 * 1024;
+
+/// `Frame::check` 和 `Frame::parse_from` 允许的单行（不含`\r\n`）的默认最大字节数。
+///
+/// 简单字符串、错误、整数以及数组和字符串块的长度都以行的形式发送。如果不加限制，不发送`\r\n`的对等方会使读缓冲区无限增长。
+pub const DEFAULT_MAX_LINE_LEN: usize = 64 * 1024;
+
+/// `Frame::check` 和 `Frame::parse_from` 允许的单个字符串块的默认最大字节数，与 Redis 的`proto-max-bulk-len`默认值相同。
+///
+/// 与数组长度一样，字符串块的长度由对等方提供。在等待其内容之前就检查长度，因此过大的字符串块不会被缓冲。
+pub const DEFAULT_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+/// `Frame::check` 和 `Frame::parse_from` 允许的单个顶层帧（即一条命令）的默认最大字节数。
+///
+/// 单独的限制只约束每个元素，由大量小字符串块组成的命令仍可能占用大量内存。总字节数在检查帧时逐步累计，一旦超过限制就立即拒绝，无需等待帧结束。
+pub const DEFAULT_MAX_FRAME_LEN: usize = 1024 * 1024 * 1024;
+
 #[derive(Debug)]
 pub enum Error {
     /// 不足以解析消息的数据
@@ -26,12 +48,53 @@ pub enum Error {
     Other(crate::Error),
 }
 
+/// `Frame::check_with_limits` 检查帧时使用的限制。
+#[derive(Clone, Copy)]
+struct Limits {
+    max_array_len: usize,
+    max_line_len: usize,
+    max_bulk_len: usize,
+    max_frame_len: usize,
+
+    /// 顶层帧在缓冲区中的起始位置。
+    start: u64,
+}
+
+impl Limits {
+    /// 如果帧到光标为止的字节数，再加上尚未读取的 `pending` 字节，超过了 `max_frame_len`，则返回协议错误。
+    fn check_frame_len(&self, src: &Cursor<&[u8]>, pending: usize) -> Result<(), Error> {
+        let len = ((src.position() - self.start) as usize).saturating_add(pending);
+
+        if len > self.max_frame_len {
+            return Err(format!(
+                "protocol error; frame exceeds the limit of {} bytes",
+                self.max_frame_len
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
 impl Frame {
     /// 返回一个空数组
     pub(crate) fn array() -> Frame {
         Frame::Array(vec![])
     }
 
+    /// 返回帧类型的名称，例如 `"bulk"`，用于日志和追踪。
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Frame::Simple(_) => "simple",
+            Frame::Error(_) => "error",
+            Frame::Integer(_) => "integer",
+            Frame::Bulk(_) => "bulk",
+            Frame::Null => "null",
+            Frame::Array(_) => "array",
+        }
+    }
+
     /// 将“bulk”帧推送到数组中。`self`必须是Array帧。
     ///
     /// # Panics
@@ -51,7 +114,7 @@ impl Frame {
     /// # Panics
     ///
     /// 如果`self`不是数组，则会panic
-    pub(crate) fn push_int(&mut self, value: u64) {
+    pub(crate) fn push_int(&mut self, value: i64) {
         match self {
             Frame::Array(vec) => {
                 vec.push(Frame::Integer(value));
@@ -61,18 +124,69 @@ impl Frame {
     }
 
     /// 检查是否可以从`src`解码整个消息
+    ///
+    /// 元素超过`DEFAULT_MAX_ARRAY_LEN`的数组被视为协议错误。
     pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
+        Frame::check_with_limit(src, DEFAULT_MAX_ARRAY_LEN)
+    }
+
+    /// 与`check`相同，但任何数组（包括嵌套数组）的元素超过`max_array_len`时返回协议错误。
+    ///
+    /// 在读取任何元素之前就会检查长度，因此不会为过大的数组做任何工作。
+    pub fn check_with_limit(src: &mut Cursor<&[u8]>, max_array_len: usize) -> Result<(), Error> {
+        Frame::check_with_limits(
+            src,
+            max_array_len,
+            DEFAULT_MAX_LINE_LEN,
+            DEFAULT_MAX_BULK_LEN,
+            DEFAULT_MAX_FRAME_LEN,
+        )
+    }
+
+    /// 与`check_with_limit`相同，但任何一行超过`max_line_len`字节、任何字符串块超过`max_bulk_len`字节，或整个帧超过`max_frame_len`字节时也返回协议错误。
+    ///
+    /// 尚未收到`\r\n`的行一旦超过限制就会被拒绝，无需等待行结束。同样，帧的总字节数在检查每个元素时累计，字符串块声明的长度会在等待其内容之前计入。
+    pub fn check_with_limits(
+        src: &mut Cursor<&[u8]>,
+        max_array_len: usize,
+        max_line_len: usize,
+        max_bulk_len: usize,
+        max_frame_len: usize,
+    ) -> Result<(), Error> {
+        let limits = Limits {
+            max_array_len,
+            max_line_len,
+            max_bulk_len,
+            max_frame_len,
+            start: src.position(),
+        };
+
+        Frame::check_within(src, &limits)
+    }
+
+    /// 检查一个帧，嵌套数组的元素递归检查，`limits.start` 始终是顶层帧的起始位置。
+    fn check_within(src: &mut Cursor<&[u8]>, limits: &Limits) -> Result<(), Error> {
+        let Limits {
+            max_array_len,
+            max_line_len,
+            max_bulk_len,
+            ..
+        } = *limits;
+
         match get_u8(src)? {
             b'+' => {
+                check_line_len(src, max_line_len)?;
                 get_line(src)?;
                 Ok(())
             }
             b'-' => {
+                check_line_len(src, max_line_len)?;
                 get_line(src)?;
                 Ok(())
             }
             b':' => {
-                let _ = get_decimal(src)?;
+                check_line_len(src, max_line_len)?;
+                let _ = get_decimal::<i64>(src)?;
                 Ok(())
             }
             b'$' => {
@@ -81,17 +195,40 @@ impl Frame {
                     skip(src, 4)
                 } else {
                     // 读取字符串块
-                    let len: usize = get_decimal(src)?.try_into()?;
+                    check_line_len(src, max_line_len)?;
+                    let len: usize = get_decimal::<u64>(src)?.try_into()?;
+
+                    if len > max_bulk_len {
+                        return Err(format!(
+                            "protocol error; bulk string of {} bytes exceeds the limit of {}",
+                            len, max_bulk_len
+                        )
+                        .into());
+                    }
 
-                    // 跳过相应数量的字节 + 2 (\r\n)。
-                    skip(src, len + 2)
+                    // 在等待内容之前，把声明的长度计入帧的总字节数。
+                    limits.check_frame_len(src, len.saturating_add(2))?;
+
+                    // 跳过相应数量的字节，然后确认其后是\r\n。
+                    skip(src, len)?;
+                    get_crlf(src)
                 }
             }
             b'*' => {
-                let len = get_decimal(src)?;
+                check_line_len(src, max_line_len)?;
+                let len: usize = get_decimal::<u64>(src)?.try_into()?;
+
+                if len > max_array_len {
+                    return Err(format!(
+                        "protocol error; array of {} elements exceeds the limit of {}",
+                        len, max_array_len
+                    )
+                    .into());
+                }
 
                 for _ in 0..len {
-                    Frame::check(src)?;
+                    Frame::check_within(src, limits)?;
+                    limits.check_frame_len(src, 0)?;
                 }
 
                 Ok(())
@@ -122,8 +259,8 @@ impl Frame {
                 Ok(Frame::Error(string))
             }
             b':' => {
-                let len = get_decimal(src)?;
-                Ok(Frame::Integer(len))
+                let val = get_decimal::<i64>(src)?;
+                Ok(Frame::Integer(val))
             }
             b'$' => {
                 if b'-' == peek_u8(src)? {
@@ -136,24 +273,26 @@ impl Frame {
                     Ok(Frame::Null)
                 } else {
                     // 读取字符串块
-                    let len = get_decimal(src)?.try_into()?;
-                    let n = len + 2;
+                    let len = get_decimal::<u64>(src)?.try_into()?;
 
-                    if src.remaining() < n {
+                    if src.remaining() < len + 2 {
                         return Err(Error::Incomplete);
                     }
 
                     let data = Bytes::copy_from_slice(&src.chunk()[..len]);
 
-                    // 跳过相应数量的字节 + 2 (\r\n)。
-                    skip(src, n)?;
+                    // 跳过相应数量的字节，然后确认其后是\r\n。
+                    skip(src, len)?;
+                    get_crlf(src)?;
 
                     Ok(Frame::Bulk(data))
                 }
             }
             b'*' => {
-                let len = get_decimal(src)?.try_into()?;
-                let mut out = Vec::with_capacity(len);
+                let len: usize = get_decimal::<u64>(src)?.try_into()?;
+
+                // 长度来自对等方，不能信任。每个元素至少占用 3 个字节（例如 `+\r\n`），因此按剩余的字节数限制预分配的容量。
+                let mut out = Vec::with_capacity(len.min(src.remaining() / 3));
 
                 for _ in 0..len {
                     out.push(Frame::parse(src)?);
@@ -165,6 +304,112 @@ impl Frame {
         }
     }
 
+    /// 从 `src` 的开头解析单个帧，返回该帧以及它占用的字节数。
+    ///
+    /// 如果 `src` 尚未包含完整的帧，则返回 `Error::Incomplete`，调用方应在接收到更多数据后重试。帧之后的数据不会被读取，调用方负责将缓冲区前进返回的字节数。
+    ///
+    /// 元素超过`DEFAULT_MAX_ARRAY_LEN`的数组被视为协议错误。
+    pub fn parse_from(src: &[u8]) -> Result<(Frame, usize), Error> {
+        Frame::parse_from_with_limit(src, DEFAULT_MAX_ARRAY_LEN)
+    }
+
+    /// 与`parse_from`相同，但任何数组的元素超过`max_array_len`时返回协议错误。
+    pub fn parse_from_with_limit(
+        src: &[u8],
+        max_array_len: usize,
+    ) -> Result<(Frame, usize), Error> {
+        Frame::parse_from_with_limits(
+            src,
+            max_array_len,
+            DEFAULT_MAX_LINE_LEN,
+            DEFAULT_MAX_BULK_LEN,
+            DEFAULT_MAX_FRAME_LEN,
+        )
+    }
+
+    /// 与`parse_from_with_limit`相同，但任何一行超过`max_line_len`字节、任何字符串块超过`max_bulk_len`字节，或整个帧超过`max_frame_len`字节时也返回协议错误。
+    pub fn parse_from_with_limits(
+        src: &[u8],
+        max_array_len: usize,
+        max_line_len: usize,
+        max_bulk_len: usize,
+        max_frame_len: usize,
+    ) -> Result<(Frame, usize), Error> {
+        let mut buf = Cursor::new(src);
+
+        // 首先检查是否已经有完整的帧。这比完整解析要快得多，并且避免为不完整的帧分配数据结构。`check` 将光标前进到帧的末尾，因此光标位置就是帧的长度。
+        Frame::check_with_limits(
+            &mut buf,
+            max_array_len,
+            max_line_len,
+            max_bulk_len,
+            max_frame_len,
+        )?;
+        let len = buf.position() as usize;
+
+        // 在解析之前将位置重置为零。
+        buf.set_position(0);
+        let frame = Frame::parse(&mut buf)?;
+
+        Ok((frame, len))
+    }
+
+    /// 将帧编码为线路上的字节，与 `Connection::write_frame` 写入的字节完全相同。
+    pub fn encode(&self) -> Bytes {
+        let mut dst = BytesMut::new();
+        self.write_to(&mut dst);
+        dst.freeze()
+    }
+
+    /// 将帧编码并追加到 `dst` 末尾。
+    ///
+    /// 数组通过编码每个条目来进行编码，条目本身可以是数组。
+    pub fn write_to(&self, dst: &mut BytesMut) {
+        match self {
+            Frame::Simple(val) => {
+                dst.put_u8(b'+');
+                dst.put_slice(val.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Error(val) => {
+                dst.put_u8(b'-');
+                dst.put_slice(val.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Integer(val) => {
+                dst.put_u8(b':');
+                put_decimal(dst, *val);
+            }
+            Frame::Null => {
+                dst.put_slice(b"$-1\r\n");
+            }
+            Frame::Bulk(val) => {
+                dst.put_u8(b'$');
+                put_decimal(dst, val.len() as i64);
+                dst.put_slice(val);
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Array(val) => {
+                // 编码帧类型前缀和数组的长度，然后迭代并编码数组中的每个条目。
+                dst.put_u8(b'*');
+                put_decimal(dst, val.len() as i64);
+
+                for entry in val {
+                    entry.write_to(dst);
+                }
+            }
+        }
+    }
+
+    /// 返回帧中错误帧的数量，包括嵌套在数组中的错误帧。
+    pub(crate) fn error_count(&self) -> u64 {
+        match self {
+            Frame::Error(_) => 1,
+            Frame::Array(val) => val.iter().map(Frame::error_count).sum(),
+            _ => 0,
+        }
+    }
+
     /// 将帧转换为“unexpected frame”错误
     pub(crate) fn to_error(&self) -> crate::Error {
         format!("unexpected frame: {}", self).into()
@@ -210,6 +455,12 @@ impl fmt::Display for Frame {
     }
 }
 
+/// 将十进制数及其后的 `\r\n` 追加到 `dst`。
+fn put_decimal(dst: &mut BytesMut, val: i64) {
+    dst.put_slice(val.to_string().as_bytes());
+    dst.put_slice(b"\r\n");
+}
+
 fn peek_u8(src: &mut Cursor<&[u8]>) -> Result<u8, Error> {
     if !src.has_remaining() {
         return Err(Error::Incomplete);
@@ -235,29 +486,72 @@ fn skip(src: &mut Cursor<&[u8]>, n: usize) -> Result<(), Error> {
     Ok(())
 }
 
-/// 读取以新行终止的十进制数
-fn get_decimal(src: &mut Cursor<&[u8]>) -> Result<u64, Error> {
+/// 读取字符串块数据之后的`\r\n`。
+///
+/// 终止符不正确说明帧的长度与数据不符，继续读取会与对等方失去同步，因此作为协议错误处理。
+fn get_crlf(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
+    if src.remaining() < 2 {
+        return Err(Error::Incomplete);
+    }
+
+    if &src.chunk()[..2] != b"\r\n" {
+        return Err("protocol error; bulk string not terminated by CRLF".into());
+    }
+
+    src.advance(2);
+    Ok(())
+}
+
+/// 读取以新行终止的十进制数。整数帧是有符号的，而长度是无符号的。
+fn get_decimal<T: atoi::FromRadix10SignedChecked>(src: &mut Cursor<&[u8]>) -> Result<T, Error> {
     use atoi::atoi;
 
     let line = get_line(src)?;
 
-    atoi::<u64>(line).ok_or_else(|| "protocol error; invalid frame format".into())
+    atoi::<T>(line).ok_or_else(|| "protocol error; invalid frame format".into())
+}
+
+/// 确认从当前位置开始的行不超过`max_line_len`字节，不移动位置。
+///
+/// 只扫描前`max_line_len + 2`个字节，因此对于很长的缓冲区也不会重复扫描全部数据。行尚不完整但还未超过限制时返回`Ok`，由之后的`get_line`报告`Incomplete`。
+fn check_line_len(src: &Cursor<&[u8]>, max_line_len: usize) -> Result<(), Error> {
+    let rest = &src.get_ref()[src.position() as usize..];
+    let window = &rest[..rest.len().min(max_line_len.saturating_add(2))];
+
+    if window.len() < max_line_len.saturating_add(2) || window.windows(2).any(|w| w == b"\r\n") {
+        return Ok(());
+    }
+
+    Err(format!(
+        "protocol error; line exceeds the limit of {} bytes",
+        max_line_len
+    )
+    .into())
 }
 
 /// 查找行
+///
+/// 如果剩余的字节中没有`\r\n`，包括剩余不足两个字节的情况，则返回`Error::Incomplete`，位置保持不变。
 fn get_line<'a>(src: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], Error> {
     // 直接扫描字节
+    let buf = *src.get_ref();
     let start = src.position() as usize;
+
+    // 一行至少包含`\r\n`两个字节。提前检查，以免下面计算扫描范围时下溢。
+    if buf.len() < start.saturating_add(2) {
+        return Err(Error::Incomplete);
+    }
+
     // 扫描到倒数第二个字节
-    let end = src.get_ref().len() - 1;
+    let end = buf.len() - 1;
 
     for i in start..end {
-        if src.get_ref()[i] == b'\r' && src.get_ref()[i + 1] == b'\n' {
+        if buf[i] == b'\r' && buf[i + 1] == b'\n' {
             // 我们找到一行，更新位置到\n之后
             src.set_position((i + 2) as u64);
 
             // 返回行
-            return Ok(&src.get_ref()[start..i]);
+            return Ok(&buf[start..i]);
         }
     }
 
@@ -298,3 +592,120 @@ impl fmt::Display for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Buffers too short to hold a line, including empty ones, are reported
+    /// as incomplete rather than panicking.
+    #[test]
+    fn short_buffers_are_incomplete() {
+        for src in [&b""[..], b"\r", b"+"] {
+            let mut cursor = Cursor::new(src);
+            assert!(matches!(get_line(&mut cursor), Err(Error::Incomplete)));
+            assert_eq!(cursor.position(), 0);
+        }
+
+        // The position may already be at, or one byte before, the end.
+        let mut cursor = Cursor::new(&b"+OK\r\n"[..]);
+        cursor.set_position(5);
+        assert!(matches!(get_line(&mut cursor), Err(Error::Incomplete)));
+        cursor.set_position(6);
+        assert!(matches!(get_line(&mut cursor), Err(Error::Incomplete)));
+
+        for src in [&b""[..], b"+", b":", b"+\r", b"$", b"*"] {
+            let mut cursor = Cursor::new(src);
+            assert!(
+                matches!(Frame::check(&mut cursor), Err(Error::Incomplete)),
+                "{:?}",
+                src
+            );
+
+            let mut cursor = Cursor::new(src);
+            assert!(
+                matches!(Frame::parse(&mut cursor), Err(Error::Incomplete)),
+                "{:?}",
+                src
+            );
+
+            assert!(matches!(Frame::parse_from(src), Err(Error::Incomplete)));
+        }
+    }
+
+    /// A bulk string whose payload is not followed by `\r\n` is a protocol
+    /// error, whether the terminator is wrong or the length does not match
+    /// the data.
+    #[test]
+    fn bulk_bad_terminator() {
+        for src in [
+            &b"$3\r\nabcXY"[..],
+            b"$3\r\nabcd\r\n",
+            b"*1\r\n$4\r\nPING\n\r",
+        ] {
+            let mut cursor = Cursor::new(src);
+            assert!(
+                matches!(Frame::check(&mut cursor), Err(Error::Other(_))),
+                "{:?}",
+                src
+            );
+
+            let mut cursor = Cursor::new(src);
+            assert!(
+                matches!(Frame::parse(&mut cursor), Err(Error::Other(_))),
+                "{:?}",
+                src
+            );
+        }
+
+        // A correctly terminated bulk string still parses.
+        let (frame, len) = Frame::parse_from(b"$3\r\nabc\r\n").unwrap();
+        assert_eq!(frame, "abc");
+        assert_eq!(len, 9);
+    }
+
+    /// `Frame::parse_from` reports how many bytes the frame used and leaves
+    /// any following data alone.
+    #[test]
+    fn parse_from() {
+        let src = b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n+OK\r\n";
+
+        let (frame, len) = Frame::parse_from(src).unwrap();
+        assert_eq!(frame.to_string(), "GET hello");
+        assert_eq!(len, src.len() - 5);
+
+        let (frame, len) = Frame::parse_from(&src[len..]).unwrap();
+        assert_eq!(frame, "OK");
+        assert_eq!(len, 5);
+
+        assert!(matches!(
+            Frame::parse_from(&src[..10]),
+            Err(Error::Incomplete)
+        ));
+    }
+
+    /// Frames compare structurally, including nested arrays, so a parsed
+    /// frame equals the one that was encoded.
+    #[test]
+    fn equality() {
+        let frame = Frame::Array(vec![
+            Frame::Simple("OK".into()),
+            Frame::Error("ERR oops".into()),
+            Frame::Integer(-1),
+            Frame::Bulk("hello".into()),
+            Frame::Null,
+            Frame::Array(vec![Frame::Array(vec![]), Frame::Integer(2)]),
+        ]);
+
+        let (parsed, _) = Frame::parse_from(&frame.encode()).unwrap();
+        assert_eq!(parsed, frame);
+
+        // Frames which display the same still differ by variant.
+        assert_ne!(Frame::Simple("OK".into()), Frame::Bulk("OK".into()));
+        assert_ne!(Frame::Integer(1), Frame::Bulk("1".into()));
+        assert_ne!(
+            Frame::Array(vec![Frame::Integer(1)]),
+            Frame::Array(vec![Frame::Array(vec![Frame::Integer(1)])])
+        );
+    }
+}
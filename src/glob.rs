@@ -0,0 +1,99 @@
+/// 如果 `string` 与 Redis 风格的 glob 模式 `pattern` 匹配，则返回 `true`。
+///
+/// 支持的语法与 Redis 的 `KEYS` 和 `SCAN` 的 `MATCH` 选项相同：
+///
+/// * `*` 匹配任意数量（包括零个）的字节。
+/// * `?` 匹配恰好一个字节。
+/// * `[abc]` 匹配括号中的任意一个字节，`[a-z]` 匹配一个范围，`[^abc]` 匹配不在括号中的字节。
+/// * `\x` 按字面匹配 `x`。
+///
+/// 匹配按字节进行，不考虑 UTF-8。未闭合的 `[` 会延伸到模式的末尾。
+pub(crate) fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    let mut p = 0;
+    let mut s = 0;
+
+    // 最近一个 `*` 之后的模式位置，以及该 `*` 当前匹配到的字符串位置。后续不匹配时，让这个 `*` 多匹配一个字节后重试。
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < string.len() {
+        let next = match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p + 1, s));
+                p += 1;
+                continue;
+            }
+            Some(b'?') => Some(p + 1),
+            Some(b'[') => {
+                let (matched, end) = match_class(pattern, p + 1, string[s]);
+                if matched {
+                    Some(end)
+                } else {
+                    None
+                }
+            }
+            Some(b'\\') if p + 1 < pattern.len() => {
+                if pattern[p + 1] == string[s] {
+                    Some(p + 2)
+                } else {
+                    None
+                }
+            }
+            Some(&c) if c == string[s] => Some(p + 1),
+            _ => None,
+        };
+
+        match (next, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                s += 1;
+            }
+            (None, Some((star_p, star_s))) => {
+                p = star_p;
+                s = star_s + 1;
+                backtrack = Some((star_p, star_s + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+
+    // 字符串已经耗尽，剩余的模式只能由 `*` 组成。
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// 将字节 `c` 与从 `start`（`[` 之后）开始的字符类匹配。
+///
+/// 返回是否匹配以及字符类之后的模式位置。
+fn match_class(pattern: &[u8], start: usize, c: u8) -> (bool, usize) {
+    let mut i = start;
+
+    let negate = pattern.get(i) == Some(&b'^');
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+
+    while i < pattern.len() {
+        match pattern[i] {
+            b']' => {
+                i += 1;
+                break;
+            }
+            b'\\' if i + 1 < pattern.len() => {
+                matched |= pattern[i + 1] == c;
+                i += 2;
+            }
+            lo if i + 2 < pattern.len() && pattern[i + 1] == b'-' && pattern[i + 2] != b']' => {
+                let hi = pattern[i + 2];
+                matched |= lo.min(hi) <= c && c <= lo.max(hi);
+                i += 3;
+            }
+            other => {
+                matched |= other == c;
+                i += 1;
+            }
+        }
+    }
+
+    (matched != negate, i)
+}
@@ -16,7 +16,9 @@
 //!
 //! * `frame`：表示单个 Redis 协议帧。帧被用作在“命令”和字节表示之间的中间表示。
 //!
-//! ```rust
+//! * `codec`：启用 `codec` 特性时可用，为 `Frame` 实现 `tokio_util` 的 `Encoder` 和 `Decoder`。
+//!
+//! ```text
 //! pub mod clients;
 //! pub use clients::{BlockingClient, BufferedClient, Client};
 //!
@@ -24,7 +26,7 @@
 //! pub use cmd::Command;
 //!
 //! mod connection;
-//! pub use connection::Connection;
+//! pub use connection::{Connection, ConnectionError};
 //!
 //! pub mod frame;
 //! pub use frame::Frame;
@@ -33,13 +35,34 @@
 //! use db::Db;
 //! use db::DbDropGuard;
 //!
+//! mod glob;
+//!
 //! mod parse;
 //! use parse::{Parse, ParseError};
 //!
 //! pub mod server;
 //!
+//! mod session;
+//! use session::Session;
+//!
 //! mod shutdown;
 //! use shutdown::Shutdown;
+//!
+//! mod slowlog;
+//! use slowlog::SlowLog;
+//!
+//! mod sorted_set;
+//!
+//! mod stats;
+//! use stats::Stats;
+//!
+//! #[cfg(feature = "metrics")]
+//! mod metrics;
+//!
+//! #[cfg(feature = "codec")]
+//! pub mod codec;
+//! #[cfg(feature = "codec")]
+//! pub use codec::FrameCodec;
 //! ```
 
 pub mod clients;
@@ -49,7 +72,7 @@ pub mod cmd;
 pub use cmd::Command;
 
 mod connection;
-pub use connection::Connection;
+pub use connection::{Connection, ConnectionError};
 
 pub mod frame;
 pub use frame::Frame;
@@ -58,14 +81,35 @@ mod db;
 use db::Db;
 use db::DbDropGuard;
 
+mod glob;
+
 mod parse;
 use parse::{Parse, ParseError};
 
 pub mod server;
 
+mod session;
+use session::Session;
+
 mod shutdown;
 use shutdown::Shutdown;
 
+mod slowlog;
+use slowlog::SlowLog;
+
+mod sorted_set;
+
+mod stats;
+use stats::Stats;
+
+#[cfg(feature = "metrics")]
+mod metrics;
+
+#[cfg(feature = "codec")]
+pub mod codec;
+#[cfg(feature = "codec")]
+pub use codec::FrameCodec;
+
 /// Redis 服务器监听的默认端口。
 ///
 /// 如果没有指定端口，将使用此端口。
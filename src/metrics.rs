@@ -0,0 +1,137 @@
+//! 以 Prometheus 文本格式导出服务器统计信息的极简 HTTP 端点。
+//!
+//! 这不是一个完整的 HTTP 服务器：每个连接只处理一个请求，只支持 `GET /metrics`，响应后连接即被关闭。这足以让 Prometheus 抓取指标。
+
+use crate::Stats;
+
+use std::fmt::Write;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, error};
+
+/// 请求头的最大长度。超过此长度的请求会被拒绝。
+const MAX_REQUEST_LEN: usize = 8 * 1024;
+
+/// 在 `listener` 上接受连接，并以 `stats` 中的统计信息响应每个请求。
+///
+/// 此函数不会返回；服务器在关闭时中止运行它的任务。
+pub(crate) async fn serve(listener: TcpListener, stats: Stats) {
+    loop {
+        let socket = match listener.accept().await {
+            Ok((socket, _)) => socket,
+            Err(err) => {
+                error!(cause = %err, "failed to accept metrics connection");
+                continue;
+            }
+        };
+
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            if let Err(err) = respond(socket, &stats).await {
+                debug!(cause = ?err, "metrics connection error");
+            }
+        });
+    }
+}
+
+/// 读取单个请求并写入响应。
+async fn respond(mut socket: TcpStream, stats: &Stats) -> crate::Result<()> {
+    let mut buf = Vec::with_capacity(1024);
+
+    // 读取直到请求头结束。请求体（如果有）被忽略。
+    while !buf.windows(4).any(|window| window == b"\r\n\r\n") {
+        if buf.len() > MAX_REQUEST_LEN {
+            return Err("metrics request too large".into());
+        }
+
+        if 0 == socket.read_buf(&mut buf).await? {
+            return Err("metrics connection closed before the request was complete".into());
+        }
+    }
+
+    // 请求行的形式为 `GET /metrics HTTP/1.1`。
+    let request = String::from_utf8_lossy(&buf);
+    let mut request_line = request.lines().next().unwrap_or("").split(' ');
+
+    let (status, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render(stats)),
+        _ => ("404 Not Found", String::new()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await?;
+
+    Ok(())
+}
+
+/// 以 Prometheus 文本格式渲染 `stats`。
+///
+/// 写入 `String` 不会失败，因此忽略 `writeln!` 的结果。
+fn render(stats: &Stats) -> String {
+    let mut dst = String::new();
+
+    let gauges: &[(&str, &str, u64)] = &[
+        (
+            "mini_redis_uptime_seconds",
+            "Seconds since the server started.",
+            stats.uptime().as_secs(),
+        ),
+        (
+            "mini_redis_connected_clients",
+            "Connections currently open.",
+            stats.active_connections() as u64,
+        ),
+    ];
+
+    let counters: &[(&str, &str, u64)] = &[
+        (
+            "mini_redis_connections_accepted_total",
+            "Connections accepted by the server.",
+            stats.connections_accepted(),
+        ),
+        (
+            "mini_redis_commands_processed_total",
+            "Commands processed by the server.",
+            stats.total_commands(),
+        ),
+        (
+            "mini_redis_net_input_bytes_total",
+            "Bytes read from clients.",
+            stats.net_input_bytes(),
+        ),
+        (
+            "mini_redis_net_output_bytes_total",
+            "Bytes written to clients.",
+            stats.net_output_bytes(),
+        ),
+        (
+            "mini_redis_error_replies_total",
+            "Error replies sent to clients.",
+            stats.error_replies(),
+        ),
+    ];
+
+    for (kind, metrics) in &[("gauge", gauges), ("counter", counters)] {
+        for (name, help, value) in metrics.iter() {
+            let _ = writeln!(dst, "# HELP {} {}", name, help);
+            let _ = writeln!(dst, "# TYPE {} {}", name, kind);
+            let _ = writeln!(dst, "{} {}", name, value);
+        }
+    }
+
+    let name = "mini_redis_command_calls_total";
+    let _ = writeln!(dst, "# HELP {} Calls of each command.", name);
+    let _ = writeln!(dst, "# TYPE {} counter", name);
+    for (command, calls) in stats.commands() {
+        let _ = writeln!(dst, "{}{{command=\"{}\"}} {}", name, command, calls);
+    }
+
+    dst
+}
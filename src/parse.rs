@@ -1,6 +1,7 @@
 use crate::Frame;
 
 use bytes::Bytes;
+use std::convert::TryFrom;
 use std::{fmt, str, vec};
 
 /// 用于解析命令的实用工具
@@ -45,6 +46,24 @@ impl Parse {
         self.parts.next().ok_or(ParseError::EndOfStream)
     }
 
+    /// 返回尚未消耗的条目数量。
+    ///
+    /// 可变参数命令可以使用它预先验证参数的数量，例如参数必须成对出现时。
+    pub(crate) fn remaining(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// 在不消耗的情况下，将下一个条目作为字符串返回。
+    ///
+    /// 如果没有更多条目，或者下一个条目不能表示为字符串，则返回 `None`。这使可选参数的解析可以向前查看，而不必提交到某个分支。
+    pub(crate) fn peek_string(&self) -> Option<&str> {
+        match self.parts.as_slice().first()? {
+            Frame::Simple(s) => Some(s),
+            Frame::Bulk(data) => str::from_utf8(data).ok(),
+            _ => None,
+        }
+    }
+
     /// 将下一个条目作为字符串返回。
     ///
     /// 如果下一个条目不能表示为字符串，则返回错误。
@@ -96,7 +115,7 @@ impl Parse {
 
         match self.next()? {
             // 整数帧类型已经存储为整数。
-            Frame::Integer(v) => Ok(v),
+            Frame::Integer(v) => u64::try_from(v).map_err(|_| MSG.into()),
             // 必须将 `Simple` 和 `Bulk` 帧类型解析为整数。如果解析失败，则返回错误。
             Frame::Simple(data) => atoi::<u64>(data.as_bytes()).ok_or_else(|| MSG.into()),
             Frame::Bulk(data) => atoi::<u64>(&data).ok_or_else(|| MSG.into()),
@@ -104,6 +123,38 @@ impl Parse {
         }
     }
 
+    /// 将下一个条目作为有符号整数返回。
+    ///
+    /// 与 `next_int` 相同，但接受前导的 `-`，例如用于负索引 `-1`。
+    ///
+    /// 如果下一个条目不能表示为 64 位有符号整数，则返回错误。
+    pub(crate) fn next_i64(&mut self) -> Result<i64, ParseError> {
+        use atoi::atoi;
+
+        const MSG: &str = "协议错误；无效的数字";
+
+        match self.next()? {
+            Frame::Integer(v) => Ok(v),
+            Frame::Simple(data) => atoi::<i64>(data.as_bytes()).ok_or_else(|| MSG.into()),
+            Frame::Bulk(data) => atoi::<i64>(&data).ok_or_else(|| MSG.into()),
+            frame => Err(format!("协议错误；期望整数帧但得到 {:?}", frame).into()),
+        }
+    }
+
+    /// 将下一个条目作为浮点数返回。
+    ///
+    /// 这包括 `Simple`、`Bulk` 和 `Integer` 帧类型。文本的解析规则见 `parse_f64`。
+    ///
+    /// 如果下一个条目不能表示为浮点数，则返回错误。
+    pub(crate) fn next_f64(&mut self) -> Result<f64, ParseError> {
+        match self.next()? {
+            Frame::Integer(v) => Ok(v as f64),
+            Frame::Simple(data) => parse_f64(data.as_bytes()),
+            Frame::Bulk(data) => parse_f64(&data),
+            frame => Err(format!("协议错误；期望浮点数帧但得到 {:?}", frame).into()),
+        }
+    }
+
     /// 确保数组中没有更多条目
     pub(crate) fn finish(&mut self) -> Result<(), ParseError> {
         if self.parts.next().is_none() {
@@ -114,6 +165,22 @@ impl Parse {
     }
 }
 
+/// 将文本解析为浮点数。
+///
+/// 除了常规的十进制和科学计数法（例如 `1.5`、`-2`、`1e3`）之外，还接受不区分大小写的 `inf`、`+inf`、`-inf` 和 `infinity`。`nan` 会被拒绝：它无法与其他值比较，因此不能用作分数或增量，这与 Redis 一致。
+///
+/// 供需要解析带有额外语法的数字的命令使用，例如 `ZRANGEBYSCORE` 中的 `(1.5`。
+pub(crate) fn parse_f64(src: &[u8]) -> Result<f64, ParseError> {
+    const MSG: &str = "协议错误；无效的浮点数";
+
+    let src = str::from_utf8(src).map_err(|_| ParseError::from(MSG))?;
+
+    match src.parse::<f64>() {
+        Ok(v) if !v.is_nan() => Ok(v),
+        _ => Err(MSG.into()),
+    }
+}
+
 impl From<String> for ParseError {
     fn from(src: String) -> ParseError {
         ParseError::Other(src.into())
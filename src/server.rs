@@ -2,14 +2,24 @@
 //!
 //! 提供一个异步的`run`函数，用于监听入站连接，为每个连接生成一个任务。
 
-use crate::{Command, Connection, Db, DbDropGuard, Shutdown};
-
+use crate::cmd::CommandError;
+pub use crate::db::EvictionPolicy;
+use crate::session::Clients;
+use crate::slowlog::{self, SlowLog};
+use crate::{
+    frame, Command, Connection, ConnectionError, Db, DbDropGuard, Frame, Session, Shutdown, Stats,
+};
+
+use socket2::{SockRef, TcpKeepalive};
+use std::fmt::Debug;
 use std::future::Future;
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{broadcast, mpsc, Semaphore};
-use tokio::time::{self, Duration};
-use tracing::{debug, error, info, instrument};
+use tokio::sync::{broadcast, mpsc, Notify, Semaphore};
+use tokio::time::{self, Duration, Instant};
+use tracing::{debug, error, field, info, info_span, instrument, warn, Instrument};
 
 /// 服务器监听状态。在`run`调用中创建。它包括一个`run`方法，执行TCP监听和每个连接的初始化。
 #[derive(Debug)]
@@ -38,12 +48,40 @@ struct Listener {
     /// 每个活动连接都会收到它，达到安全的终端状态后完成任务。
     notify_shutdown: broadcast::Sender<()>,
 
+    /// 由`SHUTDOWN`命令通知以启动关闭。
+    ///
+    /// 每个连接的`Shutdown`都持有一个克隆。`run`在等待`shutdown` future 的同时等待此通知。
+    initiate_shutdown: Arc<Notify>,
+
     /// 用于优雅关闭过程的等待客户端连接完成处理。
     ///
     /// 当所有`Sender`句柄超出范围时，Tokio通道将关闭。
     /// 当通道关闭时，接收器将接收`None`。这被用于检测所有连接处理程序的完成。当连接处理程序初始化时，它会被分配一个`shutdown_complete_tx`字段的克隆。
     /// 当监听器关闭时，它放弃了`shutdown_complete_tx`字段持有的发送方。一旦所有处理程序任务完成，`Sender`的所有克隆也将被丢弃。这导致`shutdown_complete_rx.recv()`完成为`None`。此时，安全地退出服务器进程。
     shutdown_complete_tx: mpsc::Sender<()>,
+
+    /// 用于为每个接受的连接分配唯一 id 的计数器。
+    ///
+    /// id 从 1 开始单调递增，通过 `CLIENT ID` 暴露给客户端，并记录在连接的日志中。
+    next_client_id: AtomicU64,
+
+    /// 所有活动连接的注册表。
+    ///
+    /// 每个连接处理程序在启动时将自己注册到此处，并在被丢弃时移除自己。`CLIENT LIST` 读取此注册表。
+    clients: Clients,
+
+    /// 服务器范围的统计信息，例如服务器启动的时刻和处理的命令总数。
+    ///
+    /// 在创建 `Listener` 时初始化，因此启动时刻即为服务器开始接受连接的时刻。`INFO` 读取这些统计信息。
+    stats: Stats,
+
+    /// 执行时间超过`Config::slowlog_log_slower_than`的命令的日志。`SLOWLOG` 读取此日志。
+    slowlog: SlowLog,
+
+    /// 由`run_with_config`调用者提供的配置，应用于每个新连接。
+    ///
+    /// 在所有连接之间共享，`CONFIG SET` 可以在运行时修改其中的一部分参数。与 `Db` 一样，使用 `std::sync::Mutex`，因为在持有锁时不会执行异步操作。
+    config: Arc<Mutex<Config>>,
 }
 
 /// 每个连接处理程序。从`connection`读取请求并将命令应用于`db`。
@@ -65,6 +103,14 @@ struct Handler {
     /// 封装在`Listener`中的`broadcast::Receiver`与`Listener`中的发送方配对。连接处理程序处理来自连接的请求，直到对`shutdown`的关闭通知**或**从`shutdown`接收到关闭通知。在后一种情况下，正在处理的对等方的任何正在进行中的工作都会继续进行，直到达到安全状态，此时连接终止。
     shutdown: Shutdown,
 
+    /// 连接的元数据，例如其 id 和通过 `CLIENT SETNAME` 设置的名称。
+    ///
+    /// 会被传递给每个命令，以便像 `CLIENT` 这样的命令可以检查或修改它。
+    session: Session,
+
+    /// 为`true`时拒绝所有写命令，见`Config::read_only`。
+    read_only: bool,
+
     /// 不直接使用。相反，当`Handler`被释放时……？
     _shutdown_complete: mpsc::Sender<()>,
 }
@@ -78,12 +124,179 @@ struct Handler {
 /// 这也设置为一个相当低的值，以阻止在生产中使用（你可能认为所有的免责声明都会使人们明白这不是一个严肃的项目……但我对mini-http也是这么想的）。
 const MAX_CONNECTIONS: usize = 250;
 
+/// `Config::pubsub_capacity` 允许的最大值。
+///
+/// 每个通道在创建时按容量分配缓冲区，过大的容量会在第一次订阅时耗尽内存。
+pub const MAX_PUBSUB_CAPACITY: usize = 1 << 20;
+
+/// 服务器配置，传递给`run_with_config`。
+///
+/// 使用`Config::default()`获得与`run`相同的行为，然后覆盖需要更改的字段。
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// 每个发布/订阅通道保留的、尚未被所有订阅者看到的消息数。
+    ///
+    /// 较大的容量使慢速订阅者能够容忍更长的落后，代价是更多的内存。落后超过此数量的订阅者会丢失最旧的消息。默认为 1024，必须大于零且不超过`MAX_PUBSUB_CAPACITY`。
+    pub pubsub_capacity: usize,
+
+    /// 客户端发送的帧中单个数组允许的最大元素数。
+    ///
+    /// 发送更大数组的连接会因协议错误而被关闭，其他连接不受影响。默认为`frame::DEFAULT_MAX_ARRAY_LEN`。
+    pub max_array_len: usize,
+
+    /// 客户端发送的帧中单行允许的最大字节数。
+    ///
+    /// 发送更长的行，或在此字节数内未发送`\r\n`的连接会因协议错误而被关闭。默认为`frame::DEFAULT_MAX_LINE_LEN`。
+    pub max_line_len: usize,
+
+    /// 客户端发送的帧中单个字符串块允许的最大字节数。
+    ///
+    /// 发送更大的字符串块的连接会因协议错误而被关闭，其内容不会被缓冲。默认为`frame::DEFAULT_MAX_BULK_LEN`，即 512 MiB。
+    pub max_bulk_len: usize,
+
+    /// 客户端发送的单个顶层帧（即一条命令）允许的最大字节数。
+    ///
+    /// 由大量小元素组成、单独都不超过其他限制的命令，一旦累计超过此字节数，连接就会因协议错误而被关闭。默认为`frame::DEFAULT_MAX_FRAME_LEN`，即 1 GiB。
+    pub max_frame_len: usize,
+
+    /// 接受入站连接失败后两次重试之间等待的最长时间。
+    ///
+    /// 第一次失败后等待 1 秒，之后每次失败等待时间加倍，直到达到此上限。默认为 64 秒。
+    pub accept_max_backoff: Duration,
+
+    /// 接受入站连接连续失败时的最大重试次数。
+    ///
+    /// 超过此次数后，`run`会返回，服务器停止。默认为 7，即在放弃之前最多等待 1 + 2 + ... + 64 秒。
+    pub accept_max_retries: u32,
+
+    /// 后台任务每次持有数据库锁时最多删除的过期键数。
+    ///
+    /// 大量键同时过期时，清理分批进行，批次之间释放锁，以免长时间阻塞其他连接。默认为 1024，必须大于零。
+    pub expire_batch_size: usize,
+
+    /// 为`true`时，服务器拒绝所有带有`write`标志的命令，并回复`READONLY`错误。
+    ///
+    /// 读命令和发布/订阅不受影响。适用于让工具访问不希望被修改的数据集。默认为`false`。
+    pub read_only: bool,
+
+    /// 估算的内存使用量（与`INFO memory`中的`used_memory`相同）的上限，单位为字节。
+    ///
+    /// 超过上限时，服务器在执行可能增加内存使用量的命令之前按照`maxmemory_policy`删除键。默认为`0`，表示没有限制。
+    pub maxmemory: usize,
+
+    /// 内存使用量超过`maxmemory`时选择要删除的键的策略。默认为`EvictionPolicy::NoEviction`，此时超过上限的写命令会被拒绝。
+    pub maxmemory_policy: EvictionPolicy,
+
+    /// 是否在接受的套接字上设置`TCP_NODELAY`。
+    ///
+    /// 禁用 Nagle 算法可以降低小回复的延迟，代价是可能发送更多的小数据包。默认为`true`，与 Redis 相同。
+    pub tcp_nodelay: bool,
+
+    /// 在接受的套接字上启用 TCP keepalive，连接空闲这么长时间后开始发送探测。
+    ///
+    /// 可以检测到已经消失的对等方，并使中间的网络设备保持长时间空闲的连接。`None`表示不启用。默认为 300 秒，与 Redis 的`tcp-keepalive`相同。
+    pub tcp_keepalive: Option<Duration>,
+
+    /// 执行时间达到此值的命令会被记录到慢日志中，可以通过`SLOWLOG GET`读取。
+    ///
+    /// 只计算执行命令的时间，不包括读取请求和等待下一个请求的时间。`Some(Duration::ZERO)`记录所有命令，`None`禁用慢日志。默认为 10 毫秒，与 Redis 的`slowlog-log-slower-than`相同。
+    pub slowlog_log_slower_than: Option<Duration>,
+
+    /// 慢日志保留的最大记录数。记录数达到上限后，最旧的记录被丢弃。默认为 128，与 Redis 的`slowlog-max-len`相同。
+    pub slowlog_max_len: usize,
+
+    /// 每个连接尚未被对等方接受的回复允许的最大字节数。
+    ///
+    /// 设置后，写入回复不再等待对等方读取。从不读取的客户端（例如停止读取的订阅者）的待发送数据超过此值时，连接被关闭并记录警告，而不是无限地缓冲或阻塞连接处理程序。只影响之后接受的连接。默认为`0`，表示没有限制，此时写入回复会等待对等方读取。
+    pub output_buffer_limit: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            pubsub_capacity: 1024,
+            max_array_len: frame::DEFAULT_MAX_ARRAY_LEN,
+            max_line_len: frame::DEFAULT_MAX_LINE_LEN,
+            max_bulk_len: frame::DEFAULT_MAX_BULK_LEN,
+            max_frame_len: frame::DEFAULT_MAX_FRAME_LEN,
+            accept_max_backoff: Duration::from_secs(64),
+            accept_max_retries: 7,
+            expire_batch_size: 1024,
+            read_only: false,
+            maxmemory: 0,
+            maxmemory_policy: EvictionPolicy::NoEviction,
+            tcp_nodelay: true,
+            tcp_keepalive: Some(Duration::from_secs(300)),
+            slowlog_log_slower_than: Some(Duration::from_millis(10)),
+            slowlog_max_len: 128,
+            output_buffer_limit: 0,
+        }
+    }
+}
+
+/// 触发服务器关闭的信号。
+///
+/// 服务器二进制文件将其作为`shutdown` future 的输出传递给`run`，以便在日志中记录关闭的原因。由`SHUTDOWN`命令触发的关闭由服务器自己记录为`Command`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// 收到 Ctrl-C，即 SIGINT。
+    CtrlC,
+
+    /// 收到 SIGTERM，例如来自进程管理器。
+    Terminate,
+
+    /// 客户端发送了`SHUTDOWN`命令。
+    Command,
+}
+
 /// 运行mini-redis服务器。
 ///
-/// 从提供的监听器接受连接。对于每个入站连接，将生成一个任务来处理该连接。服务器运行直到`shutdown`未完成，此时服务器将优雅地关闭。
+/// 从提供的监听器接受连接。对于每个入站连接，将生成一个任务来处理该连接。服务器运行直到`shutdown`完成或客户端发送`SHUTDOWN`命令，此时服务器将优雅地关闭。`shutdown`的输出作为关闭原因记录在日志中，例如`ShutdownReason`。
 ///
 /// `tokio::signal::ctrl_c()`可以用作`shutdown`参数。这将监听SIGINT信号。
-pub async fn run(listener: TcpListener, shutdown: impl Future) {
+pub async fn run(listener: TcpListener, shutdown: impl Future<Output = impl Debug>) {
+    run_with_config(listener, Config::default(), shutdown).await
+}
+
+/// 使用`config`运行mini-redis服务器。
+///
+/// 除了配置之外，行为与`run`相同。
+///
+/// # Panics
+///
+/// 如果`config.pubsub_capacity`为零或大于`MAX_PUBSUB_CAPACITY`，或者`config.expire_batch_size`为零，则会 panic。
+pub async fn run_with_config(
+    listener: TcpListener,
+    config: Config,
+    shutdown: impl Future<Output = impl Debug>,
+) {
+    run_with_stats(listener, config, Stats::new(), shutdown).await
+}
+
+/// 运行mini-redis服务器，并在`metrics`上以 Prometheus 文本格式提供服务器的统计信息。
+///
+/// 除了指标端点之外，行为与`run_with_config`相同。指标端点在`GET /metrics`上响应，并在服务器关闭时停止。
+#[cfg(feature = "metrics")]
+pub async fn run_with_metrics(
+    listener: TcpListener,
+    metrics: TcpListener,
+    config: Config,
+    shutdown: impl Future<Output = impl Debug>,
+) {
+    let stats = Stats::new();
+
+    let exporter = tokio::spawn(crate::metrics::serve(metrics, stats.clone()));
+    run_with_stats(listener, config, stats, shutdown).await;
+    exporter.abort();
+}
+
+/// `run_with_config`和`run_with_metrics`的共享实现。`stats`在所有连接之间共享。
+async fn run_with_stats(
+    listener: TcpListener,
+    config: Config,
+    stats: Stats,
+    shutdown: impl Future<Output = impl Debug>,
+) {
     // 当提供的`shutdown`未完成时，我们必须向所有活动连接发送关闭消息。我们使用广播通道来实现这一目的。
     // 以下调用忽略了广播对的接收器，当需要接收器时，可以使用发送方上的subscribe()方法创建一个。
     let (notify_shutdown, _) = broadcast::channel(1);
@@ -92,10 +305,21 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
     // 初始化监听器状态
     let mut server = Listener {
         listener,
-        db_holder: DbDropGuard::new(),
+        db_holder: DbDropGuard::new(
+            config.pubsub_capacity,
+            config.expire_batch_size,
+            config.maxmemory,
+            config.maxmemory_policy,
+        ),
         limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
         notify_shutdown,
+        initiate_shutdown: Arc::new(Notify::new()),
         shutdown_complete_tx,
+        next_client_id: AtomicU64::new(1),
+        clients: Clients::new(),
+        stats,
+        slowlog: SlowLog::new(config.slowlog_log_slower_than, config.slowlog_max_len),
+        config: Arc::new(Mutex::new(config)),
     };
 
     // 并发运行服务器并监听`shutdown`信号。任务在遇到错误时运行，因此在正常情况下，此`select!`语句运行直到接收到`shutdown`信号。
@@ -111,6 +335,8 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
     // `select!`宏是编写异步Rust的基础构建块之一。有关更多详细信息，请参见API文档：
     //
     // https://docs.rs/tokio/*/tokio/macro.select.html
+    let initiate_shutdown = server.initiate_shutdown.clone();
+
     tokio::select! {
         res = server.run() => {
             // 如果在这里收到错误，表示从TCP监听器接受连接多次失败，服务器正在放弃并关闭。
@@ -120,9 +346,13 @@ pub async fn run(listener: TcpListener, shutdown: impl Future) {
                 error!(cause = %err, "failed to accept");
             }
         }
-        _ = shutdown => {
+        reason = shutdown => {
             // 已收到关闭信号。
-            info!("shutting down");
+            info!(?reason, "shutting down");
+        }
+        _ = initiate_shutdown.notified() => {
+            // 客户端请求了关闭。
+            info!(reason = ?ShutdownReason::Command, "shutting down");
         }
     }
 
@@ -172,18 +402,45 @@ impl Listener {
 
             // 接受新套接字。这将尝试执行错误处理。
             // `accept`方法内部尝试恢复错误，因此此处的错误是不可恢复的。
-            let socket = self.accept().await?;
+            let (socket, addr) = self.accept().await?;
+            self.stats.incr_connections();
+
+            // 新连接使用接受时的配置，之后通过 `CONFIG SET` 做的修改不影响已经建立的连接。
+            let config = self.config();
+            configure_socket(&socket, &config);
+
+            let mut connection = Connection::new(socket);
+            connection.set_max_array_len(config.max_array_len);
+            connection.set_max_line_len(config.max_line_len);
+            connection.set_max_bulk_len(config.max_bulk_len);
+            connection.set_max_frame_len(config.max_frame_len);
+            connection.set_output_buffer_limit(config.output_buffer_limit);
 
             // 创建所需的每个连接处理程序状态。
             let mut handler = Handler {
                 // 获取共享数据库的句柄。
                 db: self.db_holder.db(),
 
-                // 初始化连接状态。这会为执行redis协议帧解析分配读/写缓冲区。
-                connection: Connection::new(socket),
+                // 连接状态，已为执行redis协议帧解析分配读/写缓冲区。
+                connection,
 
                 // 接收关闭通知。
-                shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
+                shutdown: Shutdown::new(
+                    self.notify_shutdown.subscribe(),
+                    self.initiate_shutdown.clone(),
+                ),
+
+                // 为连接分配下一个 id，并将其注册到活动连接的注册表中。
+                session: Session::new(
+                    self.next_client_id.fetch_add(1, Ordering::Relaxed),
+                    addr,
+                    self.clients.clone(),
+                    self.stats.clone(),
+                    self.slowlog.clone(),
+                    self.config.clone(),
+                ),
+
+                read_only: config.read_only,
 
                 // 一旦克隆全部被删除，通知接收器的不使用。
                 _shutdown_complete: self.shutdown_complete_tx.clone(),
@@ -191,9 +448,12 @@ impl Listener {
 
             // 生成一个新任务以处理连接。Tokio任务类似于异步的绿色线程，并且是并发执行的。
             tokio::spawn(async move {
-                // 处理连接。如果遇到错误，则记录它。
+                // 处理连接。如果遇到错误，则记录它。对等方在发送帧的过程中断开是客户端一侧的问题，不是服务器的错误，因此只以 info 级别记录。
                 if let Err(err) = handler.run().await {
-                    error!(cause = ?err, "connection error");
+                    match err.downcast_ref::<ConnectionError>() {
+                        Some(err) => info!(cause = %err, "connection closed mid-frame"),
+                        None => error!(cause = ?err, "connection error"),
+                    }
                 }
                 // 将许可证移到任务中并在完成后将其丢弃。这将许可证返回到信号量。
                 drop(permit);
@@ -203,28 +463,56 @@ impl Listener {
 
     /// 接受入站连接。
     ///
-    /// 错误通过后退和重试来处理。使用指数后退策略。第一次失败后，任务将等待1秒。第二次失败后，任务将等待2秒。每次后续失败都会使等待时间加倍。如果在等待64秒后第6次尝试接受失败，那么此函数将带有错误返回。
-    async fn accept(&mut self) -> crate::Result<TcpStream> {
-        let mut backoff = 1;
+    /// 错误通过后退和重试来处理。使用指数后退策略。第一次失败后，任务将等待1秒。每次后续失败都会使等待时间加倍，但不超过`Config::accept_max_backoff`。每次重试前都会记录一条警告，包含错误和下一次等待的时间。如果重试`Config::accept_max_retries`次后接受仍然失败，那么此函数将带有错误返回。
+    async fn accept(&mut self) -> crate::Result<(TcpStream, SocketAddr)> {
+        let config = self.config();
+        let mut backoff = Duration::from_secs(1).min(config.accept_max_backoff);
+        let mut retries = 0;
 
         // 尝试接受几次
         loop {
             // 执行接受操作。如果成功接受套接字，则返回它。否则，保存错误。
             match self.listener.accept().await {
-                Ok((socket, _)) => return Ok(socket),
+                Ok((socket, addr)) => return Ok((socket, addr)),
                 Err(err) => {
-                    if backoff > 64 {
+                    if retries >= config.accept_max_retries {
                         // 接受失败太多次。返回错误。
+                        error!(cause = ?err, retries, "failed to accept, giving up");
                         return Err(err.into());
                     }
+
+                    warn!(cause = ?err, ?backoff, retries, "failed to accept, retrying");
                 }
             }
 
             // 暂停执行直到后退期间过去。
-            time::sleep(Duration::from_secs(backoff)).await;
+            time::sleep(backoff).await;
+            retries += 1;
 
             // 将后退加倍
-            backoff *= 2;
+            backoff = (backoff * 2).min(config.accept_max_backoff);
+        }
+    }
+
+    /// 返回当前配置的副本。
+    fn config(&self) -> Config {
+        self.config.lock().unwrap().clone()
+    }
+}
+
+/// 按照`config`设置刚接受的套接字的`TCP_NODELAY`和 TCP keepalive。
+///
+/// 设置失败不影响连接的正确性，因此只记录警告，连接照常处理。
+fn configure_socket(socket: &TcpStream, config: &Config) {
+    if let Err(err) = socket.set_nodelay(config.tcp_nodelay) {
+        warn!(cause = %err, "failed to set TCP_NODELAY");
+    }
+
+    if let Some(time) = config.tcp_keepalive {
+        let keepalive = TcpKeepalive::new().with_time(time);
+
+        if let Err(err) = SockRef::from(socket).set_tcp_keepalive(&keepalive) {
+            warn!(cause = %err, "failed to enable TCP keepalive");
         }
     }
 }
@@ -238,21 +526,26 @@ impl Handler {
     /// https://redis.io/topics/pipelining
     ///
     /// 当接收到关闭信号时，连接会被处理直到达到安全状态，然后终止。
-    
+    ///
     /// crate 提供的属性宏，用于标记一个异步函数或方法，并自动生成日志记录（logging）代码以记录函数的执行。这个宏的目的是简化日志记录的添加，使其与异步 Rust 代码更加兼容。
     /// 具体来说，#[instrument(skip(self))] 在生成的日志记录中表明，不需要记录这个函数中的 self 参数的详细信息。
     /// 这可以减少日志输出的冗余，尤其是当 self 是一个包含大量信息的结构体时。
-    #[instrument(skip(self))]
+    #[instrument(skip(self), fields(client_id = self.session.id()))]
     async fn run(&mut self) -> crate::Result<()> {
-        // 只要未收到关闭信号，就尝试读取新的请求帧。
-        while !self.shutdown.is_shutdown() {
+        // 只要未收到关闭信号且连接未被终止，就尝试读取新的请求帧。
+        while !self.shutdown.is_shutdown() && !self.session.is_killed() {
             // 在读取请求帧的同时，也监听关闭信号。
             let maybe_frame = tokio::select! {
                 res = self.connection.read_frame() => res?,
                 _ = self.shutdown.recv() => {
                     // 如果收到关闭信号，就从 `run` 中返回。
                     // 这将导致任务终止。
-                    return Ok(());
+                    break;
+                }
+                _ = self.session.killed() => {
+                    // 另一个连接通过 `CLIENT KILL` 终止了此连接。
+                    info!("connection killed");
+                    break;
                 }
             };
 
@@ -263,8 +556,28 @@ impl Handler {
                 None => return Ok(()),
             };
 
+            // 解析会消耗帧，因此在解析之前保留命令的参数，以便在命令执行得较慢时记录到慢日志中。
+            let args = if self.session.slowlog().is_enabled() {
+                slowlog::command_args(&frame)
+            } else {
+                Vec::new()
+            };
+
             // 将 Redis 帧转换为命令结构。如果帧不是有效的 Redis 命令或是不支持的命令，则返回错误。
-            let cmd = Command::from_frame(frame)?;
+            //
+            // 此时整个帧已经从套接字中读取，连接上的数据流仍然是同步的，因此与 Redis 一样，任何解析错误都作为错误回复发送给客户端，连接保持打开。只有 `read_frame` 和写入时的协议或 I/O 错误才会终止连接。
+            let cmd = match Command::from_frame(frame) {
+                Ok(cmd) => cmd,
+                Err(err) => {
+                    let err = match err.downcast::<CommandError>() {
+                        Ok(err) => *err,
+                        Err(err) => CommandError::InvalidRequest(err.to_string()),
+                    };
+
+                    self.connection.write_frame(&Frame::from(err)).await?;
+                    continue;
+                }
+            };
 
             // 记录 `cmd` 对象。此处的语法是由 `tracing` crate 提供的简写。可以视为类似于：
             //
@@ -275,13 +588,84 @@ impl Handler {
             // `tracing` 提供了结构化日志记录，因此信息被记录为键值对。
             debug!(?cmd);
 
+            // 只读模式下，写命令在执行之前被拒绝，连接保持打开。
+            if self.read_only && cmd.is_write() {
+                self.connection
+                    .write_frame(&Frame::from(CommandError::ReadOnly))
+                    .await?;
+                continue;
+            }
+
+            // 与 Redis 一样，在执行可能增加内存使用量的命令之前淘汰键。无法腾出足够的内存时拒绝该命令。
+            if cmd.has_flag("denyoom") && !self.db.free_memory() {
+                self.connection
+                    .write_frame(&Frame::from(CommandError::OutOfMemory))
+                    .await?;
+                continue;
+            }
+
+            // 记录最后执行的命令，以便 `CLIENT LIST` 可以报告它，并更新 `INFO` 报告的命令计数。
+            self.session.set_last_command(cmd.get_name());
+            self.session.stats().incr_commands(cmd.get_name());
+
+            // 每个命令的执行都在一个 span 中进行，以便在追踪后端中按命令关联延迟和错误。回复类型和耗时在命令完成后记录，在 span 关闭之前。
+            let span = info_span!(
+                "command",
+                name = cmd.get_name(),
+                key = cmd.key(),
+                client_id = self.session.id(),
+                reply = field::Empty,
+                duration_us = field::Empty,
+            );
+            let start = Instant::now();
+
+            // `SUBSCRIBE` 在连接退出订阅模式之前不会返回，它的执行时间并不代表服务器的延迟，因此不记录到慢日志中。
+            let is_blocking = matches!(cmd, Command::Subscribe(_));
+
             // 执行应用命令所需的工作。这可能会由于此操作导致数据库状态发生变化。
             //
             // 连接被传递到应用函数，允许命令直接向连接写入响应帧。在 pub/sub 的情况下，可能会向对等方发送多个帧。
-            cmd.apply(&self.db, &mut self.connection, &mut self.shutdown)
-                .await?;
+            cmd.apply(
+                &self.db,
+                &mut self.connection,
+                &mut self.shutdown,
+                &mut self.session,
+            )
+            .instrument(span.clone())
+            .await?;
+
+            if let Some(kind) = self.connection.take_last_reply_kind() {
+                span.record("reply", kind);
+            }
+            let duration = start.elapsed();
+            span.record("duration_us", duration.as_micros() as u64);
+
+            if !is_blocking && self.session.slowlog().is_slow(duration) {
+                self.session.slowlog().record(
+                    duration,
+                    args,
+                    self.session.addr(),
+                    self.session.name(),
+                );
+            }
+
+            // 更新 `CLIENT LIST` 报告的命令数和最后活动时间。
+            self.session.record_command();
+
+            // 读缓冲区中没有下一个请求时，这一批流水线请求已经处理完毕，将累积的回复一次性写入套接字。
+            if !self.connection.has_buffered_input() {
+                self.connection.flush().await?;
+            }
+
+            // 将读取请求和写入响应的字节数累加到服务器的统计信息中。
+            self.session
+                .stats()
+                .record_io(self.connection.take_counts());
         }
 
+        // 在关闭连接之前发送尚未写入套接字的回复，例如在收到关闭信号之前执行完的命令的回复。
+        self.connection.flush().await?;
+
         Ok(())
     }
 }
@@ -0,0 +1,238 @@
+use crate::server::Config;
+use crate::{SlowLog, Stats};
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// 单个客户端连接的元数据。
+///
+/// 每个 `Handler` 拥有一个 `Session`，并将其传递给需要检查或修改连接状态的命令（例如 `CLIENT`）。
+///
+/// 创建 `Session` 时，连接会被添加到共享的 `Clients` 注册表中；当 `Session` 被丢弃时（即连接处理程序完成时），它会从注册表中移除。
+#[derive(Debug)]
+pub(crate) struct Session {
+    /// 在接受连接时分配的唯一 id。id 单调递增，永远不会被重用。
+    id: u64,
+
+    /// 所有活动连接的注册表。此连接的名称和最后执行的命令存储在注册表中，以便其他连接的 `CLIENT LIST` 可以读取它们。
+    clients: Clients,
+
+    /// 对等方的地址。
+    addr: SocketAddr,
+
+    /// 服务器范围的统计信息，由 `INFO` 报告。
+    stats: Stats,
+
+    /// 服务器范围的慢日志，由 `SLOWLOG` 读取。
+    slowlog: SlowLog,
+
+    /// 所有连接共享的服务器配置，由 `CONFIG` 读取和修改。
+    config: Arc<Mutex<Config>>,
+
+    /// 当另一个连接通过 `CLIENT KILL` 终止此连接时收到通知。与注册表中 `ClientInfo::kill` 的句柄相同。
+    kill: Arc<Notify>,
+
+    /// 如果连接已被终止，则为 `true`。
+    is_killed: bool,
+}
+
+/// 所有活动连接的注册表。
+///
+/// 由 `Listener` 创建并在所有连接处理程序之间共享。克隆 `Clients` 是浅层的，只会增加引用计数。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Clients {
+    /// 以连接 id 为键的连接信息。与 `Db` 一样，使用 `std::sync::Mutex`，因为在持有锁时不会执行异步操作。
+    inner: Arc<Mutex<HashMap<u64, ClientInfo>>>,
+}
+
+/// `CLIENT LIST` 报告的单个连接的信息。
+#[derive(Debug, Clone)]
+pub(crate) struct ClientInfo {
+    /// 连接的 id。
+    pub(crate) id: u64,
+
+    /// 对等方的地址。
+    pub(crate) addr: SocketAddr,
+
+    /// 通过 `CLIENT SETNAME` 设置的连接名称。
+    pub(crate) name: Option<String>,
+
+    /// 接受连接的时刻。
+    pub(crate) created_at: Instant,
+
+    /// 连接最后执行的命令的名称。
+    pub(crate) last_command: Option<String>,
+
+    /// 连接已执行完成的命令数。
+    pub(crate) commands: u64,
+
+    /// 连接最后一次执行完命令的时刻。尚未执行任何命令时为接受连接的时刻。
+    pub(crate) last_interaction: Instant,
+
+    /// 用于请求终止连接。连接处理程序在等待下一帧时同时等待此通知。
+    kill: Arc<Notify>,
+}
+
+impl Session {
+    /// 为 id 为 `id`、对等方地址为 `addr` 的连接创建一个新的 `Session`，并将其注册到 `clients`。
+    pub(crate) fn new(
+        id: u64,
+        addr: SocketAddr,
+        clients: Clients,
+        stats: Stats,
+        slowlog: SlowLog,
+        config: Arc<Mutex<Config>>,
+    ) -> Session {
+        let kill = Arc::new(Notify::new());
+        let now = Instant::now();
+
+        clients.register(ClientInfo {
+            id,
+            addr,
+            name: None,
+            created_at: now,
+            last_command: None,
+            commands: 0,
+            last_interaction: now,
+            kill: kill.clone(),
+        });
+        stats.incr_active_connections();
+
+        Session {
+            id,
+            addr,
+            clients,
+            stats,
+            slowlog,
+            config,
+            kill,
+            is_killed: false,
+        }
+    }
+
+    /// 返回连接的 id。
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 返回对等方的地址。
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 返回连接的名称（如果已设置）。
+    pub(crate) fn name(&self) -> Option<String> {
+        self.clients
+            .with(self.id, |info| info.name.clone())
+            .flatten()
+    }
+
+    /// 设置连接的名称。`None` 会清除名称。
+    pub(crate) fn set_name(&mut self, name: Option<String>) {
+        self.clients.with(self.id, |info| info.name = name);
+    }
+
+    /// 记录连接最后执行的命令。
+    pub(crate) fn set_last_command(&mut self, name: &str) {
+        self.clients
+            .with(self.id, |info| info.last_command = Some(name.to_string()));
+    }
+
+    /// 记录连接执行完了一个命令：递增命令数并更新最后活动的时刻。
+    pub(crate) fn record_command(&mut self) {
+        self.clients.with(self.id, |info| {
+            info.commands += 1;
+            info.last_interaction = Instant::now();
+        });
+    }
+
+    /// 返回所有活动连接的注册表。
+    pub(crate) fn clients(&self) -> &Clients {
+        &self.clients
+    }
+
+    /// 返回服务器范围的统计信息。
+    pub(crate) fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// 返回服务器范围的慢日志。
+    pub(crate) fn slowlog(&self) -> &SlowLog {
+        &self.slowlog
+    }
+
+    /// 返回所有连接共享的服务器配置。
+    pub(crate) fn config(&self) -> &Arc<Mutex<Config>> {
+        &self.config
+    }
+
+    /// 如果连接已被 `CLIENT KILL` 终止，则返回 `true`。
+    pub(crate) fn is_killed(&self) -> bool {
+        self.is_killed
+    }
+
+    /// 等待直到连接被 `CLIENT KILL` 终止。
+    ///
+    /// `Notify::notify_one` 会在没有等待者时存储一个许可，因此即使在连接正在执行命令时请求终止，下一次调用也会立即完成。
+    pub(crate) async fn killed(&mut self) {
+        // 如果已经被终止，则立即返回。
+        if self.is_killed {
+            return;
+        }
+
+        self.kill.notified().await;
+
+        // 记住连接已被终止，以便调用者在通知被消耗后仍然可以观察到它。
+        self.is_killed = true;
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // 连接已经终止，将其从注册表中移除。
+        self.clients.inner.lock().unwrap().remove(&self.id);
+        self.stats.decr_active_connections();
+    }
+}
+
+impl Clients {
+    /// 创建一个新的、空的注册表。
+    pub(crate) fn new() -> Clients {
+        Clients::default()
+    }
+
+    /// 返回所有活动连接信息的快照，按 id 排序。
+    pub(crate) fn list(&self) -> Vec<ClientInfo> {
+        let mut clients: Vec<_> = self.inner.lock().unwrap().values().cloned().collect();
+        clients.sort_by_key(|info| info.id);
+        clients
+    }
+
+    /// 终止所有满足 `filter` 的连接，并返回被终止的连接数。
+    ///
+    /// 连接不会立即从注册表中移除。相反，它们会收到通知，并在其处理程序退出后移除自己。
+    pub(crate) fn kill(&self, filter: impl Fn(&ClientInfo) -> bool) -> usize {
+        let clients = self.inner.lock().unwrap();
+        let mut killed = 0;
+
+        for info in clients.values().filter(|info| filter(info)) {
+            info.kill.notify_one();
+            killed += 1;
+        }
+
+        killed
+    }
+
+    /// 将连接添加到注册表。
+    fn register(&self, info: ClientInfo) {
+        self.inner.lock().unwrap().insert(info.id, info);
+    }
+
+    /// 在持有锁的情况下，对 id 为 `id` 的连接信息调用 `f`。如果连接未注册，则返回 `None`。
+    fn with<T>(&self, id: u64, f: impl FnOnce(&mut ClientInfo) -> T) -> Option<T> {
+        self.inner.lock().unwrap().get_mut(&id).map(f)
+    }
+}
@@ -1,4 +1,5 @@
-use tokio::sync::broadcast;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Notify};
 
 /// 监听服务器关闭信号。
 ///
@@ -12,17 +13,28 @@ pub(crate) struct Shutdown {
 
     /// 用于监听关闭的通道的接收端。
     notify: broadcast::Receiver<()>,
+
+    /// 请求服务器关闭，由 `SHUTDOWN` 命令使用。与`run`中监听的句柄相同，所有连接共享。
+    initiate: Arc<Notify>,
 }
 
 impl Shutdown {
-    /// 创建一个由给定的 `broadcast::Receiver` 支持的新的 `Shutdown` 实例。
-    pub(crate) fn new(notify: broadcast::Receiver<()>) -> Shutdown {
+    /// 创建一个由给定的 `broadcast::Receiver` 支持的新的 `Shutdown` 实例。`initiate` 用于从连接内部请求关闭。
+    pub(crate) fn new(notify: broadcast::Receiver<()>, initiate: Arc<Notify>) -> Shutdown {
         Shutdown {
             is_shutdown: false,
             notify,
+            initiate,
         }
     }
 
+    /// 请求服务器优雅地关闭。
+    ///
+    /// 服务器随后会像收到 Ctrl-C 一样，通过广播通知所有连接，包括调用者自己的连接。`Notify::notify_one` 会在服务器尚未等待时存储一个许可，因此请求不会丢失。
+    pub(crate) fn initiate(&self) {
+        self.initiate.notify_one();
+    }
+
     /// 如果已接收关闭信号，则返回 `true`。
     pub(crate) fn is_shutdown(&self) -> bool {
         self.is_shutdown
@@ -0,0 +1,171 @@
+use crate::Frame;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 每条记录最多保留的参数数（包括命令名），与 Redis 相同。
+const MAX_ARGS: usize = 32;
+
+/// 每条记录中单个参数最多保留的字节数，与 Redis 相同。
+const MAX_ARG_LEN: usize = 128;
+
+/// 执行时间超过阈值的命令的日志，由 `SLOWLOG` 读取和清空。
+///
+/// 由 `Listener` 创建并在所有连接处理程序之间共享。克隆 `SlowLog` 是浅层的，只会增加引用计数。
+///
+/// 日志是有界的：记录数达到上限后，每添加一条新记录就丢弃最旧的一条。
+#[derive(Debug, Clone)]
+pub(crate) struct SlowLog {
+    /// 执行时间达到此值的命令会被记录。`None` 表示禁用慢日志。
+    threshold: Option<Duration>,
+
+    /// 保留的最大记录数。
+    max_len: usize,
+
+    /// 与 `Db` 一样，使用 `std::sync::Mutex`，因为在持有锁时不会执行异步操作。
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// 记录，最新的在前。
+    entries: VecDeque<SlowLogEntry>,
+
+    /// 分配给下一条记录的 id。id 单调递增，`SLOWLOG RESET` 之后也不会重用。
+    next_id: u64,
+}
+
+/// 慢日志中的一条记录。
+#[derive(Debug, Clone)]
+pub(crate) struct SlowLogEntry {
+    /// 记录的唯一 id。
+    pub(crate) id: u64,
+
+    /// 命令开始执行的 Unix 时间戳，单位为秒。
+    pub(crate) timestamp: u64,
+
+    /// 命令的执行时间。
+    pub(crate) duration: Duration,
+
+    /// 命令名和参数，按 `MAX_ARGS` 和 `MAX_ARG_LEN` 截断。
+    pub(crate) args: Vec<Bytes>,
+
+    /// 执行命令的客户端的地址。
+    pub(crate) addr: SocketAddr,
+
+    /// 执行命令的客户端的名称（如果已设置）。
+    pub(crate) name: Option<String>,
+}
+
+impl SlowLog {
+    /// 创建一个空的慢日志，记录执行时间达到 `threshold` 的命令，最多保留 `max_len` 条记录。
+    pub(crate) fn new(threshold: Option<Duration>, max_len: usize) -> SlowLog {
+        SlowLog {
+            threshold,
+            max_len,
+            inner: Arc::new(Mutex::new(Inner::default())),
+        }
+    }
+
+    /// 如果慢日志已启用，则返回 `true`。禁用时连接处理程序不需要保留命令的参数。
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.threshold.is_some() && self.max_len > 0
+    }
+
+    /// 如果执行时间为 `duration` 的命令应被记录，则返回 `true`。
+    pub(crate) fn is_slow(&self, duration: Duration) -> bool {
+        self.max_len > 0
+            && self
+                .threshold
+                .is_some_and(|threshold| duration >= threshold)
+    }
+
+    /// 记录刚刚执行完的命令 `args`，其执行时间为 `duration`。`addr` 和 `name` 标识执行命令的客户端。
+    ///
+    /// 调用者应先通过 `is_slow` 检查是否需要记录。
+    pub(crate) fn record(
+        &self,
+        duration: Duration,
+        args: Vec<Bytes>,
+        addr: SocketAddr,
+        name: Option<String>,
+    ) {
+        let started_at = SystemTime::now() - duration;
+        let mut inner = self.inner.lock().unwrap();
+
+        let id = inner.next_id;
+        inner.next_id += 1;
+
+        inner.entries.push_front(SlowLogEntry {
+            id,
+            timestamp: started_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            duration,
+            args: truncate_args(args),
+            addr,
+            name,
+        });
+        inner.entries.truncate(self.max_len);
+    }
+
+    /// 返回最新的 `count` 条记录，最新的在前。
+    pub(crate) fn get(&self, count: usize) -> Vec<SlowLogEntry> {
+        let inner = self.inner.lock().unwrap();
+        inner.entries.iter().take(count).cloned().collect()
+    }
+
+    /// 返回当前的记录数。
+    pub(crate) fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// 删除所有记录。
+    pub(crate) fn reset(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+}
+
+/// 返回命令帧 `frame` 中的命令名和参数，供 `SlowLog::record` 使用。
+///
+/// 克隆 `Bytes` 是浅层的，因此不会复制参数的内容。非字符串的元素（客户端不应发送）用其 `Display` 表示代替。
+pub(crate) fn command_args(frame: &Frame) -> Vec<Bytes> {
+    match frame {
+        Frame::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Frame::Bulk(data) => data.clone(),
+                Frame::Simple(s) => Bytes::from(s.clone()),
+                other => Bytes::from(other.to_string()),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 与 Redis 一样截断命令参数，使巨大的命令不会让慢日志占用过多内存。
+///
+/// 超过 `MAX_ARGS` 个参数时，最后一个保留的参数被替换为 `... (N more arguments)`；超过 `MAX_ARG_LEN` 字节的参数被截断并追加 `... (N more bytes)`。
+fn truncate_args(mut args: Vec<Bytes>) -> Vec<Bytes> {
+    if args.len() > MAX_ARGS {
+        let more = args.len() - MAX_ARGS + 1;
+        args.truncate(MAX_ARGS - 1);
+        args.push(Bytes::from(format!("... ({} more arguments)", more)));
+    }
+
+    for arg in &mut args {
+        if arg.len() > MAX_ARG_LEN {
+            let more = format!("... ({} more bytes)", arg.len() - MAX_ARG_LEN);
+
+            let mut truncated = BytesMut::with_capacity(MAX_ARG_LEN + more.len());
+            truncated.put_slice(&arg[..MAX_ARG_LEN]);
+            truncated.put_slice(more.as_bytes());
+            *arg = truncated.freeze();
+        }
+    }
+
+    args
+}
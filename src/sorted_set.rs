@@ -0,0 +1,161 @@
+use bytes::Bytes;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::mem;
+use std::ops::Bound;
+
+/// 有序集合：每个成员关联一个 `f64` 分数，成员按分数排序。
+///
+/// 分数相同的成员按字节的字典序排序，这与 Redis 一致。
+///
+/// 成员同时存储在两个结构中：`scores` 用于按成员查找分数，`ordered` 用于按顺序迭代。两者始终包含相同的成员。
+#[derive(Debug, Default)]
+pub(crate) struct SortedSet {
+    /// 成员到分数的映射。
+    scores: HashMap<Bytes, f64>,
+
+    /// 按 `(分数, 成员)` 排序的成员。克隆 `Bytes` 是浅层的，因此成员数据不会被复制两次。
+    ordered: BTreeSet<(Score, Bytes)>,
+
+    /// 所有成员的 `member_memory_usage` 之和，随插入和删除更新，因此估算集合的内存使用量不需要遍历成员。
+    memory: usize,
+}
+
+/// 具有全序的 `f64` 包装器，以便分数可以用作 `BTreeSet` 的键。
+///
+/// `NaN` 永远不会被存储：调用者在插入之前拒绝它。
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl SortedSet {
+    /// 创建一个新的空有序集合。
+    pub(crate) fn new() -> SortedSet {
+        SortedSet::default()
+    }
+
+    /// 返回成员的数量。
+    pub(crate) fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// 返回所有成员使用的估算内存字节数，参见 `member_memory_usage`。
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.memory
+    }
+
+    /// 以分数 `score` 插入 `member`。如果成员已存在，则更新其分数。
+    ///
+    /// 如果成员是新添加的，则返回 `true`。
+    pub(crate) fn insert(&mut self, member: Bytes, score: f64) -> bool {
+        debug_assert!(!score.is_nan());
+
+        // 将 `-0.0` 规范化为 `0.0`，以便两者排序相同。
+        let score = score + 0.0;
+
+        match self.scores.insert(member.clone(), score) {
+            Some(prev) => {
+                self.ordered.remove(&(Score(prev), member.clone()));
+                self.ordered.insert((Score(score), member));
+                false
+            }
+            None => {
+                self.memory += member_memory_usage(&member);
+                self.ordered.insert((Score(score), member));
+                true
+            }
+        }
+    }
+
+    /// 删除 `member`。如果成员存在，则返回 `true`。
+    pub(crate) fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.memory -= member_memory_usage(member);
+                self.ordered
+                    .remove(&(Score(score), Bytes::copy_from_slice(member)));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 返回成员的分数，如果成员不存在，则返回 `None`。
+    pub(crate) fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// 返回成员按分数升序排列的排名（从 0 开始），如果成员不存在，则返回 `None`。
+    ///
+    /// `BTreeSet` 不跟踪排名，因此这是 O(N) 的。
+    pub(crate) fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+
+        self.ordered
+            .iter()
+            .position(|(s, m)| s.0 == score && m == member)
+    }
+
+    /// 按分数升序迭代 `(成员, 分数)`。
+    pub(crate) fn iter(&self) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// 按分数升序迭代分数在 `min` 和 `max` 之间的 `(成员, 分数)`。
+    ///
+    /// 迭代从 `BTreeSet` 中第一个可能匹配的位置开始，因此跳过较小的分数不需要遍历它们。
+    pub(crate) fn range_by_score(
+        &self,
+        min: Bound<f64>,
+        max: Bound<f64>,
+    ) -> impl Iterator<Item = (&Bytes, f64)> {
+        // 空成员在所有具有相同分数的成员之前排序，因此 `(min, "")` 是分数为 `min` 的第一个可能的元素。
+        let start = match min {
+            Bound::Included(min) | Bound::Excluded(min) => {
+                Bound::Included((Score(min + 0.0), Bytes::new()))
+            }
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        self.ordered
+            .range((start, Bound::Unbounded))
+            .map(|(score, member)| (member, score.0))
+            .skip_while(move |(_, score)| matches!(min, Bound::Excluded(min) if *score <= min))
+            .take_while(move |(_, score)| match max {
+                Bound::Included(max) => *score <= max,
+                Bound::Excluded(max) => *score < max,
+                Bound::Unbounded => true,
+            })
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Score) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Score) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Score) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// 估算有序集合中一个成员使用的内存字节数。
+///
+/// 每个成员在 `scores` 和 `ordered` 中各有一个句柄和一个分数，成员数据本身是共享的。
+pub(crate) fn member_memory_usage(member: &[u8]) -> usize {
+    member.len() + 2 * mem::size_of::<(Bytes, f64)>()
+}
+
+/// 将分数格式化为回复中使用的文本形式，例如 `1`、`1.5` 或 `inf`。
+pub(crate) fn format_score(score: f64) -> Bytes {
+    Bytes::from(score.to_string())
+}
@@ -0,0 +1,160 @@
+use crate::cmd::COMMAND_TABLE;
+use crate::connection::IoCounts;
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+
+/// 服务器范围的统计信息，由 `INFO` 和指标端点报告。
+///
+/// 由 `Listener` 创建并在所有连接处理程序之间共享。克隆 `Stats` 是浅层的，只会增加引用计数。
+///
+/// 所有计数器只会递增，并且不保护其他数据，因此 `Relaxed` 排序就足够了。
+#[derive(Debug, Clone)]
+pub(crate) struct Stats {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// 服务器启动的时刻。
+    started_at: Instant,
+
+    /// 自服务器启动以来处理的命令总数。
+    total_commands: AtomicU64,
+
+    /// 每个命令被处理的次数，按命令名索引。
+    ///
+    /// 映射在创建时根据 `COMMAND_TABLE` 构建，之后只有计数器会被修改，因此不需要锁。使用 `BTreeMap` 使报告按命令名排序。
+    commands: BTreeMap<&'static str, AtomicU64>,
+
+    /// 接受的连接总数。
+    connections_accepted: AtomicU64,
+
+    /// 当前活动的连接数。在连接处理程序的 `Session` 创建时递增，在其被丢弃时递减。
+    active_connections: AtomicUsize,
+
+    /// 从客户端读取的字节总数。
+    net_input_bytes: AtomicU64,
+
+    /// 写入客户端的字节总数。
+    net_output_bytes: AtomicU64,
+
+    /// 发送给客户端的错误回复总数。
+    error_replies: AtomicU64,
+}
+
+impl Stats {
+    /// 创建一个新的 `Stats`，并将当前时刻记录为服务器的启动时间。
+    pub(crate) fn new() -> Stats {
+        Stats {
+            inner: Arc::new(Inner {
+                started_at: Instant::now(),
+                total_commands: AtomicU64::new(0),
+                commands: COMMAND_TABLE
+                    .iter()
+                    .map(|spec| (spec.name, AtomicU64::new(0)))
+                    .collect(),
+                connections_accepted: AtomicU64::new(0),
+                active_connections: AtomicUsize::new(0),
+                net_input_bytes: AtomicU64::new(0),
+                net_output_bytes: AtomicU64::new(0),
+                error_replies: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// 返回自服务器启动以来经过的时间。
+    pub(crate) fn uptime(&self) -> Duration {
+        self.inner.started_at.elapsed()
+    }
+
+    /// 记录已处理名为 `name` 的命令。
+    ///
+    /// 不在 `COMMAND_TABLE` 中的命令（例如未知命令）只计入总数。
+    pub(crate) fn incr_commands(&self, name: &str) {
+        self.inner.total_commands.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(count) = self.inner.commands.get(name) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 返回自服务器启动以来处理的命令总数。
+    pub(crate) fn total_commands(&self) -> u64 {
+        self.inner.total_commands.load(Ordering::Relaxed)
+    }
+
+    /// 按命令名排序，迭代至少被处理过一次的命令及其处理次数。
+    pub(crate) fn commands(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        self.inner
+            .commands
+            .iter()
+            .map(|(name, count)| (*name, count.load(Ordering::Relaxed)))
+            .filter(|(_, count)| *count > 0)
+    }
+
+    /// 记录已接受一个连接。
+    pub(crate) fn incr_connections(&self) {
+        self.inner
+            .connections_accepted
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 返回接受的连接总数。
+    pub(crate) fn connections_accepted(&self) -> u64 {
+        self.inner.connections_accepted.load(Ordering::Relaxed)
+    }
+
+    /// 记录一个连接处理程序已开始。
+    pub(crate) fn incr_active_connections(&self) {
+        self.inner
+            .active_connections
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一个连接处理程序已结束。
+    pub(crate) fn decr_active_connections(&self) {
+        self.inner
+            .active_connections
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// 返回当前活动的连接数。
+    ///
+    /// 与 `Clients` 注册表不同，读取此值不需要获取锁。
+    pub(crate) fn active_connections(&self) -> usize {
+        self.inner.active_connections.load(Ordering::Relaxed)
+    }
+
+    /// 将连接报告的 I/O 计数累加到总数中。
+    pub(crate) fn record_io(&self, counts: IoCounts) {
+        let inner = &self.inner;
+
+        inner
+            .net_input_bytes
+            .fetch_add(counts.bytes_read, Ordering::Relaxed);
+        inner
+            .net_output_bytes
+            .fetch_add(counts.bytes_written, Ordering::Relaxed);
+        inner
+            .error_replies
+            .fetch_add(counts.error_replies, Ordering::Relaxed);
+    }
+
+    /// 返回从客户端读取的字节总数。
+    pub(crate) fn net_input_bytes(&self) -> u64 {
+        self.inner.net_input_bytes.load(Ordering::Relaxed)
+    }
+
+    /// 返回写入客户端的字节总数。
+    pub(crate) fn net_output_bytes(&self) -> u64 {
+        self.inner.net_output_bytes.load(Ordering::Relaxed)
+    }
+
+    /// 返回发送给客户端的错误回复总数。
+    pub(crate) fn error_replies(&self) -> u64 {
+        self.inner.error_replies.load(Ordering::Relaxed)
+    }
+}
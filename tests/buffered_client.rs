@@ -1,10 +1,11 @@
 use mini_redis::{
-    clients::{BufferedClient, Client},
+    clients::{BufferConfig, BufferedClient, Client},
     server,
 };
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
+use tokio::time::{self, Duration};
 
 /// A basic "hello world" style test. A server instance is started in a
 /// background task. A client instance is then established and used to intialize
@@ -23,6 +24,75 @@ async fn pool_key_value_get_set() {
     assert_eq!(b"world", &value[..])
 }
 
+/// Commands queued without waiting for their response are all complete once
+/// `flush` returns, even with a single command allowed in flight.
+#[tokio::test]
+async fn queue_set_and_flush() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut client = BufferedClient::with_max_in_flight(client, 1);
+
+    for i in 0..100 {
+        client
+            .queue_set(&format!("key:{}", i), i.to_string().into())
+            .await
+            .unwrap();
+    }
+
+    client.flush().await.unwrap();
+
+    // A separate connection sees every value once flushed.
+    let mut other = Client::connect(addr).await.unwrap();
+    for i in 0..100 {
+        let value = other.get(&format!("key:{}", i)).await.unwrap().unwrap();
+        assert_eq!(i.to_string().as_bytes(), &value[..]);
+    }
+}
+
+/// With keep-alive enabled an idle connection to a live server stays usable.
+#[tokio::test]
+async fn keep_alive_idle_connection() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let config = BufferConfig {
+        keep_alive: Some(Duration::from_millis(10)),
+        ..BufferConfig::default()
+    };
+    let mut client = BufferedClient::with_config(client, config);
+
+    time::sleep(Duration::from_millis(100)).await;
+    assert!(client.is_alive());
+
+    client.set("hello", "world".into()).await.unwrap();
+    let value = client.get("hello").await.unwrap().unwrap();
+    assert_eq!(b"world", &value[..])
+}
+
+/// A keep-alive `PING` that gets no response marks the client dead.
+#[tokio::test]
+async fn keep_alive_detects_unresponsive_server() {
+    // Accept the connection but never respond.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (_socket, _) = listener.accept().await.unwrap();
+        time::sleep(Duration::from_secs(60)).await;
+    });
+
+    let client = Client::connect(addr).await.unwrap();
+    let config = BufferConfig {
+        keep_alive: Some(Duration::from_millis(10)),
+        ..BufferConfig::default()
+    };
+    let mut client = BufferedClient::with_config(client, config);
+
+    time::sleep(Duration::from_millis(100)).await;
+    assert!(!client.is_alive());
+    assert!(client.get("hello").await.is_err());
+}
+
 async fn start_server() -> (SocketAddr, JoinHandle<()>) {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
@@ -1,7 +1,12 @@
-use mini_redis::{clients::Client, server};
+use mini_redis::{
+    clients::{BlockingClient, Client, ClientBuilder},
+    server, Frame,
+};
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
+use tokio_stream::StreamExt;
 
 /// A PING PONG test without message provided.
 /// It should return "PONG".
@@ -39,6 +44,51 @@ async fn key_value_get_set() {
     assert_eq!(b"world", &value[..])
 }
 
+/// Commands without a dedicated method can be sent with `execute`, which
+/// returns the raw response frame. Error responses are returned as `Err`.
+#[tokio::test]
+async fn execute_arbitrary_command() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let response = client
+        .execute(vec!["SET".into(), "hello".into(), "world".into()])
+        .await
+        .unwrap();
+    assert_eq!(response, "OK");
+
+    let response = client
+        .execute(vec!["GET".into(), "hello".into()])
+        .await
+        .unwrap();
+    assert!(matches!(response, Frame::Bulk(value) if value == "world"));
+
+    let err = client.execute(vec!["FOO".into()]).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "ERR unknown command 'FOO', with args beginning with: "
+    );
+}
+
+/// Key positions are looked up in the server's command table.
+#[tokio::test]
+async fn key_positions_from_command_table() {
+    let (addr, _) = start_server().await;
+    let mut client = Client::connect(addr).await.unwrap();
+
+    let args = vec!["hello".into(), "world".into()];
+    assert_eq!(client.key_positions("SET", &args).await.unwrap(), vec![0]);
+
+    let args = vec!["a".into(), "b".into(), "c".into()];
+    assert_eq!(
+        client.key_positions("touch", &args).await.unwrap(),
+        vec![0, 1, 2]
+    );
+
+    assert!(client.key_positions("ping", &[]).await.unwrap().is_empty());
+    assert!(client.key_positions("foo", &[]).await.is_err());
+}
+
 /// similar to the "hello world" style test, But this time
 /// a single channel subscription will be tested instead
 #[tokio::test]
@@ -88,6 +138,38 @@ async fn receive_message_multiple_subscribed_channels() {
     assert_eq!(b"howdy?", &message2.content[..])
 }
 
+/// test that a subscriber can be polled as a `Stream` from `select!` and
+/// still be used to subscribe to more channels between messages
+#[tokio::test]
+async fn subscriber_is_a_stream() {
+    let (addr, _) = start_server().await;
+
+    let client = Client::connect(addr).await.unwrap();
+    let mut subscriber = client.subscribe(vec!["hello".into()]).await.unwrap();
+
+    // Nothing has been published yet, so the timeout wins.
+    tokio::select! {
+        message = subscriber.next() => panic!("unexpected message {:?}", message),
+        _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+    }
+
+    subscriber.subscribe(&["world".into()]).await.unwrap();
+
+    let mut publisher = Client::connect(addr).await.unwrap();
+    publisher.publish("hello", "one".into()).await.unwrap();
+    publisher.publish("world", "two".into()).await.unwrap();
+
+    let messages: Vec<_> = (&mut subscriber)
+        .take(2)
+        .map(|message| message.unwrap())
+        .collect()
+        .await;
+    assert_eq!("hello", &messages[0].channel);
+    assert_eq!(b"one", &messages[0].content[..]);
+    assert_eq!("world", &messages[1].channel);
+    assert_eq!(b"two", &messages[1].content[..]);
+}
+
 /// test that a client accurately removes its own subscribed chanel list
 /// when unsubscribing to all subscribed channels by submitting an empty vec
 #[tokio::test]
@@ -104,6 +186,78 @@ async fn unsubscribes_from_channels() {
     assert_eq!(subscriber.get_subscribed().len(), 0);
 }
 
+/// The blocking client mirrors the async one, including pub/sub. The server
+/// runs on its own runtime as the blocking client must not be used from within
+/// one.
+#[test]
+fn blocking_client_ping_and_subscribe() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let (addr, _) = rt.block_on(start_server());
+
+    let mut client = BlockingClient::connect(addr).unwrap();
+    let pong = client.ping(None).unwrap();
+    assert_eq!(b"PONG", &pong[..]);
+
+    let mut subscriber = client.subscribe(vec!["hello".into()]).unwrap();
+    assert_eq!(subscriber.get_subscribed(), &["hello".to_string()]);
+
+    let mut publisher = BlockingClient::connect(addr).unwrap();
+    publisher.publish("hello", "world".into()).unwrap();
+
+    let message = subscriber.next_message().unwrap().unwrap();
+    assert_eq!("hello", &message.channel);
+    assert_eq!(b"world", &message.content[..]);
+}
+
+/// A client configured through the builder names its connection and applies
+/// its options.
+#[tokio::test]
+async fn builder_names_connection() {
+    let (addr, _) = start_server().await;
+
+    let mut client = ClientBuilder::new(addr)
+        .read_timeout(Duration::from_secs(5))
+        .write_timeout(Duration::from_secs(5))
+        .write_capacity(64)
+        .name("worker-1")
+        .build()
+        .await
+        .unwrap();
+
+    let name = client
+        .execute(vec!["CLIENT".into(), "GETNAME".into()])
+        .await
+        .unwrap();
+    assert_eq!(name, "worker-1");
+
+    // The server rejects invalid names, which fails the build.
+    let result = Client::builder(addr).name("bad name").build().await;
+    assert!(result.is_err());
+}
+
+/// A request which gets no response within the read timeout fails instead of
+/// waiting forever.
+#[tokio::test]
+async fn builder_read_timeout() {
+    // A server which accepts the connection but never replies.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (_socket, _) = listener.accept().await.unwrap();
+        std::future::pending::<()>().await;
+    });
+
+    let mut client = ClientBuilder::new(addr)
+        .read_timeout(Duration::from_millis(50))
+        .build()
+        .await
+        .unwrap();
+
+    let err = client.get("foo").await.unwrap_err();
+    let err = err.downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
 async fn start_server() -> (SocketAddr, JoinHandle<()>) {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
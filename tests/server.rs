@@ -1,4 +1,4 @@
-use mini_redis::server;
+use mini_redis::{clients::Client, server, Connection, ConnectionError, Frame};
 
 use std::net::SocketAddr;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -346,11 +346,12 @@ async fn send_error_unknown_command() {
         .await
         .unwrap();
 
-    let mut response = [0; 28];
+    let expected = b"-ERR unknown command 'FOO', with args beginning with: 'hello' \r\n";
+    let mut response = [0; 64];
 
     stream.read_exact(&mut response).await.unwrap();
 
-    assert_eq!(b"-ERR unknown command \'foo\'\r\n", &response);
+    assert_eq!(&expected[..], &response[..]);
 }
 
 // In this case we test that server Responds with an Error message if a client
@@ -381,20 +382,3135 @@ async fn send_error_get_set_after_subscribe() {
         .await
         .unwrap();
 
-    let mut response = [0; 28];
+    let expected = b"-ERR Can't execute 'set': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context\r\n";
+    let mut response = [0; 116];
 
     stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"-ERR unknown command \'set\'\r\n", &response);
+    assert_eq!(&expected[..], &response[..]);
 
     stream
         .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
         .await
         .unwrap();
 
-    let mut response = [0; 28];
+    let expected = b"-ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context\r\n";
+    let mut response = [0; 116];
 
     stream.read_exact(&mut response).await.unwrap();
-    assert_eq!(b"-ERR unknown command \'get\'\r\n", &response);
+    assert_eq!(&expected[..], &response[..]);
+}
+
+#[tokio::test]
+async fn random_key() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // The keyspace is empty, nil is returned
+    stream
+        .write_all(b"*1\r\n$9\r\nRANDOMKEY\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+
+    // Set a key
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // The only key is returned
+    stream
+        .write_all(b"*1\r\n$9\r\nRANDOMKEY\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nhello\r\n", &response);
+}
+
+#[tokio::test]
+async fn touch_counts_existing_keys() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Set a key
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Only `hello` exists; it is counted once per occurrence
+    stream
+        .write_all(b"*4\r\n$5\r\nTOUCH\r\n$5\r\nhello\r\n$3\r\nfoo\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+}
+
+#[tokio::test]
+async fn object_encoding() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Set an integer and a non-integer value
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$3\r\nnum\r\n$3\r\n123\r\n")
+        .await
+        .unwrap();
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 10];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n+OK\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$6\r\nOBJECT\r\n$8\r\nENCODING\r\n$3\r\nnum\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 6];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+int\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$6\r\nOBJECT\r\n$8\r\nencoding\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 6];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+raw\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$6\r\nOBJECT\r\n$8\r\nREFCOUNT\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    // Missing keys are nil
+    stream
+        .write_all(b"*3\r\n$6\r\nOBJECT\r\n$8\r\nENCODING\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
+#[tokio::test]
+async fn wait_acknowledges_zero_replicas() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$4\r\nWAIT\r\n$1\r\n1\r\n$3\r\n100\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+}
+
+#[tokio::test]
+async fn debug_sleep() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$5\r\nDEBUG\r\n$5\r\nSLEEP\r\n$3\r\n0.5\r\n")
+        .await
+        .unwrap();
+
+    // No response is sent before the sleep has elapsed
+    let mut response = [0; 5];
+    time::timeout(Duration::from_millis(400), stream.read(&mut response))
+        .await
+        .unwrap_err();
+
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+}
+
+/// `DEBUG SLEEP` rejects durations which cannot be represented, and the
+/// connection stays usable.
+#[tokio::test]
+async fn debug_sleep_invalid_duration() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for secs in &["1e30", "-1"] {
+        let response = send(&mut connection, &["DEBUG", "SLEEP", secs]).await;
+        assert!(
+            matches!(&response, Frame::Error(err) if err.contains("invalid sleep duration")),
+            "{:?}",
+            response
+        );
+    }
+
+    assert_eq!(send(&mut connection, &["PING"]).await, "PONG");
+}
+
+#[tokio::test]
+async fn client_id_and_name() {
+    let addr = start_server().await;
+
+    let mut first = TcpStream::connect(addr).await.unwrap();
+    let mut second = TcpStream::connect(addr).await.unwrap();
+
+    // Ids are assigned in increasing order
+    first
+        .write_all(b"*2\r\n$6\r\nCLIENT\r\n$2\r\nID\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    first.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    second
+        .write_all(b"*2\r\n$6\r\nCLIENT\r\n$2\r\nID\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    second.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+
+    // No name has been set yet
+    first
+        .write_all(b"*2\r\n$6\r\nCLIENT\r\n$7\r\nGETNAME\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    first.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+
+    first
+        .write_all(b"*3\r\n$6\r\nCLIENT\r\n$7\r\nSETNAME\r\n$6\r\nworker\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    first.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    first
+        .write_all(b"*2\r\n$6\r\nCLIENT\r\n$7\r\nGETNAME\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 12];
+    first.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$6\r\nworker\r\n", &response);
+
+    // Names with spaces are rejected
+    first
+        .write_all(b"*3\r\n$6\r\nCLIENT\r\n$7\r\nSETNAME\r\n$3\r\na b\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 1];
+    first.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"-", &response);
+}
+
+#[tokio::test]
+async fn client_list() {
+    let addr = start_server().await;
+
+    let mut first = TcpStream::connect(addr).await.unwrap();
+
+    first
+        .write_all(b"*3\r\n$6\r\nCLIENT\r\n$7\r\nSETNAME\r\n$6\r\nworker\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 5];
+    first.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // The listing is a bulk string of unknown length, so use `Connection` to
+    // read the frame.
+    let mut second = Connection::new(TcpStream::connect(addr).await.unwrap());
+    second
+        .write_frame(&Frame::Array(vec![
+            Frame::Bulk("CLIENT".into()),
+            Frame::Bulk("LIST".into()),
+        ]))
+        .await
+        .unwrap();
+
+    let list = match second.read_frame().await.unwrap().unwrap() {
+        Frame::Bulk(list) => String::from_utf8(list.to_vec()).unwrap(),
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+
+    let lines: Vec<_> = list.lines().collect();
+    assert_eq!(2, lines.len());
+    assert!(lines[0].starts_with("id=1 addr=127.0.0.1:"));
+    assert!(lines[0].ends_with(" name=worker age=0 idle=0 cmd=client tot-cmds=1"));
+    assert!(lines[1].starts_with("id=2 addr=127.0.0.1:"));
+    assert!(lines[1].ends_with(" name= age=0 idle=0 cmd=client tot-cmds=0"));
+
+    // Once the first connection closes it is removed from the registry
+    drop(first);
+    time::sleep(Duration::from_millis(100)).await;
+
+    second
+        .write_frame(&Frame::Array(vec![
+            Frame::Bulk("CLIENT".into()),
+            Frame::Bulk("LIST".into()),
+        ]))
+        .await
+        .unwrap();
+
+    let list = match second.read_frame().await.unwrap().unwrap() {
+        Frame::Bulk(list) => String::from_utf8(list.to_vec()).unwrap(),
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+
+    let lines: Vec<_> = list.lines().collect();
+    assert_eq!(1, lines.len());
+    assert!(lines[0].starts_with("id=2 "));
+}
+
+#[tokio::test]
+async fn client_kill() {
+    let addr = start_server().await;
+
+    // The first connection is idle, the second one is subscribed
+    let mut idle = TcpStream::connect(addr).await.unwrap();
+    idle.write_all(b"*2\r\n$6\r\nCLIENT\r\n$2\r\nID\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    idle.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    let mut sub = TcpStream::connect(addr).await.unwrap();
+    sub.write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 34];
+    sub.read_exact(&mut response).await.unwrap();
+
+    let mut admin = TcpStream::connect(addr).await.unwrap();
+
+    // The calling connection is skipped by default
+    admin
+        .write_all(b"*4\r\n$6\r\nCLIENT\r\n$4\r\nKILL\r\n$2\r\nID\r\n$1\r\n3\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    admin.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    admin
+        .write_all(b"*4\r\n$6\r\nCLIENT\r\n$4\r\nKILL\r\n$2\r\nID\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    admin.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    admin
+        .write_all(b"*4\r\n$6\r\nCLIENT\r\n$4\r\nKILL\r\n$2\r\nID\r\n$1\r\n2\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 4];
+    admin.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    // Both killed connections are closed by the server
+    let mut response = [0; 1];
+    assert_eq!(0, idle.read(&mut response).await.unwrap());
+    assert_eq!(0, sub.read(&mut response).await.unwrap());
+}
+
+#[tokio::test]
+async fn command_table() {
+    let addr = start_server().await;
+
+    // The reply contains nested arrays and negative integers, so use
+    // `Connection` to read it.
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    connection
+        .write_frame(&Frame::Array(vec![Frame::Bulk("COMMAND".into())]))
+        .await
+        .unwrap();
+
+    let entries = match connection.read_frame().await.unwrap().unwrap() {
+        Frame::Array(entries) => entries,
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+
+    // The entry for `GET` describes its arity, flags and key positions
+    match &entries[0] {
+        Frame::Array(fields) => {
+            let fields: Vec<_> = fields.iter().map(|field| field.to_string()).collect();
+            assert_eq!(vec!["get", "2", "readonly fast", "1", "1", "1"], fields);
+        }
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    let set = entries
+        .iter()
+        .find_map(|entry| match entry {
+            Frame::Array(fields) if fields[0] == "set" => Some(fields),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(6, set.len());
+    assert_eq!("-3", set[1].to_string());
+
+    // `COMMAND COUNT` agrees with the table
+    connection
+        .write_frame(&Frame::Array(vec![
+            Frame::Bulk("COMMAND".into()),
+            Frame::Bulk("COUNT".into()),
+        ]))
+        .await
+        .unwrap();
+
+    match connection.read_frame().await.unwrap().unwrap() {
+        Frame::Integer(count) => assert_eq!(entries.len() as i64, count),
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+
+    // `COMMAND DOCS` replies with a flattened map
+    connection
+        .write_frame(&Frame::Array(vec![
+            Frame::Bulk("COMMAND".into()),
+            Frame::Bulk("DOCS".into()),
+            Frame::Bulk("get".into()),
+        ]))
+        .await
+        .unwrap();
+
+    match connection.read_frame().await.unwrap().unwrap() {
+        Frame::Array(docs) => {
+            assert_eq!(2, docs.len());
+            assert_eq!("get", docs[0].to_string());
+        }
+        frame => panic!("unexpected frame: {:?}", frame),
+    }
+}
+
+#[tokio::test]
+async fn info_sections() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    connection
+        .write_frame(&Frame::Array(vec![
+            Frame::Bulk("SET".into()),
+            Frame::Bulk("hello".into()),
+            Frame::Bulk("world".into()),
+        ]))
+        .await
+        .unwrap();
+    assert_eq!(connection.read_frame().await.unwrap().unwrap(), "OK");
+
+    // Without an argument every section is returned
+    connection
+        .write_frame(&Frame::Array(vec![Frame::Bulk("INFO".into())]))
+        .await
+        .unwrap();
+
+    let info = match connection.read_frame().await.unwrap().unwrap() {
+        Frame::Bulk(info) => String::from_utf8(info.to_vec()).unwrap(),
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+
+    for header in &["# Server", "# Clients", "# Memory", "# Stats", "# Keyspace"] {
+        assert!(info.contains(header), "missing {} in {:?}", header, info);
+    }
+    assert!(info.contains("connected_clients:1\r\n"));
+    // The estimate includes overhead, so it is at least the size of the data.
+    let used_memory = info
+        .lines()
+        .find_map(|line| line.strip_prefix("used_memory:"))
+        .map(|used| used.parse::<usize>().unwrap())
+        .unwrap();
+    assert!(used_memory >= "hello".len() + "world".len());
+    assert!(info.contains("total_commands_processed:2\r\n"));
+    assert!(info.contains("pubsub_channels:0\r\n"));
+    assert!(info.contains("total_connections_received:1\r\n"));
+    assert!(!info.contains("# Commandstats"));
+    assert!(info.contains("db0:keys=1,expires=0\r\n"));
+
+    // A single section can be requested
+    connection
+        .write_frame(&Frame::Array(vec![
+            Frame::Bulk("INFO".into()),
+            Frame::Bulk("KEYSPACE".into()),
+        ]))
+        .await
+        .unwrap();
+
+    let info = match connection.read_frame().await.unwrap().unwrap() {
+        Frame::Bulk(info) => String::from_utf8(info.to_vec()).unwrap(),
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+    assert_eq!("# Keyspace\r\ndb0:keys=1,expires=0\r\n", info);
+
+    // `commandstats` is only returned on request
+    connection
+        .write_frame(&Frame::Array(vec![
+            Frame::Bulk("INFO".into()),
+            Frame::Bulk("commandstats".into()),
+        ]))
+        .await
+        .unwrap();
+
+    let info = match connection.read_frame().await.unwrap().unwrap() {
+        Frame::Bulk(info) => String::from_utf8(info.to_vec()).unwrap(),
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+    assert_eq!(
+        "# Commandstats\r\ncmdstat_info:calls=3\r\ncmdstat_set:calls=1\r\n",
+        info
+    );
+}
+
+/// Commands are counted under their name in the command table, including
+/// those whose type name differs, such as `PUBLISH`.
+#[tokio::test]
+async fn info_commandstats_counts_publish() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(&mut connection, &["PUBLISH", "channel", "message"]).await;
+    assert_eq!(response.to_string(), "0");
+
+    let info = send(&mut connection, &["INFO", "commandstats"]).await;
+    assert_eq!(
+        info.to_string(),
+        "# Commandstats\r\ncmdstat_info:calls=1\r\ncmdstat_publish:calls=1\r\n"
+    );
+}
+
+#[tokio::test]
+async fn reset_leaves_subscribe_mode() {
+    let addr = start_server().await;
+
+    let mut publisher = TcpStream::connect(addr).await.unwrap();
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+
+    // Outside of subscribe mode, `RESET` is simply acknowledged
+    subscriber
+        .write_all(b"*1\r\n$5\r\nRESET\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 8];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+RESET\r\n", &response);
+
+    subscriber
+        .write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 34];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+
+    // `RESET` drops the subscription and leaves subscribe mode
+    subscriber
+        .write_all(b"*1\r\n$5\r\nRESET\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 8];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+RESET\r\n", &response);
+
+    publisher
+        .write_all(b"*3\r\n$7\r\nPUBLISH\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    publisher.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    // Regular commands are accepted again
+    subscriber
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    subscriber.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$-1\r\n", &response);
+}
+
+#[tokio::test]
+async fn bit_operations() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // Setting bit 7 creates a one byte string and returns the old bit
+    stream
+        .write_all(b"*4\r\n$6\r\nSETBIT\r\n$4\r\nbits\r\n$1\r\n7\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    // Setting bit 9 grows the string to two bytes
+    stream
+        .write_all(b"*4\r\n$6\r\nSETBIT\r\n$4\r\nbits\r\n$1\r\n9\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$4\r\nbits\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 8];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$2\r\n\x01\x40\r\n", &response);
+
+    // Setting an already set bit returns `1`
+    stream
+        .write_all(b"*4\r\n$6\r\nSETBIT\r\n$4\r\nbits\r\n$1\r\n7\r\n$1\r\n1\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    stream
+        .write_all(b"*3\r\n$6\r\nGETBIT\r\n$4\r\nbits\r\n$1\r\n9\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    // Bits past the end of the string are `0`
+    stream
+        .write_all(b"*3\r\n$6\r\nGETBIT\r\n$4\r\nbits\r\n$3\r\n100\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$8\r\nBITCOUNT\r\n$4\r\nbits\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":2\r\n", &response);
+
+    // Count only the last byte
+    stream
+        .write_all(b"*4\r\n$8\r\nBITCOUNT\r\n$4\r\nbits\r\n$2\r\n-1\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":1\r\n", &response);
+
+    // A backwards range is empty
+    stream
+        .write_all(b"*4\r\n$8\r\nBITCOUNT\r\n$4\r\nbits\r\n$1\r\n1\r\n$1\r\n0\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+}
+
+#[tokio::test]
+async fn get_and_set_range() {
+    let addr = start_server().await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$11\r\nHello World\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+
+    // Each case is `(start, end, expected substring)`
+    let cases: &[(&str, &str, &str)] = &[
+        ("0", "4", "Hello"),
+        ("-5", "-1", "World"),
+        ("0", "-1", "Hello World"),
+        ("6", "100", "World"),
+        ("-100", "4", "Hello"),
+        ("5", "3", ""),
+        ("-1", "-5", ""),
+        ("100", "200", ""),
+        ("-100", "-50", "H"),
+    ];
+
+    for (start, end, expected) in cases {
+        let cmd = format!(
+            "*4\r\n$8\r\nGETRANGE\r\n$5\r\nhello\r\n${}\r\n{}\r\n${}\r\n{}\r\n",
+            start.len(),
+            start,
+            end.len(),
+            end
+        );
+        stream.write_all(cmd.as_bytes()).await.unwrap();
+
+        let expected = format!("${}\r\n{}\r\n", expected.len(), expected);
+        let mut response = vec![0; expected.len()];
+        stream.read_exact(&mut response).await.unwrap();
+        assert_eq!(
+            expected.as_bytes(),
+            &response[..],
+            "GETRANGE {} {}",
+            start,
+            end
+        );
+    }
+
+    // Overwrite part of the string
+    stream
+        .write_all(b"*4\r\n$8\r\nSETRANGE\r\n$5\r\nhello\r\n$1\r\n6\r\n$5\r\nRedis\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":11\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 18];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$11\r\nHello Redis\r\n", &response);
+
+    // Writing past the end of a missing key pads with zero bytes
+    stream
+        .write_all(b"*4\r\n$8\r\nSETRANGE\r\n$3\r\nfoo\r\n$1\r\n2\r\n$1\r\nx\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":3\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 9];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$3\r\n\0\0x\r\n", &response);
+
+    // An empty value does not create a missing key
+    stream
+        .write_all(b"*4\r\n$8\r\nSETRANGE\r\n$3\r\nbar\r\n$1\r\n5\r\n$0\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b":0\r\n", &response);
+
+    // GETRANGE on a missing key is an empty string
+    stream
+        .write_all(b"*4\r\n$8\r\nGETRANGE\r\n$3\r\nbar\r\n$1\r\n0\r\n$2\r\n-1\r\n")
+        .await
+        .unwrap();
+
+    let mut response = [0; 6];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$0\r\n\r\n", &response);
+}
+
+#[tokio::test]
+async fn sorted_set() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(
+        &mut connection,
+        &["ZADD", "zset", "2", "b", "1", "a", "2", "c", "-inf", "z"],
+    )
+    .await;
+    assert_eq!("4", response.to_string());
+
+    // Updating a score does not count as an addition
+    let response = send(&mut connection, &["ZADD", "zset", "3", "a", "5", "d"]).await;
+    assert_eq!("1", response.to_string());
+
+    let response = send(&mut connection, &["ZSCORE", "zset", "a"]).await;
+    assert_eq!(response, "3");
+
+    let response = send(&mut connection, &["ZSCORE", "zset", "z"]).await;
+    assert_eq!(response, "-inf");
+
+    let response = send(&mut connection, &["ZSCORE", "zset", "missing"]).await;
+    assert!(matches!(response, Frame::Null));
+
+    // Ties are ordered by member
+    let response = send(&mut connection, &["ZRANGE", "zset", "0", "-1"]).await;
+    assert_eq!("z b c a d", response.to_string());
+
+    let response = send(&mut connection, &["ZRANGE", "zset", "1", "2", "WITHSCORES"]).await;
+    assert_eq!("b 2 c 2", response.to_string());
+
+    let response = send(&mut connection, &["ZRANGE", "zset", "-2", "100"]).await;
+    assert_eq!("a d", response.to_string());
+
+    let response = send(&mut connection, &["ZRANGE", "zset", "3", "1"]).await;
+    assert!(matches!(response, Frame::Array(members) if members.is_empty()));
+
+    let response = send(&mut connection, &["ZRANK", "zset", "c"]).await;
+    assert_eq!("2", response.to_string());
+
+    let response = send(&mut connection, &["ZRANK", "zset", "missing"]).await;
+    assert!(matches!(response, Frame::Null));
+
+    // Sorted set commands reject string values, and vice versa
+    send(&mut connection, &["SET", "string", "value"]).await;
+
+    let response = send(&mut connection, &["ZADD", "string", "1", "a"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+
+    let response = send(&mut connection, &["GET", "zset"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+}
+
+#[tokio::test]
+async fn zrangebyscore_bounds() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(
+        &mut connection,
+        &[
+            "ZADD", "zset", "-inf", "min", "1", "a", "2", "b", "2", "c", "3", "d", "+inf", "max",
+        ],
+    )
+    .await;
+
+    // Each case is `(min, max, expected members)`
+    let cases: &[(&str, &str, &str)] = &[
+        ("1", "2", "a b c"),
+        ("(1", "2", "b c"),
+        ("1", "(2", "a"),
+        ("(1", "(3", "b c"),
+        ("-inf", "+inf", "min a b c d max"),
+        ("(-inf", "(+inf", "a b c d"),
+        ("-inf", "1", "min a"),
+        ("3", "inf", "d max"),
+        ("2", "2", "b c"),
+        ("(2", "2", ""),
+        ("3", "1", ""),
+        ("1.5", "2.5", "b c"),
+    ];
+
+    for (min, max, expected) in cases {
+        let response = send(&mut connection, &["ZRANGEBYSCORE", "zset", min, max]).await;
+        assert_eq!(
+            *expected,
+            response.to_string(),
+            "ZRANGEBYSCORE {} {}",
+            min,
+            max
+        );
+    }
+
+    let response = send(
+        &mut connection,
+        &[
+            "ZRANGEBYSCORE",
+            "zset",
+            "1",
+            "3",
+            "WITHSCORES",
+            "LIMIT",
+            "1",
+            "2",
+        ],
+    )
+    .await;
+    assert_eq!("b 2 c 2", response.to_string());
+
+    // A negative count returns every remaining member
+    let response = send(
+        &mut connection,
+        &["ZRANGEBYSCORE", "zset", "1", "3", "LIMIT", "2", "-1"],
+    )
+    .await;
+    assert_eq!("c d", response.to_string());
+
+    // Removing members returns the number actually removed
+    let response = send(&mut connection, &["ZREM", "zset", "a", "b", "missing"]).await;
+    assert_eq!("2", response.to_string());
+
+    let response = send(&mut connection, &["ZRANGEBYSCORE", "zset", "1", "2"]).await;
+    assert_eq!("c", response.to_string());
+
+    // Removing the last member deletes the key
+    send(&mut connection, &["ZADD", "small", "1", "x"]).await;
+    let response = send(&mut connection, &["ZREM", "small", "x"]).await;
+    assert_eq!("1", response.to_string());
+
+    let response = send(&mut connection, &["TOUCH", "small"]).await;
+    assert_eq!("0", response.to_string());
+}
+
+#[tokio::test]
+async fn incr_by_float() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // A missing key is treated as `0`.
+    let response = send(&mut connection, &["INCRBYFLOAT", "float", "10.5"]).await;
+    assert_eq!(response, "10.5");
+
+    let response = send(&mut connection, &["INCRBYFLOAT", "float", "0.1"]).await;
+    assert_eq!(response, "10.6");
+
+    // Whole results have no trailing zeros, and large ones no exponent.
+    let response = send(&mut connection, &["INCRBYFLOAT", "float", "-0.6"]).await;
+    assert_eq!(response, "10");
+
+    send(&mut connection, &["SET", "float", "5.0e3"]).await;
+    let response = send(&mut connection, &["INCRBYFLOAT", "float", "1e17"]).await;
+    assert_eq!(response, "100000000000005000");
+
+    let response = send(&mut connection, &["GET", "float"]).await;
+    assert_eq!(response, "100000000000005000");
+
+    // Invalid values and non-finite results are rejected, leaving the value
+    // unchanged.
+    send(&mut connection, &["SET", "float", "abc"]).await;
+    let response = send(&mut connection, &["INCRBYFLOAT", "float", "1"]).await;
+    assert!(matches!(response, Frame::Error(err) if err == "ERR value is not a valid float"));
+
+    send(&mut connection, &["SET", "float", "1"]).await;
+    let response = send(&mut connection, &["INCRBYFLOAT", "float", "inf"]).await;
+    assert!(
+        matches!(response, Frame::Error(err) if err == "ERR increment would produce NaN or Infinity")
+    );
+
+    let response = send(&mut connection, &["GET", "float"]).await;
+    assert_eq!(response, "1");
+
+    send(&mut connection, &["ZADD", "zset", "1", "a"]).await;
+    let response = send(&mut connection, &["INCRBYFLOAT", "zset", "1"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+}
+
+#[tokio::test]
+async fn getex_updates_expiration() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // Without an option, `GETEX` behaves like `GET`.
+    send(&mut connection, &["SET", "hello", "world"]).await;
+    let response = send(&mut connection, &["GETEX", "hello"]).await;
+    assert_eq!(response, "world");
+
+    let response = send(&mut connection, &["GETEX", "hello", "EX", "1"]).await;
+    assert_eq!(response, "world");
+
+    time::sleep(Duration::from_secs(1)).await;
+
+    let response = send(&mut connection, &["GET", "hello"]).await;
+    assert!(matches!(response, Frame::Null));
+
+    // `PERSIST` removes the expiration set by `SET`.
+    send(&mut connection, &["SET", "hello", "world", "PX", "500"]).await;
+    let response = send(&mut connection, &["GETEX", "hello", "PERSIST"]).await;
+    assert_eq!(response, "world");
+
+    time::sleep(Duration::from_secs(1)).await;
+
+    let response = send(&mut connection, &["GET", "hello"]).await;
+    assert_eq!(response, "world");
+
+    // A timestamp in the past expires the key right away.
+    let response = send(&mut connection, &["GETEX", "hello", "EXAT", "1"]).await;
+    assert_eq!(response, "world");
+
+    time::sleep(Duration::from_millis(1)).await;
+
+    let response = send(&mut connection, &["GET", "hello"]).await;
+    assert!(matches!(response, Frame::Null));
+
+    let response = send(&mut connection, &["GETEX", "hello", "PERSIST"]).await;
+    assert!(matches!(response, Frame::Null));
+
+    send(&mut connection, &["ZADD", "zset", "1", "a"]).await;
+    let response = send(&mut connection, &["GETEX", "zset", "EX", "1"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn metrics_endpoint() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let metrics = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let metrics_addr = metrics.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        server::run_with_metrics(
+            listener,
+            metrics,
+            server::Config::default(),
+            tokio::signal::ctrl_c(),
+        )
+        .await
+    });
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    send(&mut connection, &["SET", "hello", "world"]).await;
+    send(&mut connection, &["GET", "hello"]).await;
+
+    let mut stream = TcpStream::connect(metrics_addr).await.unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("\nmini_redis_connections_accepted_total 1\n"));
+    assert!(response.contains("\nmini_redis_commands_processed_total 2\n"));
+    assert!(response.contains("\nmini_redis_command_calls_total{command=\"get\"} 1\n"));
+    assert!(response.contains("\nmini_redis_command_calls_total{command=\"set\"} 1\n"));
+}
+
+#[tokio::test]
+async fn wrong_type_errors() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["SET", "string", "value"]).await;
+    send(&mut connection, &["ZADD", "zset", "1", "a"]).await;
+
+    let string_commands: &[&[&str]] = &[
+        &["GET", "zset"],
+        &["GETEX", "zset", "PERSIST"],
+        &["GETRANGE", "zset", "0", "-1"],
+        &["SETRANGE", "zset", "0", "x"],
+        &["GETBIT", "zset", "0"],
+        &["SETBIT", "zset", "0", "1"],
+        &["BITCOUNT", "zset"],
+        &["INCRBYFLOAT", "zset", "1"],
+    ];
+
+    let sorted_set_commands: &[&[&str]] = &[
+        &["ZADD", "string", "1", "a"],
+        &["ZSCORE", "string", "a"],
+        &["ZRANK", "string", "a"],
+        &["ZRANGE", "string", "0", "-1"],
+        &["ZRANGEBYSCORE", "string", "-inf", "+inf"],
+        &["ZREM", "string", "a"],
+    ];
+
+    // Every command replies with the same error and leaves the connection
+    // usable.
+    for args in string_commands.iter().chain(sorted_set_commands) {
+        let response = send(&mut connection, args).await;
+        assert!(
+            matches!(
+                &response,
+                Frame::Error(err) if err == "WRONGTYPE Operation against a key holding the wrong kind of value"
+            ),
+            "{:?} replied {:?}",
+            args,
+            response
+        );
+    }
+
+    // Neither value was overwritten.
+    let response = send(&mut connection, &["GET", "string"]).await;
+    assert_eq!(response, "value");
+
+    let response = send(
+        &mut connection,
+        &["ZRANGE", "zset", "0", "-1", "WITHSCORES"],
+    )
+    .await;
+    assert_eq!("a 1", response.to_string());
+}
+
+/// `Frame::encode` produces the exact wire bytes, so it can be used to build
+/// requests without a `Connection`.
+#[tokio::test]
+async fn frame_encode() {
+    let frame = Frame::Array(vec![
+        Frame::Simple("OK".into()),
+        Frame::Error("ERR oops".into()),
+        Frame::Integer(-42),
+        Frame::Null,
+        Frame::Array(vec![Frame::Bulk("hello".into())]),
+    ]);
+    assert_eq!(
+        &b"*5\r\n+OK\r\n-ERR oops\r\n:-42\r\n$-1\r\n*1\r\n$5\r\nhello\r\n"[..],
+        &frame.encode()[..]
+    );
+
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    let request = Frame::Array(vec![
+        Frame::Bulk("SET".into()),
+        Frame::Bulk("hello".into()),
+        Frame::Bulk("world".into()),
+    ]);
+    stream.write_all(&request.encode()).await.unwrap();
+
+    let mut response = [0; 5];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+OK\r\n", &response);
+}
+
+/// `FrameCodec` turns a socket into a stream of frames and waits for complete
+/// frames before yielding them.
+#[cfg(feature = "codec")]
+#[tokio::test]
+async fn frame_codec() {
+    use bytes::BytesMut;
+    use mini_redis::FrameCodec;
+    use tokio_stream::StreamExt;
+    use tokio_util::codec::{Decoder, Encoder, Framed};
+
+    let mut codec = FrameCodec;
+    let mut buf = BytesMut::from(&b"$5\r\nhel"[..]);
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    buf.extend_from_slice(b"lo\r\n:1\r\n");
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "hello");
+    assert_eq!(&buf[..], b":1\r\n");
+
+    let addr = start_server().await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+
+    let mut request = BytesMut::new();
+    let frame = Frame::Array(vec![Frame::Bulk("PING".into())]);
+    codec.encode(&frame, &mut request).unwrap();
+
+    let mut framed = Framed::new(stream, codec);
+    framed.get_mut().write_all(&request).await.unwrap();
+
+    let response = framed.next().await.unwrap().unwrap();
+    assert_eq!(response, "PONG");
+}
+
+/// A subscriber that falls more than 1024 messages behind skips the oldest
+/// ones and stays subscribed rather than being disconnected.
+#[tokio::test]
+async fn lagged_subscriber_skips_messages() {
+    let addr = start_server().await;
+
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+    send(&mut subscriber, &["SUBSCRIBE", "hello"]).await;
+
+    // Stop reading from the subscriber while publishing large messages, so
+    // the socket buffers fill up and the channel overflows.
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let payload = "x".repeat(16 * 1024);
+    for i in 0..2048 {
+        let message = format!("{}:{}", i, payload);
+        send(&mut publisher, &["PUBLISH", "hello", &message]).await;
+    }
+    send(&mut publisher, &["PUBLISH", "hello", "last"]).await;
+
+    let mut received = 0;
+    loop {
+        let message = subscriber.read_frame().await.unwrap().unwrap().to_string();
+        received += 1;
+
+        if message == "message hello last" {
+            break;
+        }
+    }
+    assert!(received < 2049, "received {} messages", received);
+
+    // The connection is still subscribed and usable.
+    let response = send(&mut subscriber, &["SUBSCRIBE", "world"]).await;
+    assert_eq!(response.to_string(), "subscribe world 2");
+}
+
+/// The pub/sub channel capacity set through `server::Config` bounds how far a
+/// subscriber may fall behind before messages are skipped.
+#[tokio::test]
+async fn configured_pubsub_capacity() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        pubsub_capacity: 16,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+    send(&mut subscriber, &["SUBSCRIBE", "hello"]).await;
+
+    // Far fewer messages than the default capacity are enough to overflow
+    // the channel once the socket buffers are full.
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let payload = "x".repeat(16 * 1024);
+    for i in 0..512 {
+        let message = format!("{}:{}", i, payload);
+        send(&mut publisher, &["PUBLISH", "hello", &message]).await;
+    }
+    send(&mut publisher, &["PUBLISH", "hello", "last"]).await;
+
+    let mut received = 0;
+    loop {
+        let message = subscriber.read_frame().await.unwrap().unwrap().to_string();
+        received += 1;
+
+        if message == "message hello last" {
+            break;
+        }
+    }
+    assert!(received < 513, "received {} messages", received);
+}
+
+/// `Connection::peer_addr` reports the address of the remote end.
+#[tokio::test]
+async fn connection_peer_addr() {
+    let addr = start_server().await;
+
+    let connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    assert_eq!(connection.peer_addr().unwrap(), addr);
+}
+
+/// Subscribing to more channels while subscribed stays in subscribe mode and
+/// confirms each channel with the new total count.
+#[tokio::test]
+async fn subscribe_while_subscribed() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for (i, channel) in ["one", "two", "three"].iter().enumerate() {
+        let response = send(&mut connection, &["SUBSCRIBE", channel]).await;
+        assert_eq!(
+            response.to_string(),
+            format!("subscribe {} {}", channel, i + 1)
+        );
+    }
+
+    // Subscribing again to a channel does not change the count.
+    let response = send(&mut connection, &["SUBSCRIBE", "two"]).await;
+    assert_eq!(response.to_string(), "subscribe two 3");
+
+    // Messages from every channel are still delivered.
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    send(&mut publisher, &["PUBLISH", "one", "hello"]).await;
+    let message = connection.read_frame().await.unwrap().unwrap();
+    assert_eq!(message.to_string(), "message one hello");
+}
+
+/// The server shuts down once the `shutdown` future completes, whatever
+/// reason it reports.
+#[tokio::test]
+async fn shutdown_with_reason() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+    let server = tokio::spawn(server::run(listener, async move {
+        let _ = rx.await;
+        server::ShutdownReason::Terminate
+    }));
+
+    tx.send(()).unwrap();
+    time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+/// `connected_clients` follows connections as they open and close.
+#[tokio::test]
+async fn connected_clients_count() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut other = Connection::new(TcpStream::connect(addr).await.unwrap());
+    send(&mut other, &["PING"]).await;
+
+    let info = send(&mut connection, &["INFO", "clients"]).await;
+    assert!(info.to_string().contains("connected_clients:2\r\n"));
+
+    drop(other);
+
+    // The server notices the closed connection asynchronously.
+    let mut info = String::new();
+    for _ in 0..100 {
+        info = send(&mut connection, &["INFO", "clients"])
+            .await
+            .to_string();
+        if info.contains("connected_clients:1\r\n") {
+            break;
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(info.contains("connected_clients:1\r\n"), "{}", info);
+}
+
+/// A frame with more array elements than the configured limit closes only the
+/// offending connection, before any element is read.
+#[tokio::test]
+async fn array_length_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        max_array_len: 8,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let response = send(
+        &mut connection,
+        &["TOUCH", "a", "b", "c", "d", "e", "f", "g"],
+    )
+    .await;
+    assert_eq!(response.to_string(), "0");
+
+    // Only the header of a huge array is sent, the server must not wait for
+    // the elements.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"*1000000\r\n").await.unwrap();
+    let mut buf = [0; 16];
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+
+    // Other connections are unaffected.
+    let response = send(&mut connection, &["PING"]).await;
+    assert_eq!(response, "PONG");
+}
+
+/// `DEBUG POPULATE` creates the requested keys without touching existing ones.
+#[tokio::test]
+async fn debug_populate() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["SET", "key:1", "mine"]).await;
+
+    let response = send(&mut connection, &["DEBUG", "POPULATE", "3"]).await;
+    assert_eq!(response, "OK");
+    assert_eq!(send(&mut connection, &["GET", "key:0"]).await, "value:0");
+    assert_eq!(send(&mut connection, &["GET", "key:1"]).await, "mine");
+    assert_eq!(send(&mut connection, &["GET", "key:2"]).await, "value:2");
+    assert!(matches!(
+        send(&mut connection, &["GET", "key:3"]).await,
+        Frame::Null
+    ));
+
+    send(&mut connection, &["DEBUG", "POPULATE", "2000", "big", "10"]).await;
+    assert_eq!(
+        send(&mut connection, &["GET", "big:1999"]).await,
+        "value:1999"
+    );
+    let response = send(&mut connection, &["GETRANGE", "big:0", "0", "-1"]).await;
+    assert_eq!(response, "value:0\0\0\0");
+
+    let response = send(&mut connection, &["DEBUG", "POPULATE", "1", "short", "3"]).await;
+    assert_eq!(response, "OK");
+    assert_eq!(send(&mut connection, &["GET", "short:0"]).await, "val");
+}
+
+/// `DEBUG POPULATE` rejects huge counts and stops creating keys once the
+/// memory limit is reached.
+#[tokio::test]
+async fn debug_populate_limits() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        maxmemory: 1024 * 1024,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(
+        &mut connection,
+        &["DEBUG", "POPULATE", "18446744073709551615"],
+    )
+    .await;
+    assert!(matches!(response, Frame::Error(_)), "{:?}", response);
+
+    // About 100 MB of values against a 1 MB limit.
+    let response = send(
+        &mut connection,
+        &["DEBUG", "POPULATE", "100000", "key", "1000"],
+    )
+    .await;
+    assert!(
+        matches!(&response, Frame::Error(err) if err.starts_with("OOM")),
+        "{:?}",
+        response
+    );
+
+    // The keys created before the limit was reached are kept.
+    assert_eq!(
+        send(&mut connection, &["GET", "key:0"])
+            .await
+            .to_string()
+            .len(),
+        1000
+    );
+    let response = send(&mut connection, &["GET", "key:99999"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+}
+
+/// Error replies start with the code of their `CommandError`.
+#[tokio::test]
+async fn command_error_codes() {
+    use mini_redis::cmd::CommandError;
+
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["ZADD", "zset", "1", "one"]).await;
+    let response = send(&mut connection, &["GET", "zset"]).await;
+    assert!(matches!(response, Frame::Error(err) if err == CommandError::WrongType.to_string()));
+    assert!(CommandError::WrongType
+        .to_string()
+        .starts_with("WRONGTYPE "));
+
+    let response = send(&mut connection, &["NOPE"]).await;
+    let expected = CommandError::UnknownCommand("NOPE".into(), Vec::new());
+    assert_eq!(expected.code(), "ERR");
+    assert!(matches!(response, Frame::Error(err) if err == expected.to_string()));
+}
+
+/// A known command with the wrong number of arguments gets an error reply
+/// naming the command, and the connection stays open.
+#[tokio::test]
+async fn wrong_number_of_arguments() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(&mut connection, &["GET"]).await;
+    assert!(
+        matches!(response, Frame::Error(err) if err == "ERR wrong number of arguments for 'get' command")
+    );
+
+    let response = send(&mut connection, &["ZSCORE", "zset", "one", "two"]).await;
+    assert!(
+        matches!(response, Frame::Error(err) if err == "ERR wrong number of arguments for 'zscore' command")
+    );
+
+    // Running out of arguments for an option is reported the same way.
+    let response = send(&mut connection, &["GETEX", "key", "EX"]).await;
+    assert!(
+        matches!(response, Frame::Error(err) if err == "ERR wrong number of arguments for 'getex' command")
+    );
+
+    let response = send(&mut connection, &["PING"]).await;
+    assert_eq!(response, "PONG");
+}
+
+/// `SHUTDOWN` shuts the server down like Ctrl-C, closing every connection.
+/// `SAVE` is rejected as there is no persistence.
+#[tokio::test]
+async fn shutdown_command() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(server::run(listener, std::future::pending::<()>()));
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut other = Connection::new(TcpStream::connect(addr).await.unwrap());
+    send(&mut other, &["PING"]).await;
+
+    let resp = send(&mut connection, &["SHUTDOWN", "SAVE"]).await;
+    assert!(matches!(resp, Frame::Error(err) if err == "ERR persistence is not supported"));
+
+    let frame = Frame::Array(vec![
+        Frame::Bulk("SHUTDOWN".into()),
+        Frame::Bulk("NOSAVE".into()),
+    ]);
+    connection.write_frame(&frame).await.unwrap();
+
+    // No response is sent, both connections are closed.
+    assert!(connection.read_frame().await.unwrap().is_none());
+    assert!(other.read_frame().await.unwrap().is_none());
+
+    time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+/// `GETORSET` stores the default only if the key is missing, and clients
+/// racing on the same key all get the value that was stored.
+#[tokio::test]
+async fn getorset_stores_default_once() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let resp = send(&mut connection, &["GETORSET", "hello", "world"]).await;
+    assert_eq!(resp, "world");
+    let resp = send(&mut connection, &["GETORSET", "hello", "other"]).await;
+    assert_eq!(resp, "world");
+    let resp = send(&mut connection, &["GET", "hello"]).await;
+    assert_eq!(resp, "world");
+
+    let mut tasks = vec![];
+    for i in 0..16 {
+        tasks.push(tokio::spawn(async move {
+            let mut client = Client::connect(addr).await.unwrap();
+            client
+                .get_or_set("race", i.to_string().into())
+                .await
+                .unwrap()
+        }));
+    }
+
+    let mut values = vec![];
+    for task in tasks {
+        values.push(task.await.unwrap());
+    }
+    assert!(values.iter().all(|value| *value == values[0]));
+
+    send(&mut connection, &["ZADD", "zset", "1", "a"]).await;
+    let resp = send(&mut connection, &["GETORSET", "zset", "world"]).await;
+    assert!(matches!(resp, Frame::Error(err) if err.starts_with("WRONGTYPE ")));
+}
+
+/// `ZSCAN` iterates every member once with its score, honoring `COUNT` and
+/// filtering with `MATCH`.
+#[tokio::test]
+async fn zscan_iterates_members() {
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for i in 0..25 {
+        let score = i.to_string();
+        let member = format!("m:{}", i);
+        send(&mut connection, &["ZADD", "zset", &score, &member]).await;
+    }
+
+    // Iterate with the default `COUNT`.
+    let mut cursor = "0".to_string();
+    let mut members = vec![];
+    let mut calls = 0;
+    loop {
+        let resp = send(&mut connection, &["ZSCAN", "zset", &cursor]).await;
+        let (next, elements) = match resp {
+            Frame::Array(mut parts) => match (parts.remove(0), parts.remove(0)) {
+                (Frame::Bulk(next), Frame::Array(elements)) => (next, elements),
+                frame => panic!("unexpected reply {:?}", frame),
+            },
+            frame => panic!("unexpected reply {:?}", frame),
+        };
+
+        for pair in elements.chunks(2) {
+            members.push((pair[0].to_string(), pair[1].to_string()));
+        }
+
+        calls += 1;
+        cursor = String::from_utf8(next.to_vec()).unwrap();
+        if cursor == "0" {
+            break;
+        }
+    }
+
+    assert_eq!(calls, 3);
+    assert_eq!(members.len(), 25);
+    assert_eq!(members[7], ("m:7".to_string(), "7".to_string()));
+
+    let resp = send(
+        &mut connection,
+        &["ZSCAN", "zset", "0", "MATCH", "m:1?", "COUNT", "100"],
+    )
+    .await;
+    assert_eq!(
+        resp.to_string(),
+        "0 m:10 10 m:11 11 m:12 12 m:13 13 m:14 14 \
+         m:15 15 m:16 16 m:17 17 m:18 18 m:19 19"
+    );
+
+    let resp = send(
+        &mut connection,
+        &["ZSCAN", "zset", "0", "MATCH", "m:[2-3]*"],
+    )
+    .await;
+    assert_eq!(resp.to_string(), "10 m:2 2 m:3 3");
+
+    let resp = send(&mut connection, &["ZSCAN", "missing", "0"]).await;
+    assert_eq!(resp.to_string(), "0 ");
+}
+
+/// `DUMP` serializes a value which `RESTORE` recreates under another key,
+/// with a fresh TTL. Existing keys are only replaced with `REPLACE`.
+#[tokio::test]
+async fn dump_and_restore() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["SET", "hello", "world"]).await;
+    send(&mut connection, &["ZADD", "zset", "1", "a", "2.5", "b"]).await;
+
+    let resp = send(&mut connection, &["DUMP", "missing"]).await;
+    assert!(matches!(resp, Frame::Null));
+
+    // Restore the string on another key.
+    let payload = match send(&mut connection, &["DUMP", "hello"]).await {
+        Frame::Bulk(payload) => payload,
+        frame => panic!("unexpected reply {:?}", frame),
+    };
+    let restore = Frame::Array(vec![
+        Frame::Bulk("RESTORE".into()),
+        Frame::Bulk("copy".into()),
+        Frame::Bulk("0".into()),
+        Frame::Bulk(payload.clone()),
+    ]);
+    connection.write_frame(&restore).await.unwrap();
+    assert_eq!(connection.read_frame().await.unwrap().unwrap(), "OK");
+    assert_eq!(send(&mut connection, &["GET", "copy"]).await, "world");
+
+    // The key exists now.
+    connection.write_frame(&restore).await.unwrap();
+    let resp = connection.read_frame().await.unwrap().unwrap();
+    assert!(matches!(resp, Frame::Error(err) if err == "BUSYKEY Target key name already exists."));
+
+    // Replace the string with the sorted set, expiring shortly.
+    let payload = match send(&mut connection, &["DUMP", "zset"]).await {
+        Frame::Bulk(payload) => payload,
+        frame => panic!("unexpected reply {:?}", frame),
+    };
+    let restore = Frame::Array(vec![
+        Frame::Bulk("RESTORE".into()),
+        Frame::Bulk("copy".into()),
+        Frame::Bulk("100".into()),
+        Frame::Bulk(payload),
+        Frame::Bulk("REPLACE".into()),
+    ]);
+    connection.write_frame(&restore).await.unwrap();
+    assert_eq!(connection.read_frame().await.unwrap().unwrap(), "OK");
+
+    let resp = send(
+        &mut connection,
+        &["ZRANGE", "copy", "0", "-1", "WITHSCORES"],
+    )
+    .await;
+    assert_eq!(resp.to_string(), "a 1 b 2.5");
+
+    time::advance(Duration::from_millis(200)).await;
+    let resp = send(&mut connection, &["DUMP", "copy"]).await;
+    assert!(matches!(resp, Frame::Null));
+
+    // A corrupt payload is rejected.
+    let resp = send(&mut connection, &["RESTORE", "bad", "0", "garbage"]).await;
+    assert!(
+        matches!(resp, Frame::Error(err) if err == "ERR DUMP payload version or format is wrong")
+    );
+    let resp = send(&mut connection, &["RESTORE", "bad", "-1", "garbage"]).await;
+    assert!(matches!(resp, Frame::Error(err) if err.starts_with("ERR invalid expire time")));
+}
+
+/// Keys expiring together are all purged even when the background task only
+/// removes one key per batch.
+#[tokio::test]
+async fn expire_in_small_batches() {
+    tokio::time::pause();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        expire_batch_size: 1,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for i in 0..100 {
+        let key = format!("key:{}", i);
+        send(&mut connection, &["SET", &key, "value", "PX", "10"]).await;
+    }
+
+    // `INFO` counts expired keys until the background task removes them.
+    let mut info = String::new();
+    for _ in 0..100 {
+        time::advance(Duration::from_millis(10)).await;
+        info = send(&mut connection, &["INFO", "keyspace"])
+            .await
+            .to_string();
+        if !info.contains("db0:") {
+            break;
+        }
+    }
+    assert!(!info.contains("db0:"), "{}", info);
+}
+
+/// Reading an expired key removes it right away, whether or not the
+/// background task has purged it yet.
+#[tokio::test]
+async fn read_removes_expired_key() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["SET", "hello", "world", "PX", "20"]).await;
+    send(&mut connection, &["ZADD", "zset", "1", "a"]).await;
+    time::advance(Duration::from_millis(40)).await;
+
+    let resp = send(&mut connection, &["GET", "hello"]).await;
+    assert!(matches!(resp, Frame::Null));
+    let resp = send(&mut connection, &["TOUCH", "hello"]).await;
+    assert_eq!(resp.to_string(), "0");
+
+    let info = send(&mut connection, &["INFO", "keyspace"]).await;
+    assert!(info.to_string().contains("db0:keys=1,expires=0\r\n"));
+}
+
+/// A line longer than the configured limit closes the connection, even if
+/// its terminator never arrives.
+#[tokio::test]
+async fn line_length_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        max_line_len: 16,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let response = send(
+        &mut connection,
+        &["SET", "key", "a value past sixteen bytes"],
+    )
+    .await;
+    assert_eq!(response, "OK");
+
+    // An unterminated integer line; the server must give up rather than keep
+    // buffering.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"*1\r\n:").await.unwrap();
+    stream.write_all(&[b'1'; 64]).await.unwrap();
+    let mut buf = [0; 16];
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+
+    // Other connections are unaffected.
+    let response = send(&mut connection, &["GET", "key"]).await;
+    assert_eq!(response, "a value past sixteen bytes");
+}
+
+/// A read-only server rejects write commands but still serves reads and
+/// pub/sub.
+#[tokio::test]
+async fn read_only_rejects_writes() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        read_only: true,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for args in [&["SET", "key", "value"][..], &["ZADD", "zset", "1", "m"]] {
+        let response = send(&mut connection, args).await;
+        assert!(
+            matches!(&response, Frame::Error(err)
+                if err == "READONLY You can't write against a read only replica."),
+            "{:?}",
+            response
+        );
+    }
+
+    // `DEBUG POPULATE` writes even though other `DEBUG` subcommands do not.
+    let response = send(&mut connection, &["DEBUG", "POPULATE", "10"]).await;
+    assert!(
+        matches!(&response, Frame::Error(err) if err.starts_with("READONLY")),
+        "{:?}",
+        response
+    );
+    let response = send(&mut connection, &["GET", "key:0"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+    assert_eq!(send(&mut connection, &["DEBUG", "SLEEP", "0"]).await, "OK");
+
+    let response = send(&mut connection, &["GET", "key"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+    assert_eq!(send(&mut connection, &["PING"]).await, "PONG");
+    let response = send(&mut connection, &["PUBLISH", "chan", "hello"]).await;
+    assert_eq!(response.to_string(), "0");
+}
+
+/// `OBJECT IDLETIME` reports the seconds since the key was last used, and is
+/// reset by reads but not by `OBJECT` itself.
+#[tokio::test]
+async fn object_idletime() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["SET", "key", "value"]).await;
+
+    time::advance(Duration::from_secs(5)).await;
+    let response = send(&mut connection, &["OBJECT", "IDLETIME", "key"]).await;
+    assert_eq!(response.to_string(), "5");
+
+    time::advance(Duration::from_secs(3)).await;
+    let response = send(&mut connection, &["OBJECT", "IDLETIME", "key"]).await;
+    assert_eq!(response.to_string(), "8");
+
+    send(&mut connection, &["GET", "key"]).await;
+    time::advance(Duration::from_secs(2)).await;
+    let response = send(&mut connection, &["OBJECT", "IDLETIME", "key"]).await;
+    assert_eq!(response.to_string(), "2");
+
+    let response = send(&mut connection, &["OBJECT", "IDLETIME", "missing"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+}
+
+/// Commands that modify a string in place keep its time to live, while `SET`
+/// replaces the value together with its expiration.
+#[tokio::test]
+async fn string_commands_ttl_matrix() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // Each command runs against a key set to "1" with a 10 second TTL. The
+    // flag tells whether the key must still expire afterwards.
+    let matrix: &[(&[&str], bool)] = &[
+        (&["SETRANGE", "key", "0", "2"], true),
+        (&["SETBIT", "key", "7", "1"], true),
+        (&["INCRBYFLOAT", "key", "1.5"], true),
+        (&["GETEX", "key"], true),
+        (&["GETORSET", "key", "other"], true),
+        (&["GET", "key"], true),
+        (&["SET", "key", "2"], false),
+        (&["SET", "key", "2", "EX", "100"], false),
+        (&["GETEX", "key", "PERSIST"], false),
+    ];
+
+    for (args, keeps_ttl) in matrix {
+        let response = send(&mut connection, &["SET", "key", "1", "EX", "10"]).await;
+        assert_eq!(response, "OK");
+
+        let response = send(&mut connection, args).await;
+        assert!(!matches!(response, Frame::Error(_)), "{:?}", args);
+
+        time::advance(Duration::from_secs(10)).await;
+
+        let response = send(&mut connection, &["GET", "key"]).await;
+        assert_eq!(
+            matches!(response, Frame::Null),
+            *keeps_ttl,
+            "{:?}: {:?}",
+            args,
+            response
+        );
+    }
+}
+
+/// `OBJECT FREQ` is only available with the `allkeys-lfu` policy. It starts
+/// new keys at 5, counts the first access, and decays by one for every idle
+/// minute.
+#[tokio::test]
+async fn object_freq() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["SET", "key", "value"]).await;
+    let response = send(&mut connection, &["OBJECT", "FREQ", "key"]).await;
+    assert!(
+        matches!(&response, Frame::Error(err) if err.starts_with("ERR An LFU maxmemory policy is not selected")),
+        "{:?}",
+        response
+    );
+
+    let response = send(
+        &mut connection,
+        &["CONFIG", "SET", "maxmemory-policy", "allkeys-lfu"],
+    )
+    .await;
+    assert_eq!(response, "OK");
+    let response = send(&mut connection, &["OBJECT", "FREQ", "key"]).await;
+    assert_eq!(response.to_string(), "5");
+
+    send(&mut connection, &["GET", "key"]).await;
+    let response = send(&mut connection, &["OBJECT", "FREQ", "key"]).await;
+    assert_eq!(response.to_string(), "6");
+
+    time::advance(Duration::from_secs(3 * 60)).await;
+    let response = send(&mut connection, &["OBJECT", "FREQ", "key"]).await;
+    assert_eq!(response.to_string(), "3");
+
+    let response = send(&mut connection, &["OBJECT", "FREQ", "missing"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+}
+
+/// Each eviction policy picks its own victims once memory usage goes above
+/// `maxmemory`, and `noeviction` rejects writes instead.
+#[tokio::test]
+async fn maxmemory_policies() {
+    async fn start(policy: server::EvictionPolicy) -> Connection {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = server::Config {
+            maxmemory: 50_000,
+            maxmemory_policy: policy,
+            ..server::Config::default()
+        };
+        tokio::spawn(async move {
+            server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+        });
+
+        Connection::new(TcpStream::connect(addr).await.unwrap())
+    }
+
+    let value = "x".repeat(10_000);
+
+    // LRU evicts the key that was used least recently.
+    let mut connection = start(server::EvictionPolicy::AllKeysLru).await;
+    send(&mut connection, &["SET", "first", "1"]).await;
+    for i in 0..20 {
+        let key = format!("filler:{}", i);
+        assert_eq!(send(&mut connection, &["SET", &key, &value]).await, "OK");
+    }
+    let response = send(&mut connection, &["GET", "first"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+    assert_eq!(
+        send(&mut connection, &["GET", "filler:19"]).await,
+        value.as_str()
+    );
+    let info = send(&mut connection, &["INFO", "stats"]).await.to_string();
+    assert!(!info.contains("evicted_keys:0\r\n"), "{}", info);
+
+    // LFU keeps the key that was read, even though it is the oldest.
+    let mut connection = start(server::EvictionPolicy::AllKeysLfu).await;
+    send(&mut connection, &["SET", "hot", "1"]).await;
+    send(&mut connection, &["GET", "hot"]).await;
+    for i in 0..20 {
+        let key = format!("filler:{}", i);
+        assert_eq!(send(&mut connection, &["SET", &key, &value]).await, "OK");
+    }
+    assert_eq!(send(&mut connection, &["GET", "hot"]).await, "1");
+    let response = send(&mut connection, &["GET", "filler:0"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+
+    // Without eviction, writes fail once the limit is reached but reads work.
+    let mut connection = start(server::EvictionPolicy::NoEviction).await;
+    let mut rejected = false;
+    for i in 0..20 {
+        let key = format!("filler:{}", i);
+        let response = send(&mut connection, &["SET", &key, &value]).await;
+        if let Frame::Error(err) = &response {
+            assert!(err.starts_with("OOM "), "{}", err);
+            rejected = true;
+            break;
+        }
+    }
+    assert!(rejected);
+    assert_eq!(
+        send(&mut connection, &["GET", "filler:0"]).await,
+        value.as_str()
+    );
+}
+
+/// `CLIENT LIST` reports how many commands each connection has run and how
+/// long it has been idle since the last one.
+#[tokio::test]
+async fn client_list_activity() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+    let mut first = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut second = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut first, &["PING"]).await;
+    send(&mut first, &["SET", "key", "value"]).await;
+    send(&mut first, &["GET", "key"]).await;
+
+    time::advance(Duration::from_secs(7)).await;
+
+    let list = send(&mut second, &["CLIENT", "LIST"]).await.to_string();
+    let lines: Vec<_> = list.lines().collect();
+    assert!(lines[0].ends_with(" idle=7 cmd=get tot-cmds=3"), "{}", list);
+    assert!(
+        lines[1].ends_with(" idle=7 cmd=client tot-cmds=0"),
+        "{}",
+        list
+    );
+}
+
+/// `LOLWUT` returns some art followed by the server version, with or without
+/// the `VERSION` argument.
+#[tokio::test]
+async fn lolwut() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let plain = send(&mut connection, &["LOLWUT"]).await.to_string();
+    let version = format!("mini-redis ver. {}\n", env!("CARGO_PKG_VERSION"));
+    assert!(plain.ends_with(&version), "{}", plain);
+    assert!(plain.lines().count() > 1);
+
+    let versioned = send(&mut connection, &["LOLWUT", "VERSION", "5"]).await;
+    assert_eq!(versioned.to_string(), plain);
+}
+
+/// `PING` replies with a simple `PONG`, or with its message as a bulk string.
+/// While subscribed, the reply is a `pong` array instead.
+#[tokio::test]
+async fn ping_reply_shapes() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    let mut response = [0; 7];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"+PONG\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$4\r\nPING\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 11];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"$5\r\nhello\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$9\r\nSUBSCRIBE\r\n$5\r\nhello\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 34];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(
+        &b"*3\r\n$9\r\nsubscribe\r\n$5\r\nhello\r\n:1\r\n"[..],
+        &response[..]
+    );
+
+    stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+    let mut response = [0; 20];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$4\r\npong\r\n$0\r\n\r\n", &response);
+
+    stream
+        .write_all(b"*2\r\n$4\r\nPING\r\n$2\r\nhi\r\n")
+        .await
+        .unwrap();
+    let mut response = [0; 22];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"*2\r\n$4\r\npong\r\n$2\r\nhi\r\n", &response);
+}
+
+/// With a zero threshold every command is recorded in the slow log, newest
+/// first, and the log keeps at most `slowlog_max_len` entries.
+#[tokio::test]
+async fn slowlog() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        slowlog_log_slower_than: Some(Duration::ZERO),
+        slowlog_max_len: 3,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let client_addr = stream.local_addr().unwrap().to_string();
+    let mut connection = Connection::new(stream);
+    send(&mut connection, &["CLIENT", "SETNAME", "tester"]).await;
+
+    // `SLOWLOG LEN` is recorded after it returns.
+    assert_eq!(
+        send(&mut connection, &["SLOWLOG", "LEN"]).await.to_string(),
+        "1"
+    );
+
+    send(&mut connection, &["SET", "key", "value"]).await;
+    send(&mut connection, &["GET", "key"]).await;
+    assert_eq!(
+        send(&mut connection, &["SLOWLOG", "LEN"]).await.to_string(),
+        "3"
+    );
+
+    let response = send(&mut connection, &["SLOWLOG", "GET", "2"]).await;
+    let entries = match response {
+        Frame::Array(entries) => entries,
+        frame => panic!("{:?}", frame),
+    };
+    assert_eq!(entries.len(), 2);
+
+    match &entries[1] {
+        Frame::Array(fields) => {
+            assert_eq!(fields.len(), 6);
+            assert_eq!(fields[0].to_string(), "3");
+            assert!(matches!(fields[2], Frame::Integer(us) if us >= 0));
+            assert_eq!(fields[3].to_string(), "GET key");
+            assert_eq!(fields[4].to_string(), client_addr);
+            assert_eq!(fields[5], "tester");
+        }
+        frame => panic!("{:?}", frame),
+    }
+
+    let response = send(&mut connection, &["SLOWLOG", "GET", "-1"]).await;
+    assert!(matches!(&response, Frame::Array(entries) if entries.len() == 3));
+
+    assert_eq!(send(&mut connection, &["SLOWLOG", "RESET"]).await, "OK");
+    assert_eq!(
+        send(&mut connection, &["SLOWLOG", "LEN"]).await.to_string(),
+        "1"
+    );
+}
+
+/// Only commands which take at least `slowlog_log_slower_than` are recorded,
+/// and a `None` threshold disables the slow log.
+#[tokio::test]
+async fn slowlog_threshold() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["GET", "key"]).await;
+    send(&mut connection, &["DEBUG", "SLEEP", "0.05"]).await;
+
+    let response = send(&mut connection, &["SLOWLOG", "GET"]).await;
+    assert_eq!(response.to_string().split(' ').nth(3), Some("DEBUG"));
+    assert!(matches!(&response, Frame::Array(entries) if entries.len() == 1));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        slowlog_log_slower_than: None,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    send(&mut connection, &["DEBUG", "SLEEP", "0.05"]).await;
+    assert_eq!(
+        send(&mut connection, &["SLOWLOG", "LEN"]).await.to_string(),
+        "0"
+    );
+}
+
+/// `CONFIG GET` matches parameter names against a glob pattern and
+/// `CONFIG SET` validates values before applying them.
+#[tokio::test]
+async fn config_get_set() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(&mut connection, &["CONFIG", "GET", "maxmemory*"]).await;
+    assert_eq!(
+        response.to_string(),
+        "maxmemory 0 maxmemory-policy noeviction"
+    );
+
+    let response = send(&mut connection, &["CONFIG", "SET", "maxmemory", "1mb"]).await;
+    assert_eq!(response, "OK");
+    let response = send(
+        &mut connection,
+        &["CONFIG", "SET", "MAXMEMORY-POLICY", "allkeys-lru"],
+    )
+    .await;
+    assert_eq!(response, "OK");
+
+    let response = send(&mut connection, &["CONFIG", "GET", "MAXMEMORY*"]).await;
+    assert_eq!(
+        response.to_string(),
+        "maxmemory 1048576 maxmemory-policy allkeys-lru"
+    );
+
+    let response = send(&mut connection, &["CONFIG", "GET", "pubsub-capacity"]).await;
+    assert_eq!(response.to_string(), "pubsub-capacity 1024");
+    let response = send(&mut connection, &["CONFIG", "GET", "nonexistent"]).await;
+    assert!(matches!(&response, Frame::Array(items) if items.is_empty()));
+
+    let response = send(&mut connection, &["CONFIG", "SET", "maxmemory", "lots"]).await;
+    assert!(
+        matches!(&response, Frame::Error(err) if err
+            == "ERR CONFIG SET failed (possibly related to argument 'maxmemory') - argument must be a memory value"),
+        "{:?}",
+        response
+    );
+
+    let response = send(
+        &mut connection,
+        &["CONFIG", "SET", "maxmemory-policy", "volatile-lru"],
+    )
+    .await;
+    assert!(matches!(&response, Frame::Error(_)), "{:?}", response);
+    let response = send(&mut connection, &["CONFIG", "SET", "pubsub-capacity", "0"]).await;
+    assert!(matches!(&response, Frame::Error(_)), "{:?}", response);
+
+    let response = send(&mut connection, &["CONFIG", "SET", "port", "1234"]).await;
+    assert!(
+        matches!(&response, Frame::Error(err) if err
+            == "ERR Unknown option or number of arguments for CONFIG SET - 'port'"),
+        "{:?}",
+        response
+    );
+
+    // Failed updates leave the previous values in place.
+    let response = send(&mut connection, &["CONFIG", "GET", "*"]).await;
+    assert_eq!(
+        response.to_string(),
+        "maxmemory 1048576 maxmemory-policy allkeys-lru proto-max-bulk-len 536870912 pubsub-capacity 1024"
+    );
+}
+
+/// A pub/sub capacity too large to allocate is rejected, so the next
+/// subscription does not bring the server down.
+#[tokio::test]
+async fn config_set_pubsub_capacity_limit() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for capacity in &["1048577", "100000000000", "18446744073709551615"] {
+        let response = send(
+            &mut connection,
+            &["CONFIG", "SET", "pubsub-capacity", capacity],
+        )
+        .await;
+        assert!(
+            matches!(&response, Frame::Error(err) if err
+                == "ERR CONFIG SET failed (possibly related to argument 'pubsub-capacity') - argument must be an integer between 1 and 1048576"),
+            "{:?}",
+            response
+        );
+    }
+
+    let response = send(
+        &mut connection,
+        &["CONFIG", "SET", "pubsub-capacity", "1048576"],
+    )
+    .await;
+    assert_eq!(response, "OK");
+
+    let response = send(&mut connection, &["SUBSCRIBE", "hello"]).await;
+    assert_eq!(response.to_string(), "subscribe hello 1");
+}
+
+/// A lower `maxmemory` set at runtime evicts keys immediately, and a new
+/// `proto-max-bulk-len` applies to connections accepted afterwards.
+#[tokio::test]
+async fn config_set_takes_effect() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    for i in 0..10 {
+        let key = format!("key:{}", i);
+        send(&mut connection, &["SET", &key, "value"]).await;
+    }
+
+    send(
+        &mut connection,
+        &["CONFIG", "SET", "maxmemory-policy", "allkeys-lru"],
+    )
+    .await;
+    send(&mut connection, &["CONFIG", "SET", "maxmemory", "1"]).await;
+    let response = send(&mut connection, &["RANDOMKEY"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+
+    send(&mut connection, &["CONFIG", "SET", "maxmemory", "0"]).await;
+    send(
+        &mut connection,
+        &["CONFIG", "SET", "proto-max-bulk-len", "8"],
+    )
+    .await;
+
+    // The existing connection keeps the limit it was accepted with.
+    let response = send(&mut connection, &["SET", "key", "a long value"]).await;
+    assert_eq!(response, "OK");
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let response = send(&mut connection, &["SET", "key", "short"]).await;
+    assert_eq!(response, "OK");
+
+    let frame = Frame::Array(vec![
+        Frame::Bulk("SET".into()),
+        Frame::Bulk("key".into()),
+        Frame::Bulk("a long value".into()),
+    ]);
+    connection.write_frame(&frame).await.unwrap();
+    assert!(!matches!(connection.read_frame().await, Ok(Some(_))));
+}
+
+/// A subscriber attached during shutdown receives an unsubscribe
+/// confirmation for each of its channels, then the connection is closed and
+/// the server finishes.
+#[tokio::test]
+async fn shutdown_unsubscribes_subscribers() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+    let server = tokio::spawn(server::run(listener, async move {
+        let _ = rx.await;
+    }));
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    send(&mut connection, &["SUBSCRIBE", "one", "two"]).await;
+    let response = connection.read_frame().await.unwrap().unwrap();
+    assert_eq!(response.to_string(), "subscribe two 2");
+
+    tx.send(()).unwrap();
+
+    let mut channels = Vec::new();
+    for remaining in [1, 0] {
+        let response = connection.read_frame().await.unwrap().unwrap();
+        let response = response.to_string();
+        let parts: Vec<_> = response.split(' ').collect();
+        assert_eq!(parts[0], "unsubscribe");
+        assert_eq!(parts[2], remaining.to_string());
+        channels.push(parts[1].to_string());
+    }
+    channels.sort();
+    assert_eq!(channels, ["one", "two"]);
+
+    assert!(connection.read_frame().await.unwrap().is_none());
+    time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+/// Pipelined requests are answered in order, whether the replies fit in one
+/// write or cross the write buffer's high-water mark.
+#[tokio::test]
+async fn pipelined_requests() {
+    let addr = start_server().await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(
+            b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n\
+              *2\r\n$3\r\nGET\r\n$3\r\nkey\r\n\
+              *1\r\n$4\r\nPING\r\n",
+        )
+        .await
+        .unwrap();
+
+    let expected = b"+OK\r\n$5\r\nvalue\r\n+PONG\r\n";
+    let mut response = [0; 23];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(&response[..], &expected[..]);
+
+    // 2000 replies of 7 bytes are well over the 8 KiB write buffer.
+    let batch = b"*1\r\n$4\r\nPING\r\n".repeat(2000);
+    stream.write_all(&batch).await.unwrap();
+
+    let mut response = vec![0; 7 * 2000];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(response, b"+PONG\r\n".repeat(2000));
+}
+
+/// `ZRANDMEMBER` returns a single member without a count, distinct members
+/// for a positive count and possibly repeated members for a negative count,
+/// up to a limit.
+#[tokio::test]
+async fn zrandmember() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(&mut connection, &["ZRANDMEMBER", "zset"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+    let response = send(&mut connection, &["ZRANDMEMBER", "zset", "3"]).await;
+    assert_eq!(response.to_string(), "");
+
+    send(
+        &mut connection,
+        &["ZADD", "zset", "1", "a", "2", "b", "3", "c"],
+    )
+    .await;
+
+    let response = send(&mut connection, &["ZRANDMEMBER", "zset"]).await;
+    assert!(
+        ["a", "b", "c"].contains(&&*response.to_string()),
+        "{}",
+        response
+    );
+
+    let response = send(&mut connection, &["ZRANDMEMBER", "zset", "10"]).await;
+    let mut members: Vec<_> = response
+        .to_string()
+        .split(' ')
+        .map(str::to_string)
+        .collect();
+    members.sort();
+    assert_eq!(members, ["a", "b", "c"]);
+
+    let response = send(&mut connection, &["ZRANDMEMBER", "zset", "-10"]).await;
+    assert!(matches!(&response, Frame::Array(members) if members.len() == 10));
+
+    let response = send(&mut connection, &["ZRANDMEMBER", "zset", "0"]).await;
+    assert!(matches!(&response, Frame::Array(members) if members.is_empty()));
+
+    let response = send(
+        &mut connection,
+        &["ZRANDMEMBER", "zset", "-2", "WITHSCORES"],
+    )
+    .await;
+    let reply = response.to_string();
+    let parts: Vec<_> = reply.split(' ').collect();
+    assert_eq!(parts.len(), 4);
+    for pair in parts.chunks(2) {
+        let expected = match pair[0] {
+            "a" => "1",
+            "b" => "2",
+            "c" => "3",
+            other => panic!("unexpected member {}", other),
+        };
+        assert_eq!(pair[1], expected);
+    }
+
+    // Counts whose reply could not be allocated are rejected.
+    for count in &["-9223372036854775808", "4611686018427387904", "-1048577"] {
+        let response = send(&mut connection, &["ZRANDMEMBER", "zset", count]).await;
+        assert!(matches!(&response, Frame::Error(_)), "{:?}", response);
+    }
+
+    send(&mut connection, &["SET", "string", "value"]).await;
+    let response = send(&mut connection, &["ZRANDMEMBER", "string"]).await;
+    assert!(
+        matches!(&response, Frame::Error(err) if err.starts_with("WRONGTYPE")),
+        "{:?}",
+        response
+    );
+}
+
+/// `EXPIRE` options make the new timeout conditional on the current one, a
+/// key without a timeout counting as never expiring.
+#[tokio::test]
+async fn expire_options() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let cases: &[(&[&str], &str)] = &[
+        (&["EXPIRE", "missing", "10"][..], "0"),
+        (&["SET", "key", "value"][..], "OK"),
+        (&["EXPIRE", "key", "10", "XX"][..], "0"),
+        (&["EXPIRE", "key", "10", "GT"][..], "0"),
+        (&["EXPIRE", "key", "100", "LT"][..], "1"),
+        (&["EXPIRE", "key", "10", "NX"][..], "0"),
+        (&["EXPIRE", "key", "10", "XX"][..], "1"),
+        (&["EXPIRE", "key", "5", "GT"][..], "0"),
+        (&["EXPIRE", "key", "20", "gt"][..], "1"),
+        (&["EXPIRE", "key", "30", "LT"][..], "0"),
+        (&["EXPIRE", "key", "5", "LT"][..], "1"),
+    ];
+    for (args, expected) in cases {
+        let response = send(&mut connection, args).await;
+        assert_eq!(response.to_string(), *expected, "{:?}", args);
+    }
+
+    time::advance(Duration::from_secs(4)).await;
+    let response = send(&mut connection, &["GET", "key"]).await;
+    assert_eq!(response.to_string(), "value");
+
+    time::advance(Duration::from_secs(2)).await;
+    let response = send(&mut connection, &["GET", "key"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+
+    // A timeout of zero or less deletes the key right away.
+    send(&mut connection, &["SET", "key", "value"]).await;
+    let response = send(&mut connection, &["EXPIRE", "key", "-1"]).await;
+    assert_eq!(response.to_string(), "1");
+    let response = send(&mut connection, &["GET", "key"]).await;
+    assert!(matches!(response, Frame::Null), "{:?}", response);
+}
+
+/// `ROLE` reports a master without replicas.
+#[tokio::test]
+async fn role() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(&mut connection, &["ROLE"]).await;
+    match response {
+        Frame::Array(fields) => {
+            assert_eq!(fields.len(), 3);
+            assert_eq!(fields[0], "master");
+            assert!(matches!(fields[1], Frame::Integer(0)));
+            assert!(matches!(&fields[2], Frame::Array(replicas) if replicas.is_empty()));
+        }
+        frame => panic!("{:?}", frame),
+    }
+}
+
+#[tokio::test]
+async fn debug_object() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["SET", "num", "12345"]).await;
+    let response = send(&mut connection, &["DEBUG", "OBJECT", "num"]).await;
+    assert_eq!(
+        response,
+        "Value at:0x0 refcount:1 encoding:int serializedlength:15 lru_seconds_idle:0"
+    );
+
+    send(&mut connection, &["ZADD", "zset", "1", "a", "2", "b"]).await;
+    let response = send(&mut connection, &["DEBUG", "OBJECT", "zset"]).await;
+    let response = response.to_string();
+    assert!(response.contains("encoding:skiplist"), "{}", response);
+    assert!(response.ends_with(" length:2"), "{}", response);
+
+    let response = send(&mut connection, &["DEBUG", "OBJECT", "missing"]).await;
+    assert!(matches!(response, Frame::Error(err) if err == "ERR no such key"));
+}
+
+/// Requests which cannot be parsed into a command get an error reply and the
+/// connection stays open, as long as the frames themselves are well formed.
+#[tokio::test]
+async fn invalid_requests_keep_connection_open() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(&mut connection, &["ZADD", "zset", "one", "member"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("ERR ")));
+
+    let response = send(&mut connection, &["DEBUG", "NOPE"]).await;
+    assert!(
+        matches!(response, Frame::Error(err) if err == "ERR unknown subcommand 'nope' for `DEBUG`")
+    );
+
+    // A request must be an array.
+    connection
+        .write_frame(&Frame::Simple("PING".into()))
+        .await
+        .unwrap();
+    let response = connection.read_frame().await.unwrap().unwrap();
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("ERR ")));
+
+    let response = send(&mut connection, &["PING"]).await;
+    assert_eq!(response, "PONG");
+}
+
+/// A request which cannot be parsed while subscribed gets an error reply, and
+/// the connection stays subscribed.
+#[tokio::test]
+async fn invalid_request_while_subscribed() {
+    let addr = start_server().await;
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(&mut subscriber, &["SUBSCRIBE", "chan"]).await;
+    assert_eq!(response.to_string(), "subscribe chan 1");
+
+    // A request must be an array.
+    subscriber
+        .write_frame(&Frame::Simple("PING".into()))
+        .await
+        .unwrap();
+    let response = subscriber.read_frame().await.unwrap().unwrap();
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("ERR ")));
+
+    // Unknown commands are reported as such, not as disallowed.
+    let response = send(&mut subscriber, &["NOPE"]).await;
+    assert!(
+        matches!(&response, Frame::Error(err) if err.starts_with("ERR unknown command 'NOPE'")),
+        "{:?}",
+        response
+    );
+
+    let response = send(&mut publisher, &["PUBLISH", "chan", "hello"]).await;
+    assert_eq!(response.to_string(), "1");
+    let response = subscriber.read_frame().await.unwrap().unwrap();
+    assert_eq!(response.to_string(), "message chan hello");
+}
+
+/// The reply to an unknown command echoes its leading arguments, truncated
+/// to 128 bytes as in Redis, and the connection stays open.
+#[tokio::test]
+async fn unknown_command_echoes_args() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let response = send(&mut connection, &["Nope", "a", "b\r\nc"]).await;
+    assert!(matches!(
+        response,
+        Frame::Error(err) if err == "ERR unknown command 'Nope', with args beginning with: 'a' 'b  c' "
+    ));
+
+    let long = "x".repeat(200);
+    let response = send(&mut connection, &["NOPE", "a", &long, "b"]).await;
+    let expected = format!(
+        "ERR unknown command 'NOPE', with args beginning with: 'a' '{}' ",
+        "x".repeat(124)
+    );
+    assert!(matches!(response, Frame::Error(err) if err == expected));
+
+    let response = send(&mut connection, &["PING"]).await;
+    assert_eq!(response, "PONG");
+}
+
+/// A subscriber that stops reading is disconnected once its pending messages
+/// exceed the output buffer limit, instead of stalling its connection.
+#[tokio::test]
+async fn output_buffer_limit_disconnects_slow_subscriber() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        output_buffer_limit: 1024 * 1024,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    // Subscribe, then never read again.
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+    send(&mut subscriber, &["SUBSCRIBE", "news"]).await;
+
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let message = "x".repeat(64 * 1024);
+
+    // Keep publishing until the subscriber is gone. The socket buffers absorb
+    // some data first, so it takes more than the limit itself.
+    let disconnected = time::timeout(Duration::from_secs(10), async {
+        loop {
+            let response = send(&mut publisher, &["PUBLISH", "news", &message]).await;
+            if matches!(response, Frame::Integer(0)) {
+                break;
+            }
+
+            time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await;
+    assert!(disconnected.is_ok(), "subscriber was not disconnected");
+
+    // The publisher is unaffected.
+    let response = send(&mut publisher, &["PING"]).await;
+    assert_eq!(response, "PONG");
+}
+
+/// `BITPOS` resolves byte and bit ranges like Redis, including the padding of
+/// strings with zero bits when no end is given.
+#[tokio::test]
+async fn bitpos() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let cases: &[(&[u8], &[&str], i64)] = &[
+        // Missing keys are all zero bits.
+        (b"", &["missing", "0"], 0),
+        (b"", &["missing", "1"], -1),
+        (b"", &["key", "0"], -1),
+        (b"", &["key", "1"], -1),
+        (b"\xff\xf0\x00", &["key", "0"], 12),
+        (b"\x00\x00\x00", &["key", "1"], -1),
+        // Searching for a clear bit past the end only succeeds without `end`.
+        (b"\xff\xff\xff", &["key", "0"], 24),
+        (b"\xff\xff\xff", &["key", "0", "1"], 24),
+        (b"\xff\xff\xff", &["key", "0", "1", "-1"], -1),
+        (b"\xff\xff\xff", &["key", "0", "1", "-1", "BIT"], -1),
+        // Byte ranges.
+        (b"\x00\xff\xf0", &["key", "1", "0"], 8),
+        (b"\x00\xff\xf0", &["key", "1", "2"], 16),
+        (b"\x00\xff\xf0", &["key", "1", "2", "-1", "BYTE"], 16),
+        (b"\x00\xff\x00", &["key", "0", "1", "-1"], 16),
+        (b"\x00\xff\x00", &["key", "0", "2", "200"], 16),
+        (b"\x00\xff\x00", &["key", "0", "1", "1"], -1),
+        (b"\x00\xff\x00", &["key", "1", "2", "-1"], -1),
+        // Negative indices before the start of the string clamp to it.
+        (b"\xff\x00\x00", &["key", "1", "-10", "-8"], 0),
+        // Bit ranges, starting and ending in the middle of a byte.
+        (b"\x00\xff\xf0", &["key", "1", "7", "15", "bit"], 8),
+        (b"\x00\xff\xf0", &["key", "1", "7", "-3", "BIT"], 8),
+        (b"\x00\xff\x00", &["key", "0", "8", "-1", "BIT"], 16),
+        (b"\x00\xff\x00", &["key", "0", "8", "8", "BIT"], -1),
+        (b"\x00\xff\x00", &["key", "1", "16", "200", "BIT"], -1),
+        (b"\xf0\x0f", &["key", "0", "2", "6", "BIT"], 4),
+        (b"\xf0\x0f", &["key", "1", "5", "11", "BIT"], -1),
+        (b"\xf0\x0f", &["key", "1", "5", "12", "BIT"], 12),
+    ];
+
+    for (value, args, expected) in cases {
+        set_bytes(&mut connection, "key", value).await;
+
+        let mut request = vec!["BITPOS"];
+        request.extend_from_slice(args);
+        let response = send(&mut connection, &request).await;
+        assert_eq!(response.to_string(), expected.to_string(), "{:?}", args);
+    }
+
+    let response = send(&mut connection, &["BITPOS", "key", "2"]).await;
+    assert!(matches!(response, Frame::Error(_)));
+
+    let response = send(&mut connection, &["BITPOS", "key", "1", "0", "1", "BITS"]).await;
+    assert!(matches!(response, Frame::Error(_)));
+}
+
+/// `BITOP` pads shorter sources with zero bytes, replaces the destination and
+/// deletes it when the result is empty.
+#[tokio::test]
+async fn bitop() {
+    tokio::time::pause();
+
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    set_bytes(&mut connection, "a", b"\xf0\x0f\xff").await;
+    set_bytes(&mut connection, "b", b"\x3c").await;
+
+    let cases: &[(&str, &[&str], &[u8])] = &[
+        ("AND", &["a", "b"], b"\x30\x00\x00"),
+        ("or", &["a", "b"], b"\xfc\x0f\xff"),
+        ("XOR", &["a", "b", "b"], b"\xf0\x0f\xff"),
+        ("XOR", &["a", "b"], b"\xcc\x0f\xff"),
+        ("NOT", &["b"], b"\xc3"),
+        ("AND", &["a", "missing"], b"\x00\x00\x00"),
+    ];
+
+    for (operation, sources, expected) in cases {
+        let mut request = vec!["BITOP", operation, "dest"];
+        request.extend_from_slice(sources);
+        let response = send(&mut connection, &request).await;
+        assert_eq!(response.to_string(), expected.len().to_string());
+
+        let response = send(&mut connection, &["GET", "dest"]).await;
+        assert!(
+            matches!(&response, Frame::Bulk(value) if value == expected),
+            "{} {:?}: {:?}",
+            operation,
+            sources,
+            response
+        );
+    }
+
+    // The result replaces the destination's expiration.
+    send(&mut connection, &["EXPIRE", "dest", "10"]).await;
+    send(&mut connection, &["BITOP", "OR", "dest", "a"]).await;
+    time::advance(Duration::from_secs(10)).await;
+    let response = send(&mut connection, &["GET", "dest"]).await;
+    assert!(matches!(response, Frame::Bulk(_)));
+
+    // An empty result deletes the destination.
+    let response = send(&mut connection, &["BITOP", "OR", "dest", "missing"]).await;
+    assert_eq!(response.to_string(), "0");
+    let response = send(&mut connection, &["GET", "dest"]).await;
+    assert!(matches!(response, Frame::Null));
+
+    send(&mut connection, &["ZADD", "zset", "1", "one"]).await;
+    let response = send(&mut connection, &["BITOP", "AND", "dest", "a", "zset"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+
+    let response = send(&mut connection, &["BITOP", "NOT", "dest", "a", "b"]).await;
+    assert!(matches!(response, Frame::Error(_)));
+
+    let response = send(&mut connection, &["BITOP", "NAND", "dest", "a", "b"]).await;
+    assert!(matches!(response, Frame::Error(_)));
+}
+
+/// A peer closing the connection in the middle of a frame is reported as
+/// `ConnectionError::ResetMidFrame`, while closing between frames is a clean
+/// end of stream.
+#[tokio::test]
+async fn read_frame_reset_mid_frame() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        // The first peer sends a whole frame, the second one half of it.
+        for data in [&b"+OK\r\n"[..], &b"$5\r\nhel"[..]] {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket.write_all(data).await.unwrap();
+        }
+    });
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let frame = connection.read_frame().await.unwrap();
+    assert!(matches!(frame, Some(Frame::Simple(s)) if s == "OK"));
+    assert!(connection.read_frame().await.unwrap().is_none());
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let err = connection.read_frame().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ConnectionError>(),
+        Some(&ConnectionError::ResetMidFrame)
+    );
+}
+
+/// A command whose total size passes the configured limit closes the
+/// connection, even though each of its elements is small and the command is
+/// never completed.
+#[tokio::test]
+async fn frame_length_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let config = server::Config {
+        max_frame_len: 64,
+        ..server::Config::default()
+    };
+    tokio::spawn(async move {
+        server::run_with_config(listener, config, tokio::signal::ctrl_c()).await
+    });
+
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let response = send(&mut connection, &["SET", "key", "value"]).await;
+    assert_eq!(response, "OK");
+
+    // Many one-byte bulks, well within the array and bulk limits.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut command = b"*100\r\n$4\r\nMSET\r\n".to_vec();
+    for _ in 0..20 {
+        command.extend_from_slice(b"$1\r\na\r\n");
+    }
+    stream.write_all(&command).await.unwrap();
+    let mut buf = [0; 16];
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+
+    // A single bulk declared past the limit is rejected before its content
+    // arrives.
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"*2\r\n$3\r\nGET\r\n$100\r\n")
+        .await
+        .unwrap();
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+
+    // Other connections are unaffected.
+    let response = send(&mut connection, &["GET", "key"]).await;
+    assert_eq!(response, "value");
+}
+
+#[tokio::test]
+async fn memory_usage() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let usage = |frame: Frame| frame.to_string().parse::<usize>().unwrap();
+
+    send(&mut connection, &["SET", "short", "abc"]).await;
+    send(&mut connection, &["SET", "long", "abcdefghij"]).await;
+    let short = usage(send(&mut connection, &["MEMORY", "USAGE", "short"]).await);
+    let long = usage(send(&mut connection, &["MEMORY", "USAGE", "long"]).await);
+    assert!(short > "short".len() + "abc".len());
+    // One byte less for each of the two copies of the key name, seven more
+    // for the value.
+    assert_eq!(long, short - 2 + 7);
+
+    // Members of the same size extrapolate to the exact total.
+    send(
+        &mut connection,
+        &["ZADD", "zset", "1", "a", "2", "b", "3", "c", "4", "d"],
+    )
+    .await;
+    let exact = usage(send(&mut connection, &["MEMORY", "USAGE", "zset"]).await);
+    let sampled = send(
+        &mut connection,
+        &["memory", "usage", "zset", "samples", "2"],
+    )
+    .await;
+    assert_eq!(usage(sampled), exact);
+    let all = send(
+        &mut connection,
+        &["MEMORY", "USAGE", "zset", "SAMPLES", "0"],
+    )
+    .await;
+    assert_eq!(usage(all), exact);
+
+    let response = send(&mut connection, &["MEMORY", "USAGE", "missing"]).await;
+    assert!(matches!(response, Frame::Null));
+}
+
+/// `used_memory` is kept up to date as keys are modified in place, given a
+/// timeout and removed, so it comes back to the same value once the keyspace
+/// holds the same data again.
+#[tokio::test]
+async fn used_memory_follows_writes() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    async fn used_memory(connection: &mut Connection) -> usize {
+        send(connection, &["INFO", "memory"])
+            .await
+            .to_string()
+            .lines()
+            .find_map(|line| line.strip_prefix("used_memory:"))
+            .map(|used| used.parse().unwrap())
+            .unwrap()
+    }
+
+    send(&mut connection, &["SET", "key", "value"]).await;
+    send(&mut connection, &["ZADD", "zset", "1", "a"]).await;
+    let before = used_memory(&mut connection).await;
+
+    send(&mut connection, &["SETRANGE", "key", "100", "grown"]).await;
+    send(&mut connection, &["SETBIT", "key", "2000", "1"]).await;
+    send(&mut connection, &["EXPIRE", "key", "100"]).await;
+    send(
+        &mut connection,
+        &["ZADD", "zset", "2", "b", "3", "c", "4", "a"],
+    )
+    .await;
+    assert!(used_memory(&mut connection).await > before);
+
+    send(&mut connection, &["ZREM", "zset", "a", "b", "c"]).await;
+    send(&mut connection, &["SET", "key", "value"]).await;
+    send(&mut connection, &["ZADD", "zset", "1", "a"]).await;
+    assert_eq!(used_memory(&mut connection).await, before);
+
+    send(&mut connection, &["EXPIRE", "key", "100"]).await;
+    send(&mut connection, &["GETEX", "key", "PERSIST"]).await;
+    assert_eq!(used_memory(&mut connection).await, before);
+}
+
+#[tokio::test]
+async fn lcs() {
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    send(&mut connection, &["SET", "key1", "ohmytext"]).await;
+    send(&mut connection, &["SET", "key2", "mynewtext"]).await;
+
+    // (arguments, expected reply); nested arrays are displayed flattened.
+    let cases: &[(&[&str], &str)] = &[
+        (&["key1", "key2"], "mytext"),
+        (&["key1", "key2", "LEN"], "6"),
+        (&["key1", "key2", "IDX"], "matches 4 7 5 8 2 3 0 1 len 6"),
+        (
+            &["key1", "key2", "idx", "minmatchlen", "4", "withmatchlen"],
+            "matches 4 7 5 8 4 len 6",
+        ),
+        // The subsequence with itself is the whole string.
+        (&["key1", "key1", "IDX"], "matches 0 7 0 7 len 8"),
+        // A missing key is an empty string.
+        (&["key1", "missing"], ""),
+        (&["missing", "key2", "LEN"], "0"),
+        (&["missing", "missing", "IDX"], "matches  len 0"),
+    ];
+
+    for (args, expected) in cases {
+        let mut command = vec!["LCS"];
+        command.extend_from_slice(args);
+
+        let response = send(&mut connection, &command).await;
+        assert_eq!(response.to_string(), *expected, "{:?}", args);
+    }
+
+    // The matches are nested arrays of ranges.
+    let response = send(
+        &mut connection,
+        &[
+            "LCS",
+            "key1",
+            "key2",
+            "IDX",
+            "MINMATCHLEN",
+            "4",
+            "WITHMATCHLEN",
+        ],
+    )
+    .await;
+    let range = |start, end| Frame::Array(vec![Frame::Integer(start), Frame::Integer(end)]);
+    assert_eq!(
+        response,
+        Frame::Array(vec![
+            Frame::Bulk("matches".into()),
+            Frame::Array(vec![Frame::Array(vec![
+                range(4, 7),
+                range(5, 8),
+                Frame::Integer(4)
+            ])]),
+            Frame::Bulk("len".into()),
+            Frame::Integer(6),
+        ])
+    );
+
+    // No byte in common.
+    send(&mut connection, &["SET", "abc", "abc"]).await;
+    send(&mut connection, &["SET", "xyz", "xyz"]).await;
+    let response = send(&mut connection, &["LCS", "abc", "xyz"]).await;
+    assert_eq!(response, "");
+
+    // The subsequence need not be contiguous in either string.
+    send(&mut connection, &["SET", "abcb", "abcb"]).await;
+    send(&mut connection, &["SET", "bdcaba", "bdcaba"]).await;
+    let response = send(&mut connection, &["LCS", "abcb", "bdcaba", "LEN"]).await;
+    assert_eq!(response.to_string(), "3");
+
+    let response = send(&mut connection, &["LCS", "key1", "key2", "LEN", "IDX"]).await;
+    assert!(matches!(response, Frame::Error(_)));
+
+    send(&mut connection, &["ZADD", "zset", "1", "a"]).await;
+    let response = send(&mut connection, &["LCS", "key1", "zset"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.starts_with("WRONGTYPE")));
+
+    // The comparison table must fit within the bulk string limit.
+    send(
+        &mut connection,
+        &["CONFIG", "SET", "proto-max-bulk-len", "64"],
+    )
+    .await;
+    let response = send(&mut connection, &["LCS", "key1", "key2"]).await;
+    assert!(matches!(response, Frame::Error(err) if err.contains("exceeds proto-max-bulk-len")));
+    let response = send(&mut connection, &["LCS", "abc", "xyz"]).await;
+    assert_eq!(response, "");
+}
+
+#[tokio::test]
+async fn time() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let addr = start_server().await;
+    let mut connection = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let response = send(&mut connection, &["TIME"]).await;
+    let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    let parts = match response {
+        Frame::Array(parts) => parts,
+        frame => panic!("unexpected frame: {:?}", frame),
+    };
+    assert_eq!(parts.len(), 2);
+    assert!(parts.iter().all(|part| matches!(part, Frame::Bulk(_))));
+
+    let secs: u64 = parts[0].to_string().parse().unwrap();
+    let micros: u64 = parts[1].to_string().parse().unwrap();
+    assert!(micros < 1_000_000);
+
+    let time = secs * 1_000_000 + micros;
+    assert!(before.as_micros() as u64 <= time && time <= after.as_micros() as u64);
+
+    let response = send(&mut connection, &["TIME", "extra"]).await;
+    assert!(matches!(response, Frame::Error(_)));
+}
+
+/// Subscribing to several channels in one command confirms each channel in
+/// order, with the cumulative number of subscriptions.
+#[tokio::test]
+async fn subscribe_confirms_each_channel() {
+    let addr = start_server().await;
+    let mut publisher = Connection::new(TcpStream::connect(addr).await.unwrap());
+    let mut subscriber = Connection::new(TcpStream::connect(addr).await.unwrap());
+
+    // `send` returns the first confirmation, the others follow.
+    let response = send(&mut subscriber, &["SUBSCRIBE", "a", "b", "c"]).await;
+    assert_eq!(response.to_string(), "subscribe a 1");
+    for expected in ["subscribe b 2", "subscribe c 3"] {
+        let response = subscriber.read_frame().await.unwrap().unwrap();
+        assert_eq!(response.to_string(), expected);
+    }
+
+    // A channel subscribed to again is confirmed without counting twice.
+    let response = send(&mut subscriber, &["SUBSCRIBE", "b", "d"]).await;
+    assert_eq!(response.to_string(), "subscribe b 3");
+    let response = subscriber.read_frame().await.unwrap().unwrap();
+    assert_eq!(response.to_string(), "subscribe d 4");
+
+    // ... and delivers each message once.
+    let response = send(&mut publisher, &["PUBLISH", "b", "hi"]).await;
+    assert_eq!(response.to_string(), "1");
+    let response = subscriber.read_frame().await.unwrap().unwrap();
+    assert_eq!(response.to_string(), "message b hi");
+
+    let response = send(&mut publisher, &["PUBLISH", "d", "bye"]).await;
+    assert_eq!(response.to_string(), "1");
+    let response = subscriber.read_frame().await.unwrap().unwrap();
+    assert_eq!(response.to_string(), "message d bye");
+}
+
+/// Sends `args` as a command and returns the response.
+async fn send(connection: &mut Connection, args: &[&str]) -> Frame {
+    let frame = Frame::Array(
+        args.iter()
+            .map(|arg| Frame::Bulk(arg.to_string().into()))
+            .collect(),
+    );
+    connection.write_frame(&frame).await.unwrap();
+    connection.read_frame().await.unwrap().unwrap()
 }
 
 async fn start_server() -> SocketAddr {
@@ -405,3 +3521,15 @@ async fn start_server() -> SocketAddr {
 
     addr
 }
+
+/// Sets `key` to `value`, which, unlike the arguments of `send`, need not be
+/// valid UTF-8.
+async fn set_bytes(connection: &mut Connection, key: &str, value: &[u8]) {
+    let frame = Frame::Array(vec![
+        Frame::Bulk("SET".into()),
+        Frame::Bulk(key.to_string().into()),
+        Frame::Bulk(value.to_vec().into()),
+    ]);
+    connection.write_frame(&frame).await.unwrap();
+    connection.read_frame().await.unwrap().unwrap();
+}